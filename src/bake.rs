@@ -0,0 +1,611 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+use crate::layer;
+use crate::math::{run_filter, HeightGrid, Sample, Sampler2D};
+use crate::undo::{self, Action, ReflectAction};
+
+mod mesh;
+
+pub const MAX_RESOLUTION: u32 = 16384;
+pub const MIN_RESOLUTION: u32 = 2;
+
+// PLUGIN
+
+pub struct BakePlugin;
+
+impl Plugin for BakePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BakeSettings>()
+            .register_type::<UpdateBakeSettingsAction>()
+            .init_resource::<Bake>()
+            .init_resource::<BakeSettings>();
+        app.add_systems(Update, poll_bake_system.run_if(is_baking));
+    }
+
+    fn finish(&self, app: &mut App) {
+        if !app.is_plugin_added::<undo::UndoPlugin>() {
+            app.add_plugins(undo::UndoPlugin);
+        }
+    }
+}
+
+// RESOURCES
+
+/// The bake that is currently running, if any.
+#[derive(Default, Resource)]
+pub struct Bake {
+    task: Option<BakeTask>,
+}
+
+impl Bake {
+    pub fn is_running(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// Ratio of the work done, between 0.0 and 1.0.
+    pub fn progress(&self) -> Option<f32> {
+        self.task
+            .as_ref()
+            .map(|task| task.progress.load(Ordering::Relaxed) as f32 / task.total.max(1) as f32)
+    }
+}
+
+/// Region, resolution and output format of bakes.
+#[derive(Clone, Debug, Deserialize, PartialEq, Reflect, Resource, Serialize)]
+#[reflect(Resource)]
+pub struct BakeSettings {
+    pub center: Vec2,
+    pub format: BakeFormat,
+    pub mesh: MeshSettings,
+    pub normalization: HeightNormalization,
+    /// Number of pixels on each axis.
+    pub resolution: UVec2,
+    /// Width of the baked region.  Pixels are square, so the height of the
+    /// region follows the aspect ratio of the resolution.
+    pub size: f32,
+}
+
+impl BakeSettings {
+    /// Width and height of the baked region.
+    pub fn extent(&self) -> Vec2 {
+        (self.resolution - UVec2::ONE).as_vec2() * self.spacing()
+    }
+
+    /// Distance between two adjacent pixels.
+    pub fn spacing(&self) -> f32 {
+        self.size / (self.resolution.x - 1) as f32
+    }
+
+    /// Check the settings that [BakeSettings::extent] and the bake rely on.
+    ///
+    /// The UI cannot produce invalid settings, but saved files can be edited
+    /// by hand.
+    pub fn validate(&self) -> Result<(), BakeSettingsError> {
+        if self.resolution.cmplt(UVec2::splat(MIN_RESOLUTION)).any()
+            || self.resolution.cmpgt(UVec2::splat(MAX_RESOLUTION)).any()
+        {
+            return Err(BakeSettingsError::InvalidResolution(self.resolution));
+        }
+        if !(self.size.is_finite() && self.size > 0.0) {
+            return Err(BakeSettingsError::InvalidSize(self.size));
+        }
+        if !(self.mesh.scale.is_finite() && self.mesh.scale > 0.0) {
+            return Err(BakeSettingsError::InvalidMeshScale(self.mesh.scale));
+        }
+        match self.mesh.base_depth {
+            Some(base_depth) if !(base_depth.is_finite() && base_depth >= 0.0) => {
+                return Err(BakeSettingsError::InvalidBaseDepth(base_depth));
+            }
+            _ => (),
+        }
+        match self.normalization {
+            HeightNormalization::Range { max, min }
+                if !(min.is_finite() && max.is_finite() && min < max) =>
+            {
+                Err(BakeSettingsError::InvalidHeightRange { max, min })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Position of the top left pixel.
+    fn origin(&self) -> Vec2 {
+        // Y is inverted.
+        self.center + self.extent() * Vec2::new(-0.5, 0.5)
+    }
+}
+
+impl Default for BakeSettings {
+    fn default() -> Self {
+        Self {
+            center: Vec2::ZERO,
+            format: BakeFormat::default(),
+            mesh: MeshSettings::default(),
+            normalization: HeightNormalization::default(),
+            resolution: UVec2::splat(1025),
+            size: 100.0,
+        }
+    }
+}
+
+// ACTIONS
+
+#[derive(Debug, Reflect)]
+#[reflect(Action)]
+pub struct UpdateBakeSettingsAction {
+    old_value: BakeSettings,
+    new_value: BakeSettings,
+}
+
+impl UpdateBakeSettingsAction {
+    pub fn new(old_value: BakeSettings, new_value: BakeSettings) -> Self {
+        debug_assert!(old_value != new_value);
+        Self {
+            old_value,
+            new_value,
+        }
+    }
+}
+
+impl Action for UpdateBakeSettingsAction {
+    fn apply(&self, world: &mut World) {
+        let mut bake_settings = world.resource_mut::<BakeSettings>();
+        debug_assert!(*bake_settings == self.old_value);
+        *bake_settings = self.new_value.clone();
+    }
+
+    fn revert(&self, world: &mut World) {
+        let reverse_action = Self {
+            old_value: self.new_value.clone(),
+            new_value: self.old_value.clone(),
+        };
+        reverse_action.apply(world);
+    }
+}
+
+// COMMANDS
+
+/// Stop the running bake, nothing is written.
+pub struct CancelBake;
+
+impl Command for CancelBake {
+    fn apply(self, world: &mut World) {
+        if let Some(task) = world.resource_mut::<Bake>().task.take() {
+            info!("Cancelling bake of '{}'", task.path.display());
+            // Dropping a task cancels it.
+            drop(task);
+        }
+    }
+}
+
+/// Bake the layers enabled for baking to the file at the path.
+///
+/// See [BakeSettings].
+pub struct StartBake(pub PathBuf);
+
+impl Command for StartBake {
+    fn apply(self, world: &mut World) {
+        if world.resource::<Bake>().is_running() {
+            error!("Cannot start a new bake while another one is running.");
+            return;
+        }
+        let path = self.0;
+        let settings: BakeSettings = world.resource::<BakeSettings>().clone();
+        let layers: Vec<Box<dyn Sampler2D>> = extract_layers(world);
+        info!("Baking {} layers to '{}'", layers.len(), path.display());
+        let progress = Arc::new(AtomicUsize::new(0));
+        let total: usize = layers.len() * settings.resolution.y as usize;
+        let task: Task<Result<(), BakeError>> = AsyncComputeTaskPool::get().spawn({
+            let path = path.clone();
+            let progress = progress.clone();
+            async move {
+                let height_grid: HeightGrid = bake_heights(&layers, &settings, &progress).await;
+                write_bake(&path, &height_grid, &settings)
+            }
+        });
+        world.resource_mut::<Bake>().task = Some(BakeTask {
+            path,
+            progress,
+            task,
+            total,
+        });
+    }
+}
+
+// SYSTEMS
+
+fn is_baking(bake: Res<Bake>) -> bool {
+    bake.is_running()
+}
+
+fn poll_bake_system(mut bake: ResMut<Bake>) {
+    let Some(result) = bake
+        .task
+        .as_mut()
+        .and_then(|task| block_on(future::poll_once(&mut task.task)))
+    else {
+        return;
+    };
+    let task: BakeTask = bake.task.take().unwrap();
+    match result {
+        Ok(()) => info!("Baked '{}'", task.path.display()),
+        Err(e) => error!(error = &e as &dyn core::error::Error),
+    }
+}
+
+// LIB
+
+#[derive(Debug, Error)]
+pub enum BakeError {
+    #[error("image error: {0}")]
+    ImageError(image::ImageError),
+    #[error("i/o error: {0}")]
+    IoError(std::io::Error),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum BakeSettingsError {
+    #[error("base depth {0} is not zero or a positive number")]
+    InvalidBaseDepth(f32),
+    #[error("height range from {min} to {max} is empty")]
+    InvalidHeightRange { max: f32, min: f32 },
+    #[error("mesh scale {0} is not a positive number")]
+    InvalidMeshScale(f32),
+    #[error("resolution {0} is not between {MIN_RESOLUTION} and {MAX_RESOLUTION}")]
+    InvalidResolution(UVec2),
+    #[error("size {0} is not a positive number")]
+    InvalidSize(f32),
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Reflect, Serialize)]
+pub enum BakeFormat {
+    /// 32-bit float OpenEXR.
+    Exr,
+    /// Binary glTF 2.0 mesh.
+    Glb,
+    /// Wavefront OBJ mesh.
+    Obj,
+    /// 16-bit grayscale PNG, heights are normalized.
+    #[default]
+    Png16,
+    /// Binary STL mesh.
+    Stl,
+    /// 32-bit float TIFF.
+    Tiff,
+}
+
+impl BakeFormat {
+    /// Format of a file with the given extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "exr" => Some(Self::Exr),
+            "glb" => Some(Self::Glb),
+            "obj" => Some(Self::Obj),
+            "png" => Some(Self::Png16),
+            "stl" => Some(Self::Stl),
+            "tif" | "tiff" => Some(Self::Tiff),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Exr => "exr",
+            Self::Glb => "glb",
+            Self::Obj => "obj",
+            Self::Png16 => "png",
+            Self::Stl => "stl",
+            Self::Tiff => "tif",
+        }
+    }
+
+    /// Whether the height field is exported as a mesh, instead of an image.
+    pub fn is_mesh(&self) -> bool {
+        matches!(self, Self::Glb | Self::Obj | Self::Stl)
+    }
+}
+
+impl ToString for BakeFormat {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Exr => "EXR (32-bit float)",
+            Self::Glb => "glTF (binary)",
+            Self::Obj => "OBJ",
+            Self::Png16 => "PNG (16-bit)",
+            Self::Stl => "STL (binary)",
+            Self::Tiff => "TIFF (32-bit float)",
+        }
+        .to_owned()
+    }
+}
+
+/// How heights are mapped to the range of integer formats.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Reflect, Serialize)]
+pub enum HeightNormalization {
+    /// The lowest and the highest baked heights become black and white.
+    #[default]
+    Fit,
+    /// `min` and `max` become black and white, heights outside are clamped.
+    Range { max: f32, min: f32 },
+}
+
+impl HeightNormalization {
+    /// Heights mapped to `0..=u16::MAX`.
+    fn normalize(&self, heights: &[f32]) -> Vec<u16> {
+        let (min, max): (f32, f32) = match self {
+            Self::Fit => heights
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), h| {
+                    (min.min(*h), max.max(*h))
+                }),
+            Self::Range { max, min } => (*min, *max),
+        };
+        heights
+            .iter()
+            .map(|h| {
+                let t: f32 = if max > min {
+                    ((h - min) / (max - min)).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                (t * u16::MAX as f32).round() as u16
+            })
+            .collect()
+    }
+}
+
+/// Settings for the mesh formats.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Reflect, Serialize)]
+pub struct MeshSettings {
+    /// Depth of the base below the lowest point of the terrain.  Terrain is
+    /// not closed if this is `None`.
+    pub base_depth: Option<f32>,
+    /// Size of a world unit in the exported mesh.
+    pub scale: f32,
+}
+
+impl Default for MeshSettings {
+    fn default() -> Self {
+        Self {
+            base_depth: None,
+            scale: 1.0,
+        }
+    }
+}
+
+struct BakeTask {
+    path: PathBuf,
+    /// Number of rows sampled so far, for all layers.
+    progress: Arc<AtomicUsize>,
+    task: Task<Result<(), BakeError>>,
+    total: usize,
+}
+
+/// Bake the heights of the layers enabled for baking, blocking until done.
+///
+/// This is meant for the command line, [StartBake] bakes in the background.
+pub fn bake_now(world: &mut World, settings: &BakeSettings) -> HeightGrid {
+    let layers: Vec<Box<dyn Sampler2D>> = extract_layers(world);
+    block_on(bake_heights(&layers, settings, &AtomicUsize::new(0)))
+}
+
+/// Deserialize bake settings, rejecting the ones [BakeSettings::validate]
+/// rejects.
+pub fn deserialize_bake_settings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BakeSettings, D::Error> {
+    let settings: BakeSettings = BakeSettings::deserialize(deserializer)?;
+    match settings.validate() {
+        Ok(()) => Ok(settings),
+        Err(e) => Err(de::Error::custom(e)),
+    }
+}
+
+/// Sample layers one at a time over the whole region.
+///
+/// `progress` is incremented after each row of each layer.
+async fn bake_heights(
+    layers: &[Box<dyn Sampler2D>],
+    settings: &BakeSettings,
+    progress: &AtomicUsize,
+) -> HeightGrid {
+    let size: UVec2 = settings.resolution;
+    let mut height_grid = HeightGrid::new(
+        settings.origin(),
+        settings.spacing(),
+        size,
+        vec![0.0; size.x as usize * size.y as usize],
+    );
+    let mut samples: Vec<Sample> = vec![Sample::default(); height_grid.heights().len()];
+    for layer in layers.iter() {
+        if layer.is_filter() {
+            // Report the progress of the filter in rows, like the other layers.
+            let mut rows_done: usize = 0;
+            let mut report_rows = |ratio: f32| {
+                let rows: usize = ((ratio * size.y as f32) as usize).min(size.y as usize);
+                progress.fetch_add(rows.saturating_sub(rows_done), Ordering::Relaxed);
+                rows_done = rows_done.max(rows);
+            };
+            if let Some(filter) = layer.filter(&height_grid, &samples) {
+                let filtered: HeightGrid = run_filter(filter, &mut report_rows).await;
+                for (sample, height) in samples.iter_mut().zip(filtered.into_heights()) {
+                    sample.set_height(height);
+                }
+            }
+            report_rows(1.0);
+        } else {
+            for y in 0..size.y {
+                for x in 0..size.x {
+                    let idx: usize = height_grid.index(x, y);
+                    let gradient: Option<Vec2> =
+                        layer.needs_gradient().then(|| height_grid.gradient(idx));
+                    let layer_sample = layer.sample_with_gradient(
+                        height_grid.position(idx),
+                        &samples[idx],
+                        gradient,
+                    );
+                    samples[idx].mix_in_place(&layer_sample);
+                }
+                progress.fetch_add(1, Ordering::Relaxed);
+                // Yield control at every row, so the bake can be cancelled.
+                future::yield_now().await;
+            }
+        }
+        // Following layers see the heights of the previous ones.
+        for (height, sample) in height_grid.heights_mut().iter_mut().zip(samples.iter()) {
+            *height = sample.height();
+        }
+        future::yield_now().await;
+    }
+    height_grid
+}
+
+/// Write baked heights to `path` in the format of `settings`.
+pub fn write_bake(
+    path: &Path,
+    height_grid: &HeightGrid,
+    settings: &BakeSettings,
+) -> Result<(), BakeError> {
+    if settings.format.is_mesh() {
+        let terrain_mesh = mesh::TerrainMesh::new(height_grid, settings.mesh.base_depth);
+        let scale: f32 = settings.mesh.scale;
+        match settings.format {
+            BakeFormat::Glb => mesh::write_glb(path, &terrain_mesh, scale),
+            BakeFormat::Obj => mesh::write_obj(path, &terrain_mesh, scale),
+            BakeFormat::Stl => mesh::write_stl(path, &terrain_mesh, scale),
+            _ => unreachable!("{:?} is not a mesh format.", settings.format),
+        }
+        .map_err(|e| BakeError::IoError(e))
+    } else {
+        write_height_map(path, height_grid, settings.format, settings.normalization)
+    }
+}
+
+fn extract_layers(world: &mut World) -> Vec<Box<dyn Sampler2D>> {
+    layer::extract_samplers(world, |layer| layer.enable_baking)
+        .into_iter()
+        .map(|(_, layer)| layer)
+        .collect()
+}
+
+/// Write heights in `format`.
+///
+/// Float formats store the height in all three color channels.
+fn write_height_map(
+    path: &Path,
+    height_grid: &HeightGrid,
+    format: BakeFormat,
+    normalization: HeightNormalization,
+) -> Result<(), BakeError> {
+    let size: UVec2 = height_grid.size();
+    match format {
+        BakeFormat::Exr | BakeFormat::Tiff => {
+            let pixels: Vec<f32> = height_grid
+                .heights()
+                .iter()
+                .flat_map(|h| [*h, *h, *h])
+                .collect();
+            image::Rgb32FImage::from_raw(size.x, size.y, pixels)
+                .expect("Number of pixels does not match the image size.")
+                .save_with_format(
+                    path,
+                    if format == BakeFormat::Exr {
+                        image::ImageFormat::OpenExr
+                    } else {
+                        image::ImageFormat::Tiff
+                    },
+                )
+        }
+        BakeFormat::Png16 => image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(
+            size.x,
+            size.y,
+            normalization.normalize(height_grid.heights()),
+        )
+        .expect("Number of pixels does not match the image size.")
+        .save_with_format(path, image::ImageFormat::Png),
+        BakeFormat::Glb | BakeFormat::Obj | BakeFormat::Stl => {
+            unreachable!("{:?} is not an image format.", format)
+        }
+    }
+    .map_err(|e| BakeError::ImageError(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bake_samples_a_rectangular_region() {
+        let settings = BakeSettings {
+            center: Vec2::new(10.0, 20.0),
+            resolution: UVec2::new(5, 3),
+            size: 8.0,
+            ..default()
+        };
+        assert_eq!(settings.spacing(), 2.0);
+        assert_eq!(settings.extent(), Vec2::new(8.0, 4.0));
+        let layers: Vec<Box<dyn Sampler2D>> = vec![Box::new(layer::HeightMap::Constant(7.0))];
+        let progress = AtomicUsize::new(0);
+        let height_grid: HeightGrid = block_on(bake_heights(&layers, &settings, &progress));
+        assert_eq!(height_grid.size(), settings.resolution);
+        assert_eq!(height_grid.position(0), Vec2::new(6.0, 22.0));
+        assert_eq!(height_grid.position(14), Vec2::new(14.0, 18.0));
+        assert!(height_grid.heights().iter().all(|h| *h == 7.0));
+        assert_eq!(progress.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn bake_reports_the_progress_of_filters() {
+        let settings = BakeSettings {
+            resolution: UVec2::new(9, 7),
+            ..default()
+        };
+        let layers: Vec<Box<dyn Sampler2D>> = vec![
+            Box::new(layer::HeightMap::Constant(7.0)),
+            Box::new(layer::HeightMap::hydraulic_erosion()),
+        ];
+        let progress = AtomicUsize::new(0);
+        block_on(bake_heights(&layers, &settings, &progress));
+        assert_eq!(progress.load(Ordering::Relaxed), 2 * 7);
+    }
+
+    #[test]
+    fn heights_are_normalized_to_the_range() {
+        let heights = [-10.0, 0.0, 50.0, 100.0, 200.0];
+        assert_eq!(
+            HeightNormalization::Range {
+                max: 100.0,
+                min: 0.0
+            }
+            .normalize(&heights),
+            vec![0, 0, 32768, u16::MAX, u16::MAX]
+        );
+        assert_eq!(
+            HeightNormalization::Fit.normalize(&[0.0, 25.0, 50.0, 100.0]),
+            vec![0, 16384, 32768, u16::MAX]
+        );
+    }
+}
@@ -0,0 +1,359 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::math::HeightGrid;
+
+const GLB_MAGIC: u32 = 0x46546C67;
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLTF_ARRAY_BUFFER: u32 = 34962;
+const GLTF_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const GLTF_FLOAT: u32 = 5126;
+const GLTF_UNSIGNED_INT: u32 = 5125;
+const STL_HEADER_SIZE: usize = 80;
+
+/// Triangulated height field, in world units and Z-up.
+///
+/// UVs map the region to `0..=1` with V increasing downwards, the same way
+/// the baked images are laid out.
+#[derive(Debug, Default)]
+pub struct TerrainMesh {
+    indices: Vec<u32>,
+    normals: Vec<Vec3>,
+    positions: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+}
+
+impl TerrainMesh {
+    /// Triangulate `height_grid` with smooth normals.
+    ///
+    /// If `base_depth` is given, the terrain is closed with a skirt around
+    /// its edges and a flat base `base_depth` below its lowest point, so
+    /// that it can be 3D printed.
+    pub fn new(height_grid: &HeightGrid, base_depth: Option<f32>) -> Self {
+        let size: UVec2 = height_grid.size();
+        let uv = |x: u32, y: u32| {
+            Vec2::new(
+                x as f32 / (size.x - 1) as f32,
+                y as f32 / (size.y - 1) as f32,
+            )
+        };
+        let mut mesh = Self::default();
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let idx: usize = height_grid.index(x, y);
+                let gradient: Vec2 = height_grid.gradient(idx);
+                mesh.positions
+                    .push(height_grid.position(idx).extend(height_grid.heights()[idx]));
+                mesh.normals
+                    .push(Vec3::new(-gradient.x, -gradient.y, 1.0).normalize());
+                mesh.uvs.push(uv(x, y));
+            }
+        }
+        for y in 0..size.y - 1 {
+            for x in 0..size.x - 1 {
+                // Rows are ordered from top to bottom.
+                let top_left = height_grid.index(x, y) as u32;
+                let top_right = height_grid.index(x + 1, y) as u32;
+                let bottom_left = height_grid.index(x, y + 1) as u32;
+                let bottom_right = height_grid.index(x + 1, y + 1) as u32;
+                mesh.indices.extend([top_left, bottom_left, top_right]);
+                mesh.indices.extend([top_right, bottom_left, bottom_right]);
+            }
+        }
+
+        if let Some(base_depth) = base_depth {
+            let base: f32 = height_grid
+                .heights()
+                .iter()
+                .fold(f32::INFINITY, |min, h| min.min(*h))
+                - base_depth;
+            let (w, h) = (size.x - 1, size.y - 1);
+            // Sides of the perimeter, counter-clockwise when seen from above,
+            // with the outward direction of each.
+            let sides: [(Vec<UVec2>, Vec3); 4] = [
+                ((0..=w).map(|x| UVec2::new(x, h)).collect(), Vec3::NEG_Y),
+                ((0..=h).rev().map(|y| UVec2::new(w, y)).collect(), Vec3::X),
+                ((0..=w).rev().map(|x| UVec2::new(x, 0)).collect(), Vec3::Y),
+                ((0..=h).map(|y| UVec2::new(0, y)).collect(), Vec3::NEG_X),
+            ];
+            for (points, normal) in sides.iter() {
+                let first = mesh.positions.len() as u32;
+                for point in points.iter() {
+                    let top: Vec3 = mesh.positions[height_grid.index(point.x, point.y)];
+                    mesh.positions.push(top);
+                    mesh.positions.push(top.with_z(base));
+                    mesh.normals.extend([*normal, *normal]);
+                    mesh.uvs
+                        .extend([uv(point.x, point.y), uv(point.x, point.y)]);
+                }
+                for idx in 0..points.len() as u32 - 1 {
+                    let (top_p, base_p) = (first + idx * 2, first + idx * 2 + 1);
+                    let (top_q, base_q) = (top_p + 2, base_p + 2);
+                    mesh.indices.extend([base_p, base_q, top_q]);
+                    mesh.indices.extend([base_p, top_q, top_p]);
+                }
+            }
+            // Base is a fan around its center, so that its edges match the
+            // vertices of the skirt.
+            let center = mesh.positions.len() as u32;
+            mesh.positions.push(
+                height_grid
+                    .position(height_grid.index(w / 2, h / 2))
+                    .lerp(
+                        height_grid.position(height_grid.index(w - w / 2, h - h / 2)),
+                        0.5,
+                    )
+                    .extend(base),
+            );
+            mesh.normals.push(Vec3::NEG_Z);
+            mesh.uvs.push(Vec2::splat(0.5));
+            for (points, _) in sides.iter() {
+                let first = mesh.positions.len() as u32;
+                for point in points.iter() {
+                    mesh.positions
+                        .push(mesh.positions[height_grid.index(point.x, point.y)].with_z(base));
+                    mesh.normals.push(Vec3::NEG_Z);
+                    mesh.uvs.push(uv(point.x, point.y));
+                }
+                for idx in 0..points.len() as u32 - 1 {
+                    // Seen from below, so the winding is reversed.
+                    mesh.indices.extend([center, first + idx + 1, first + idx]);
+                }
+            }
+        }
+        mesh
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Vertex positions scaled by `scale`, Y-up.
+    fn positions_y_up(&self, scale: f32) -> impl Iterator<Item = Vec3> + '_ {
+        self.positions.iter().map(move |p| z_up_to_y_up(*p) * scale)
+    }
+
+    fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+    }
+}
+
+/// Write `mesh` as binary glTF 2.0, Y-up.
+pub fn write_glb(path: &Path, mesh: &TerrainMesh, scale: f32) -> io::Result<()> {
+    let mut bin: Vec<u8> = vec![];
+    let (mut min, mut max) = (Vec3::INFINITY, Vec3::NEG_INFINITY);
+    for p in mesh.positions_y_up(scale) {
+        min = min.min(p);
+        max = max.max(p);
+        bin.extend(p.to_array().iter().flat_map(|v| v.to_le_bytes()));
+    }
+    let normals_offset: usize = bin.len();
+    for n in mesh.normals.iter() {
+        bin.extend(
+            z_up_to_y_up(*n)
+                .to_array()
+                .iter()
+                .flat_map(|v| v.to_le_bytes()),
+        );
+    }
+    let uvs_offset: usize = bin.len();
+    for uv in mesh.uvs.iter() {
+        bin.extend(uv.to_array().iter().flat_map(|v| v.to_le_bytes()));
+    }
+    let indices_offset: usize = bin.len();
+    for idx in mesh.indices.iter() {
+        bin.extend(idx.to_le_bytes());
+    }
+    pad(&mut bin, 0);
+
+    let vertex_count: usize = mesh.positions.len();
+    let mut json: Vec<u8> = format!(
+        concat!(
+            r#"{{"asset":{{"version":"2.0","generator":"{generator}"}},"#,
+            r#""scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"#,
+            r#""meshes":[{{"primitives":[{{"attributes":{{"POSITION":0,"NORMAL":1,"TEXCOORD_0":2}},"indices":3}}]}}],"#,
+            r#""buffers":[{{"byteLength":{buffer_length}}}],"#,
+            r#""bufferViews":["#,
+            r#"{{"buffer":0,"byteOffset":0,"byteLength":{normals_offset},"target":{array_buffer}}},"#,
+            r#"{{"buffer":0,"byteOffset":{normals_offset},"byteLength":{normals_length},"target":{array_buffer}}},"#,
+            r#"{{"buffer":0,"byteOffset":{uvs_offset},"byteLength":{uvs_length},"target":{array_buffer}}},"#,
+            r#"{{"buffer":0,"byteOffset":{indices_offset},"byteLength":{indices_length},"target":{element_array_buffer}}}],"#,
+            r#""accessors":["#,
+            r#"{{"bufferView":0,"componentType":{float},"count":{vertex_count},"type":"VEC3","min":[{min_x},{min_y},{min_z}],"max":[{max_x},{max_y},{max_z}]}},"#,
+            r#"{{"bufferView":1,"componentType":{float},"count":{vertex_count},"type":"VEC3"}},"#,
+            r#"{{"bufferView":2,"componentType":{float},"count":{vertex_count},"type":"VEC2"}},"#,
+            r#"{{"bufferView":3,"componentType":{unsigned_int},"count":{index_count},"type":"SCALAR"}}]}}"#,
+        ),
+        generator = env!("CARGO_PKG_NAME"),
+        buffer_length = bin.len(),
+        normals_offset = normals_offset,
+        normals_length = uvs_offset - normals_offset,
+        uvs_offset = uvs_offset,
+        uvs_length = indices_offset - uvs_offset,
+        indices_offset = indices_offset,
+        indices_length = mesh.indices.len() * 4,
+        array_buffer = GLTF_ARRAY_BUFFER,
+        element_array_buffer = GLTF_ELEMENT_ARRAY_BUFFER,
+        float = GLTF_FLOAT,
+        unsigned_int = GLTF_UNSIGNED_INT,
+        vertex_count = vertex_count,
+        index_count = mesh.indices.len(),
+        min_x = min.x,
+        min_y = min.y,
+        min_z = min.z,
+        max_x = max.x,
+        max_y = max.y,
+        max_z = max.z,
+    )
+    .into_bytes();
+    pad(&mut json, b' ');
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    let total_length: usize = 12 + 8 + json.len() + 8 + bin.len();
+    for word in [GLB_MAGIC, GLB_VERSION, total_length as u32] {
+        writer.write_all(&word.to_le_bytes())?;
+    }
+    for (chunk_type, chunk) in [(GLB_CHUNK_JSON, &json), (GLB_CHUNK_BIN, &bin)] {
+        writer.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        writer.write_all(&chunk_type.to_le_bytes())?;
+        writer.write_all(chunk)?;
+    }
+    writer.flush()
+}
+
+/// Write `mesh` as Wavefront OBJ, Y-up.
+pub fn write_obj(path: &Path, mesh: &TerrainMesh, scale: f32) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "# {}", env!("CARGO_PKG_NAME"))?;
+    for p in mesh.positions_y_up(scale) {
+        writeln!(writer, "v {} {} {}", p.x, p.y, p.z)?;
+    }
+    for uv in mesh.uvs.iter() {
+        // V increases upwards in OBJ.
+        writeln!(writer, "vt {} {}", uv.x, 1.0 - uv.y)?;
+    }
+    for n in mesh.normals.iter().map(|n| z_up_to_y_up(*n)) {
+        writeln!(writer, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+    for [a, b, c] in mesh.triangles() {
+        // Indices are 1-based in OBJ.
+        let (a, b, c) = (a + 1, b + 1, c + 1);
+        writeln!(writer, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+    writer.flush()
+}
+
+/// Write `mesh` as binary STL, Z-up.
+///
+/// STL has no normals per vertex or UVs, face normals are written instead.
+pub fn write_stl(path: &Path, mesh: &TerrainMesh, scale: f32) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut header = [0u8; STL_HEADER_SIZE];
+    let name: &[u8] = env!("CARGO_PKG_NAME").as_bytes();
+    header[..name.len()].copy_from_slice(name);
+    writer.write_all(&header)?;
+    writer.write_all(&(mesh.triangle_count() as u32).to_le_bytes())?;
+    for [a, b, c] in mesh.triangles() {
+        let (a, b, c) = (
+            mesh.positions[a] * scale,
+            mesh.positions[b] * scale,
+            mesh.positions[c] * scale,
+        );
+        let normal: Vec3 = (b - a).cross(c - a).normalize_or_zero();
+        for v in [normal, a, b, c] {
+            for component in v.to_array() {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+        }
+        // Attribute byte count.
+        writer.write_all(&0u16.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Pad `bytes` to a multiple of 4 bytes with `padding`, as GLB chunks
+/// require.
+fn pad(bytes: &mut Vec<u8>, padding: u8) {
+    while bytes.len() % 4 != 0 {
+        bytes.push(padding);
+    }
+}
+
+fn z_up_to_y_up(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, v.z, -v.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_grid() -> HeightGrid {
+        HeightGrid::new(Vec2::new(-1.0, 1.0), 1.0, UVec2::new(3, 3), vec![5.0; 9])
+    }
+
+    #[test]
+    fn terrain_faces_up() {
+        let mesh = TerrainMesh::new(&flat_grid(), None);
+        assert_eq!(mesh.positions.len(), 9);
+        assert_eq!(mesh.triangle_count(), 8);
+        for [a, b, c] in mesh.triangles() {
+            let normal: Vec3 = (mesh.positions[b] - mesh.positions[a])
+                .cross(mesh.positions[c] - mesh.positions[a]);
+            assert!(normal.z > 0.0);
+        }
+        assert!(mesh.normals.iter().all(|n| *n == Vec3::Z));
+        assert_eq!(mesh.uvs[8], Vec2::ONE);
+    }
+
+    #[test]
+    fn base_closes_the_terrain() {
+        let mesh = TerrainMesh::new(&flat_grid(), Some(2.0));
+        // Every edge of a closed mesh is shared by exactly two triangles,
+        // once in each direction.
+        let quantize = |v: Vec3| (v * 1000.0).round().as_ivec3().to_array();
+        let mut edges: Vec<([i32; 3], [i32; 3])> = vec![];
+        for [a, b, c] in mesh.triangles() {
+            for (p, q) in [(a, b), (b, c), (c, a)] {
+                edges.push((quantize(mesh.positions[p]), quantize(mesh.positions[q])));
+            }
+        }
+        for (p, q) in edges.iter() {
+            assert_eq!(edges.iter().filter(|edge| **edge == (*q, *p)).count(), 1);
+        }
+        let lowest: f32 = mesh
+            .positions
+            .iter()
+            .fold(f32::INFINITY, |min, p| min.min(p.z));
+        assert_eq!(lowest, 3.0);
+        // Skirt and base faces point outwards.
+        let center: Vec3 = Vec3::new(0.0, 0.0, 4.0);
+        for [a, b, c] in mesh.triangles().skip(8) {
+            let (a, b, c) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+            let normal: Vec3 = (b - a).cross(c - a);
+            assert!(normal.dot((a + b + c) / 3.0 - center) > 0.0);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::id::BiomeId;
+use crate::math::{slope_angle, Channel, Sample};
+use crate::undo::{self, Action, ReflectAction};
+
+/// Color of the samples that do not match any biome.
+pub const UNCLASSIFIED_COLOR: Color = Color::WHITE;
+
+// PLUGIN
+
+pub struct BiomePlugin;
+
+impl Plugin for BiomePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BiomeTable>()
+            .init_resource::<BiomeTable>();
+    }
+
+    fn finish(&self, app: &mut App) {
+        if !app.is_plugin_added::<undo::UndoPlugin>() {
+            app.add_plugins(undo::UndoPlugin);
+        }
+    }
+}
+
+// RESOURCES
+
+/// Biomes of the project.
+///
+/// Each sample is classified as the first biome whose rules it matches, so
+/// the biomes higher in the table take precedence.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Reflect, Resource, Serialize)]
+#[reflect(Resource)]
+pub struct BiomeTable {
+    biomes: Vec<Biome>,
+}
+
+impl BiomeTable {
+    pub fn new(biomes: Vec<Biome>) -> Self {
+        Self { biomes }
+    }
+
+    pub fn biomes(&self) -> &[Biome] {
+        &self.biomes
+    }
+
+    /// Index of the biome `sample` belongs to.
+    ///
+    /// `gradient` is the gradient of the terrain at the sample.
+    pub fn classify(&self, sample: &Sample, gradient: Vec2) -> Option<usize> {
+        let slope: f32 = slope_angle(gradient);
+        self.biomes
+            .iter()
+            .position(|biome| biome.matches(sample, slope))
+    }
+
+    /// Color of the biome `sample` belongs to.
+    pub fn classify_color(&self, sample: &Sample, gradient: Vec2) -> Color {
+        self.classify(sample, gradient)
+            .map(|idx| self.biomes[idx].color)
+            .unwrap_or(UNCLASSIFIED_COLOR)
+    }
+
+    /// Copy of `sample` with the channel of the biome it belongs to set.
+    pub fn classify_sample(&self, sample: &Sample, gradient: Vec2) -> Sample {
+        let mut classified: Sample = sample.clone();
+        if let Some(idx) = self.classify(sample, gradient) {
+            classified.set_channel(Channel::Biome(self.biomes[idx].id()), 1.0);
+        }
+        classified
+    }
+
+    pub fn get(&self, id: BiomeId) -> Option<&Biome> {
+        self.biomes.iter().find(|biome| biome.id() == id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.biomes.is_empty()
+    }
+}
+
+// ACTIONS
+
+#[derive(Debug, Reflect)]
+#[reflect(Action)]
+pub struct UpdateBiomeTableAction {
+    old_value: BiomeTable,
+    new_value: BiomeTable,
+}
+
+impl UpdateBiomeTableAction {
+    pub fn new(old_value: BiomeTable, new_value: BiomeTable) -> Self {
+        debug_assert!(old_value != new_value);
+        Self {
+            old_value,
+            new_value,
+        }
+    }
+}
+
+impl Action for UpdateBiomeTableAction {
+    fn apply(&self, world: &mut World) {
+        let mut biome_table = world.resource_mut::<BiomeTable>();
+        debug_assert!(*biome_table == self.old_value);
+        *biome_table = self.new_value.clone();
+    }
+
+    fn revert(&self, world: &mut World) {
+        let reverse_action = Self {
+            old_value: self.new_value.clone(),
+            new_value: self.old_value.clone(),
+        };
+        reverse_action.apply(world);
+    }
+}
+
+// LIB
+
+#[derive(Debug, Error)]
+pub enum BiomeError {
+    #[error("image error: {0}")]
+    ImageError(image::ImageError),
+    #[error("too many biomes, an index map can hold at most {} biomes", u8::MAX)]
+    TooManyBiomes,
+}
+
+/// A rule based terrain classification.
+///
+/// Rules that are `None` match every sample.  Slope is in degrees, moisture
+/// and temperature are read from the channels of the sample.
+#[derive(Clone, Debug, Deserialize, PartialEq, Reflect, Serialize)]
+pub struct Biome {
+    pub color: Color,
+    pub height: Option<BiomeRange>,
+    id: BiomeId,
+    pub moisture: Option<BiomeRange>,
+    pub name: String,
+    pub slope: Option<BiomeRange>,
+    pub temperature: Option<BiomeRange>,
+}
+
+impl Biome {
+    pub fn new<T: Into<String>>(name: T, color: Color) -> Self {
+        Self {
+            color,
+            height: None,
+            id: BiomeId::now_v7(),
+            moisture: None,
+            name: name.into(),
+            slope: None,
+            temperature: None,
+        }
+    }
+
+    pub fn id(&self) -> BiomeId {
+        self.id
+    }
+
+    fn matches(&self, sample: &Sample, slope: f32) -> bool {
+        let matches = |range: &Option<BiomeRange>, value: f32| {
+            range.map(|range| range.contains(value)).unwrap_or(true)
+        };
+        matches(&self.height, sample.height())
+            && matches(&self.slope, slope)
+            && matches(
+                &self.moisture,
+                sample.channel(&Channel::Moisture).unwrap_or(0.0),
+            )
+            && matches(
+                &self.temperature,
+                sample.channel(&Channel::Temperature).unwrap_or(0.0),
+            )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Reflect, Serialize)]
+pub struct BiomeRange {
+    pub max: f32,
+    pub min: f32,
+}
+
+impl BiomeRange {
+    pub fn new(min: f32, max: f32) -> Self {
+        Self { max, min }
+    }
+
+    pub fn contains(&self, value: f32) -> bool {
+        value >= self.min && value <= self.max
+    }
+}
+
+/// Write biome indices as an 8-bit grayscale image.
+///
+/// Rows of `indices` are ordered from top to bottom.  Pixel values are
+/// 1-based indices into the biome table, unclassified pixels are 0.
+pub fn write_index_map(
+    path: &Path,
+    size: UVec2,
+    indices: &[Option<usize>],
+) -> Result<(), BiomeError> {
+    assert_eq!(indices.len(), size.x as usize * size.y as usize);
+    let pixels: Vec<u8> = indices
+        .iter()
+        .map(|idx| match idx {
+            Some(idx) => u8::try_from(idx + 1).map_err(|_| BiomeError::TooManyBiomes),
+            None => Ok(0),
+        })
+        .collect::<Result<_, _>>()?;
+    image::GrayImage::from_raw(size.x, size.y, pixels)
+        .expect("Number of pixels does not match the image size.")
+        .save(path)
+        .map_err(|e| BiomeError::ImageError(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Alpha;
+
+    #[test]
+    fn biomes_higher_in_the_table_take_precedence() {
+        let snow = Biome {
+            height: Some(BiomeRange::new(100.0, 1000.0)),
+            ..Biome::new("Snow", Color::WHITE)
+        };
+        let cliff = Biome {
+            slope: Some(BiomeRange::new(45.0, 90.0)),
+            ..Biome::new("Cliff", Color::BLACK)
+        };
+        let wetland = Biome {
+            moisture: Some(BiomeRange::new(0.5, 1.0)),
+            ..Biome::new("Wetland", Color::srgb(0.0, 0.5, 0.0))
+        };
+        let biome_table = BiomeTable::new(vec![snow, cliff, wetland]);
+
+        let flat = Vec2::ZERO;
+        let steep = Vec2::new(2.0, 0.0);
+        let low = Sample::new(10.0, Alpha::Opaque);
+        let high = Sample::new(200.0, Alpha::Opaque);
+        let wet = {
+            let mut sample = low.clone();
+            sample.set_channel(Channel::Moisture, 0.75);
+            sample
+        };
+        assert_eq!(biome_table.classify(&low, flat), None);
+        assert_eq!(biome_table.classify(&high, steep), Some(0));
+        assert_eq!(biome_table.classify(&low, steep), Some(1));
+        assert_eq!(biome_table.classify(&wet, steep), Some(1));
+        assert_eq!(biome_table.classify(&wet, flat), Some(2));
+
+        let classified: Sample = biome_table.classify_sample(&wet, flat);
+        assert_eq!(
+            classified.channel(&Channel::Biome(biome_table.biomes()[2].id())),
+            Some(1.0)
+        );
+    }
+}
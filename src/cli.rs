@@ -0,0 +1,368 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::bake;
+use crate::biome;
+use crate::layer;
+use crate::math::HeightGrid;
+use crate::session::save;
+
+pub const BAKE_COMMAND: &str = "bake";
+const BAKE_USAGE: &str = "\
+Usage: yer bake <PROJECT> --output <PATH>... [OPTIONS]
+
+Bake the layers of PROJECT that are enabled for baking.  Format of each output
+is chosen by its extension: png (16-bit), exr, tif, glb, obj or stl.
+
+Options:
+  -o, --output <PATH>          Output file, can be given more than once.
+      --center <X>,<Y>         Center of the baked region.
+      --size <SIZE>            Width of the baked region.
+      --resolution <W>x<H>     Number of pixels, or vertices, on each axis.
+      --height-range <MIN>,<MAX>
+                               Heights mapped to black and white in PNGs.
+                               Lowest and highest heights are used otherwise.
+      --scale <SCALE>          Size of a world unit in meshes.
+      --base <DEPTH>           Close meshes with a base DEPTH below the
+                               lowest point.
+  -h, --help                   Print this message.
+
+Options that are not given default to the bake settings of the project.";
+const EXIT_BAKE_FAILED: u8 = 4;
+const EXIT_LOAD_FAILED: u8 = 3;
+const EXIT_USAGE: u8 = 2;
+
+// LIB
+
+#[derive(Debug, Error, PartialEq)]
+enum CliError {
+    #[error("invalid value '{1}' for '{0}'")]
+    InvalidValue(String, String),
+    #[error("missing value for '{0}'")]
+    MissingValue(String),
+    #[error("no output is given")]
+    NoOutput,
+    #[error("no project file is given")]
+    NoProject,
+    #[error("unknown option '{0}'")]
+    UnknownOption(String),
+    #[error("unknown output format of '{0}'")]
+    UnknownFormat(String),
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct BakeArgs {
+    base_depth: Option<f32>,
+    center: Option<Vec2>,
+    height_range: Option<Vec2>,
+    outputs: Vec<(PathBuf, bake::BakeFormat)>,
+    project: PathBuf,
+    resolution: Option<UVec2>,
+    scale: Option<f32>,
+    size: Option<f32>,
+}
+
+impl BakeArgs {
+    fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Self>, CliError> {
+        let mut bake_args = Self::default();
+        let mut project: Option<PathBuf> = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| CliError::MissingValue(arg.clone()))
+            };
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-o" | "--output" => {
+                    let path = PathBuf::from(value()?);
+                    let format = path
+                        .extension()
+                        .and_then(|extension| extension.to_str())
+                        .and_then(bake::BakeFormat::from_extension)
+                        .ok_or_else(|| CliError::UnknownFormat(path.display().to_string()))?;
+                    bake_args.outputs.push((path, format));
+                }
+                "--base" => bake_args.base_depth = Some(parse_non_negative(&arg, &value()?)?),
+                "--center" => {
+                    bake_args.center = Some(parse_pair::<f32>(&arg, &value()?, ',')?.into())
+                }
+                "--height-range" => {
+                    let value: String = value()?;
+                    let [min, max] = parse_pair::<f32>(&arg, &value, ',')?;
+                    // Also rejects NaN.
+                    if !(min.is_finite() && max.is_finite() && min < max) {
+                        return Err(CliError::InvalidValue(arg, value));
+                    }
+                    bake_args.height_range = Some(Vec2::new(min, max));
+                }
+                "--resolution" => {
+                    let resolution: UVec2 = parse_pair::<u32>(&arg, &value()?, 'x')?.into();
+                    if resolution.cmplt(UVec2::splat(bake::MIN_RESOLUTION)).any()
+                        || resolution.cmpgt(UVec2::splat(bake::MAX_RESOLUTION)).any()
+                    {
+                        return Err(CliError::InvalidValue(
+                            arg,
+                            format!("{}x{}", resolution.x, resolution.y),
+                        ));
+                    }
+                    bake_args.resolution = Some(resolution);
+                }
+                "--scale" => bake_args.scale = Some(parse_positive(&arg, &value()?)?),
+                "--size" => bake_args.size = Some(parse_positive(&arg, &value()?)?),
+                _ if arg.starts_with('-') => return Err(CliError::UnknownOption(arg)),
+                _ if project.is_none() => project = Some(PathBuf::from(arg)),
+                _ => return Err(CliError::UnknownOption(arg)),
+            }
+        }
+        bake_args.project = project.ok_or(CliError::NoProject)?;
+        if bake_args.outputs.is_empty() {
+            return Err(CliError::NoOutput);
+        }
+        Ok(Some(bake_args))
+    }
+
+    /// Settings for the project loaded into `world`, with the overrides.
+    ///
+    /// Overrides are checked when they are parsed, but the settings of the
+    /// project may still be invalid.
+    fn settings(&self, world: &World) -> Result<bake::BakeSettings, bake::BakeSettingsError> {
+        let mut settings: bake::BakeSettings = world
+            .get_resource::<bake::BakeSettings>()
+            .cloned()
+            .unwrap_or_default();
+        settings.center = self.center.unwrap_or(settings.center);
+        settings.mesh.base_depth = self.base_depth.or(settings.mesh.base_depth);
+        settings.mesh.scale = self.scale.unwrap_or(settings.mesh.scale);
+        if let Some(height_range) = self.height_range {
+            settings.normalization = bake::HeightNormalization::Range {
+                max: height_range.y,
+                min: height_range.x,
+            };
+        }
+        settings.resolution = self.resolution.unwrap_or(settings.resolution);
+        settings.size = self.size.unwrap_or(settings.size);
+        settings.validate()?;
+        Ok(settings)
+    }
+}
+
+/// Run the bake command with `args`, without opening a window.
+pub fn bake(args: Vec<String>) -> AppExit {
+    let bake_args: BakeArgs = match BakeArgs::parse(args) {
+        Ok(Some(bake_args)) => bake_args,
+        Ok(None) => {
+            println!("{}", BAKE_USAGE);
+            return AppExit::Success;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, BAKE_USAGE);
+            return AppExit::from_code(EXIT_USAGE);
+        }
+    };
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, biome::BiomePlugin, layer::LayerPlugin));
+    app.finish();
+    app.cleanup();
+    if let Err(e) = save::load(bake_args.project.as_path(), app.world_mut()) {
+        eprintln!(
+            "error: cannot load '{}': {}",
+            bake_args.project.display(),
+            e
+        );
+        return AppExit::from_code(EXIT_LOAD_FAILED);
+    }
+    // Let the loaded layers and masks settle, i.e. normalize their ordering.
+    app.update();
+
+    let mut settings: bake::BakeSettings = match bake_args.settings(app.world()) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!(
+                "error: invalid bake settings in '{}': {}",
+                bake_args.project.display(),
+                e
+            );
+            return AppExit::from_code(EXIT_LOAD_FAILED);
+        }
+    };
+    let height_grid: HeightGrid = bake::bake_now(app.world_mut(), &settings);
+    let mut exit = AppExit::Success;
+    for (path, format) in bake_args.outputs.iter() {
+        settings.format = *format;
+        match bake::write_bake(path, &height_grid, &settings) {
+            Ok(()) => println!("Baked '{}'", path.display()),
+            Err(e) => {
+                eprintln!("error: cannot bake '{}': {}", path.display(), e);
+                exit = AppExit::from_code(EXIT_BAKE_FAILED);
+            }
+        }
+    }
+    exit
+}
+
+fn parse_pair<T: FromStr>(option: &str, value: &str, separator: char) -> Result<[T; 2], CliError> {
+    let invalid_value = || CliError::InvalidValue(option.to_owned(), value.to_owned());
+    let (a, b) = value.split_once(separator).ok_or_else(invalid_value)?;
+    Ok([
+        a.trim().parse().map_err(|_| invalid_value())?,
+        b.trim().parse().map_err(|_| invalid_value())?,
+    ])
+}
+
+/// Parse a finite number that is zero or greater.
+fn parse_non_negative(option: &str, value: &str) -> Result<f32, CliError> {
+    let x: f32 = parse_value(option, value)?;
+    if x.is_finite() && x >= 0.0 {
+        Ok(x)
+    } else {
+        Err(CliError::InvalidValue(option.to_owned(), value.to_owned()))
+    }
+}
+
+/// Parse a finite number greater than zero.
+fn parse_positive(option: &str, value: &str) -> Result<f32, CliError> {
+    let x: f32 = parse_value(option, value)?;
+    if x.is_finite() && x > 0.0 {
+        Ok(x)
+    } else {
+        Err(CliError::InvalidValue(option.to_owned(), value.to_owned()))
+    }
+}
+
+fn parse_value<T: FromStr>(option: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::InvalidValue(option.to_owned(), value.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn bake_args_are_parsed() {
+        assert_eq!(
+            BakeArgs::parse(args(
+                "terrain.yer -o height.png --output mesh.glb --center 10,-20 --size 500 \
+                 --resolution 2049x1025 --height-range -100,900 --scale 0.001 --base 50"
+            )),
+            Ok(Some(BakeArgs {
+                base_depth: Some(50.0),
+                center: Some(Vec2::new(10.0, -20.0)),
+                height_range: Some(Vec2::new(-100.0, 900.0)),
+                outputs: vec![
+                    (PathBuf::from("height.png"), bake::BakeFormat::Png16),
+                    (PathBuf::from("mesh.glb"), bake::BakeFormat::Glb),
+                ],
+                project: PathBuf::from("terrain.yer"),
+                resolution: Some(UVec2::new(2049, 1025)),
+                scale: Some(0.001),
+                size: Some(500.0),
+            }))
+        );
+        assert_eq!(BakeArgs::parse(args("terrain.yer --help")), Ok(None));
+    }
+
+    #[test]
+    fn invalid_bake_args_are_rejected() {
+        assert_eq!(
+            BakeArgs::parse(args("-o height.png")),
+            Err(CliError::NoProject)
+        );
+        assert_eq!(
+            BakeArgs::parse(args("terrain.yer")),
+            Err(CliError::NoOutput)
+        );
+        assert_eq!(
+            BakeArgs::parse(args("terrain.yer -o height.bmp")),
+            Err(CliError::UnknownFormat("height.bmp".to_owned()))
+        );
+        assert_eq!(
+            BakeArgs::parse(args("terrain.yer -o height.png --resolution 1x100")),
+            Err(CliError::InvalidValue(
+                "--resolution".to_owned(),
+                "1x100".to_owned()
+            ))
+        );
+        for (option, value) in [
+            ("--size", "0"),
+            ("--size", "-100"),
+            ("--size", "NaN"),
+            ("--scale", "0"),
+            ("--scale", "-0.5"),
+            ("--scale", "inf"),
+            ("--height-range", "100,100"),
+            ("--height-range", "100,-100"),
+            ("--height-range", "NaN,100"),
+            ("--base", "-1"),
+            ("--base", "NaN"),
+        ] {
+            assert_eq!(
+                BakeArgs::parse(args(&format!(
+                    "terrain.yer -o height.png {} {}",
+                    option, value
+                ))),
+                Err(CliError::InvalidValue(option.to_owned(), value.to_owned()))
+            );
+        }
+        assert_eq!(
+            BakeArgs::parse(args("terrain.yer -o height.png --size")),
+            Err(CliError::MissingValue("--size".to_owned()))
+        );
+        assert_eq!(
+            BakeArgs::parse(args("terrain.yer -o height.png --verbose")),
+            Err(CliError::UnknownOption("--verbose".to_owned()))
+        );
+    }
+
+    #[test]
+    fn invalid_project_bake_settings_are_rejected() {
+        let mut world = World::new();
+        world.insert_resource(bake::BakeSettings {
+            resolution: UVec2::new(1, 1),
+            ..default()
+        });
+        let bake_args: BakeArgs = BakeArgs::parse(args("terrain.yer -o height.png"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            bake_args.settings(&world),
+            Err(bake::BakeSettingsError::InvalidResolution(UVec2::new(1, 1)))
+        );
+        let bake_args: BakeArgs =
+            BakeArgs::parse(args("terrain.yer -o height.png --resolution 65x33"))
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            bake_args
+                .settings(&world)
+                .map(|settings| settings.resolution),
+            Ok(UVec2::new(65, 33))
+        );
+    }
+}
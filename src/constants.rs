@@ -20,6 +20,10 @@ use bevy::math::Vec3;
 
 pub const APPLICATION_TITLE: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
 
+/// Seconds between autosaves, 0 disables autosave.
+pub const AUTOSAVE_INTERVAL_DEFAULT: u32 = 60;
+pub const AUTOSAVE_INTERVAL_RANGE: RangeInclusive<u32> = 0..=3600;
+
 pub const PREVIEW_DEFAULT_FACE_COLOR: Color = Color::hsl(0.0, 0.0, 0.5);
 pub const PREVIEW_DEFAULT_WIREFRAME_COLOR: Color = Color::hsl(0.0, 0.0, 0.85);
 pub const PREVIEW_DEFAULT_FACE_ALPHA: f32 = 0.65f32;
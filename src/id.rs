@@ -14,6 +14,12 @@
 // You should have received a copy of the GNU General Public License along
 // with Yer.  If not, see <https://www.gnu.org/licenses/>.
 
+/// A stable id for biomes.
+///
+/// Masks refer to biomes by their id, so that renaming or reordering the
+/// biomes does not break them.
+pub type BiomeId = uuid::Uuid;
+
 /// A stable id for masks.
 ///
 /// We cannot use `Entity` as a stable id because if a mask is deleted and
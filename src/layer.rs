@@ -14,25 +14,34 @@
 // You should have received a copy of the GNU General Public License along
 // with Yer.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::sync::Arc;
+
 use bevy::prelude::*;
 
-use crate::math::{Sample, Sampler2D};
+use crate::biome::BiomeTable;
+use crate::math::{Filter, HeightGrid, Sample, Sampler2D};
 use crate::undo;
 
 mod actions;
+mod bitmap;
 mod components;
+mod erosion;
 mod mask;
 
 pub use actions::{
-    CreateLayerAction, DeleteLayerAction, HeightMapConstantUpdateHeightAction, RenameLayerAction,
-    SwitchLayerPositionsAction, UpdateLayerAction,
+    ChangeLayerBlendModeAction, CreateLayerAction, DeleteLayerAction, HeightMapBitmapUpdateAction,
+    HeightMapConstantUpdateHeightAction, HeightMapHydraulicErosionUpdateAction,
+    HeightMapNoiseUpdateAction, HeightMapSummitUpdateAction, HeightMapThermalErosionUpdateAction,
+    RenameLayerAction, SwitchLayerPositionsAction, UpdateLayerAction, UpdateLayerChannelsAction,
 };
+pub use bitmap::{BitmapError, BitmapImage, BitmapInterpolation, BitmapOutOfBounds};
 pub use components::{
-    HeightMap, Layer, LayerBundle, LayerOrder, NeedsLayerOrderNormalization, HEIGHT_RANGE,
+    HeightMap, Layer, LayerBlendMode, LayerBundle, LayerChannel, LayerOrder,
+    NeedsLayerOrderNormalization, SummitProfile, HEIGHT_RANGE,
 };
 pub use mask::{
-    CreateMaskAction, DeleteMaskAction, Mask, MaskBundle, MaskCompositionMode, MaskOrder,
-    MaskSource, UpdateMaskAction, UpdateMaskSourceAction,
+    CreateMaskAction, DeleteMaskAction, GradientShape, Mask, MaskBundle, MaskCompositionMode,
+    MaskCurve, MaskOrder, MaskSource, MaskVertex, UpdateMaskAction, UpdateMaskSourceAction,
 };
 
 // PLUGIN
@@ -85,25 +94,192 @@ fn normalize_layer_ordering_system(
 // LIB
 
 pub struct LayerSampler {
+    pub biome_table: Arc<BiomeTable>,
+    pub blend_mode: LayerBlendMode,
+    pub channels: Vec<LayerChannel>,
     pub height_map: HeightMap,
     pub masks: Vec<(Mask, MaskSource)>,
+    pub opacity: f32,
+    pub write_height: bool,
+}
+
+impl LayerSampler {
+    /// Combined multiplier of all the masks at `position`.
+    fn mask_multiplier(
+        &self,
+        position: Vec2,
+        base_sample: &Sample,
+        base_gradient: Option<Vec2>,
+    ) -> f32 {
+        // Biome masks read the biome of the terrain beneath from its channels.
+        let classified_sample: Option<Sample> = self
+            .masks
+            .iter()
+            .any(|(_, mask_source)| mask_source.needs_biomes())
+            .then(|| {
+                self.biome_table
+                    .classify_sample(base_sample, base_gradient.unwrap_or(Vec2::ZERO))
+            });
+        let base_sample: &Sample = classified_sample.as_ref().unwrap_or(base_sample);
+        let mut mask_multiplier: Option<f32> = None;
+        for (mask, mask_source) in self.masks.iter() {
+            mask_multiplier = Some(mask.combine(
+                mask_multiplier,
+                mask_source.sample(position, base_sample, base_gradient),
+            ));
+        }
+        // A layer without masks is not masked at all.
+        mask_multiplier.unwrap_or(1.0)
+    }
 }
 
 impl Sampler2D for LayerSampler {
     fn sample(&self, position: Vec2, base_sample: &Sample) -> Sample {
-        let mut sample = self.height_map.sample(position, base_sample);
-
-        // We need this condition to avoid multiplying the sample with zero.
-        if !self.masks.is_empty() {
-            let mut mask_multiplier: Option<f32> = None;
-            for (mask, mask_source) in self.masks.iter() {
-                mask_multiplier = Some(mask.combine(mask_multiplier, mask_source.sample(position)));
-            }
-            sample.multiply_alpha_mut(mask_multiplier.unwrap());
+        self.sample_with_gradient(position, base_sample, None)
+    }
+
+    fn sample_with_gradient(
+        &self,
+        position: Vec2,
+        base_sample: &Sample,
+        base_gradient: Option<Vec2>,
+    ) -> Sample {
+        let layer_sample = self.height_map.sample(position, base_sample);
+        let mut sample = Sample::new(
+            if self.write_height {
+                self.blend_mode
+                    .blend(base_sample.height(), layer_sample.height())
+            } else {
+                base_sample.height()
+            },
+            layer_sample.alpha(),
+        );
+        for LayerChannel {
+            blend_mode,
+            channel,
+        } in self.channels.iter()
+        {
+            let base: f32 = base_sample.channel(channel).unwrap_or(0.0);
+            sample.set_channel(
+                channel.clone(),
+                blend_mode.blend(base, layer_sample.height()),
+            );
         }
+        sample.multiply_alpha_mut(self.mask_multiplier(position, base_sample, base_gradient));
+        sample.multiply_alpha_mut(self.opacity);
 
         sample
     }
+
+    /// Whether any of the masks depend on the slope of the terrain beneath.
+    fn needs_gradient(&self) -> bool {
+        self.masks
+            .iter()
+            .any(|(_, mask_source)| mask_source.needs_gradient())
+    }
+
+    fn filter<'a>(
+        &'a self,
+        grid: &HeightGrid,
+        base_samples: &'a [Sample],
+    ) -> Option<Box<dyn Filter + 'a>> {
+        if !self.write_height {
+            return None;
+        }
+        Some(Box::new(LayerFilter {
+            base: grid.clone(),
+            base_samples,
+            filter: self.height_map.filter(grid, base_samples)?,
+            layer: self,
+        }))
+    }
+
+    fn is_filter(&self) -> bool {
+        self.height_map.is_filter()
+    }
+}
+
+/// Filter of a layer, blended with the heights beneath using the masks and
+/// the opacity of the layer.
+struct LayerFilter<'a> {
+    base: HeightGrid,
+    /// Masks read the channels of the terrain beneath from these.
+    base_samples: &'a [Sample],
+    filter: Box<dyn Filter + 'a>,
+    layer: &'a LayerSampler,
+}
+
+impl Filter for LayerFilter<'_> {
+    fn step(&mut self) -> bool {
+        self.filter.step()
+    }
+
+    fn progress(&self) -> f32 {
+        self.filter.progress()
+    }
+
+    fn finish(self: Box<Self>) -> HeightGrid {
+        let Self {
+            base: grid,
+            base_samples,
+            filter,
+            layer,
+        } = *self;
+        let mut filtered: HeightGrid = filter.finish();
+        for (idx, height) in filtered.heights_mut().iter_mut().enumerate() {
+            let base: f32 = grid.heights()[idx];
+            let base_gradient: Option<Vec2> = layer.needs_gradient().then(|| grid.gradient(idx));
+            let alpha: f32 =
+                layer.mask_multiplier(grid.position(idx), &base_samples[idx], base_gradient)
+                    * layer.opacity;
+            *height = base.lerp(layer.blend_mode.blend(base, *height), alpha);
+        }
+        filtered
+    }
+}
+
+/// Samplers of the layers `predicate` accepts, in order, with their names.
+pub fn extract_samplers<F: Fn(&Layer) -> bool>(
+    world: &mut World,
+    predicate: F,
+) -> Vec<(String, Box<dyn Sampler2D>)> {
+    let biome_table: Arc<BiomeTable> = Arc::new(world.resource::<BiomeTable>().clone());
+    let entities_and_height_maps: Vec<(Entity, Layer, HeightMap)> = world
+        .query::<(Entity, &Layer, &LayerOrder, &HeightMap)>()
+        .iter(world)
+        .sort_unstable::<&LayerOrder>()
+        .filter(|(_, layer, _, _)| predicate(layer))
+        .map(|(entity, layer, _, height_map)| (entity, layer.clone(), height_map.clone()))
+        .collect();
+    entities_and_height_maps
+        .into_iter()
+        .map(|(entity, layer, height_map)| {
+            let children: Vec<Entity> = world
+                .entity(entity)
+                .get::<Children>()
+                .map(|children| children.to_vec())
+                .unwrap_or_default();
+            let masks: Vec<(Mask, MaskSource)> = world
+                .entity(children.as_slice())
+                .iter()
+                .map(|entity_ref| (entity_ref.get::<Mask>(), entity_ref.get::<MaskSource>()))
+                .filter(|(mask, mask_source)| {
+                    mask.map(|m| m.is_enabled).unwrap_or(false) && mask_source.is_some()
+                })
+                .map(|(mask, mask_source)| (mask.unwrap().clone(), mask_source.unwrap().clone()))
+                .collect();
+            let sampler = Box::new(LayerSampler {
+                biome_table: biome_table.clone(),
+                blend_mode: layer.blend_mode,
+                channels: layer.channels.clone(),
+                height_map,
+                masks,
+                opacity: layer.opacity,
+                write_height: layer.write_height,
+            }) as Box<dyn Sampler2D>;
+            (layer.name, sampler)
+        })
+        .collect()
 }
 
 /// This is intended to be called to create the initial layer only.  It does
@@ -119,3 +295,55 @@ pub fn create_initial_layer(world: &mut World) {
         LayerOrder(0),
     ));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biome::{Biome, BiomeRange};
+    use crate::math::{Alpha, Channel};
+
+    #[test]
+    fn filter_masks_read_the_channels_beneath() {
+        let wetland = Biome {
+            moisture: Some(BiomeRange::new(0.5, 1.0)),
+            ..Biome::new("Wetland", Color::srgb(0.0, 0.5, 0.0))
+        };
+        let wetland_id = wetland.id();
+        let layer_sampler = LayerSampler {
+            biome_table: Arc::new(BiomeTable::new(vec![wetland])),
+            blend_mode: LayerBlendMode::Normal,
+            channels: vec![],
+            height_map: HeightMap::thermal_erosion(),
+            masks: vec![(
+                Mask::default(),
+                MaskSource::Biome {
+                    biomes: vec![wetland_id],
+                },
+            )],
+            opacity: 1.0,
+            write_height: true,
+        };
+        let k: u32 = 9;
+        // A cliff of 10 units in the middle.
+        let heights: Vec<f32> = (0..k * k)
+            .map(|idx| if idx % k < k / 2 { 10.0 } else { 0.0 })
+            .collect();
+        let cliff = HeightGrid::new(Vec2::ZERO, 1.0, UVec2::splat(k), heights.clone());
+        let filter = |moisture: f32| {
+            let base_samples: Vec<Sample> = heights
+                .iter()
+                .map(|height| {
+                    let mut sample = Sample::new(*height, Alpha::Opaque);
+                    sample.set_channel(Channel::Moisture, moisture);
+                    sample
+                })
+                .collect();
+            layer_sampler
+                .filter(&cliff, &base_samples)
+                .unwrap()
+                .finish()
+        };
+        assert_eq!(filter(0.0), cliff);
+        assert_ne!(filter(1.0), cliff);
+    }
+}
@@ -21,10 +21,56 @@ use crate::layer::mask::{MaskOrder, MASK_SPACING};
 use crate::math::approx_eq;
 use crate::undo::{Action, ReflectAction};
 
+use super::bitmap::{BitmapImage, BitmapInterpolation, BitmapOutOfBounds};
 // FIXME: Rename components module.
-use super::components::{HeightMap, Layer, LayerBundle, LayerOrder, LAYER_SPACING};
+use super::components::{
+    HeightMap, Layer, LayerBlendMode, LayerBundle, LayerChannel, LayerOrder, SummitProfile,
+    LAYER_SPACING,
+};
 use super::MaskBundle;
 
+#[derive(Debug, Reflect)]
+#[reflect(Action)]
+pub struct ChangeLayerBlendModeAction {
+    layer_id: LayerId,
+    old_value: LayerBlendMode,
+    new_value: LayerBlendMode,
+}
+
+impl ChangeLayerBlendModeAction {
+    pub fn new(layer_id: LayerId, old_value: LayerBlendMode, new_value: LayerBlendMode) -> Self {
+        debug_assert!(old_value != new_value);
+        Self {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+}
+
+impl Action for ChangeLayerBlendModeAction {
+    fn apply(&self, world: &mut World) {
+        world
+            .query::<&mut Layer>()
+            .iter_mut(world)
+            .find(|layer| layer.id() == self.layer_id)
+            .map(|mut layer| {
+                debug_assert!(layer.blend_mode == self.old_value);
+                layer.blend_mode = self.new_value;
+            })
+            .expect(&format!("Layer with id {} not found.", self.layer_id));
+    }
+
+    fn revert(&self, world: &mut World) {
+        let reverse_action = Self {
+            layer_id: self.layer_id,
+            old_value: self.new_value,
+            new_value: self.old_value,
+        };
+        reverse_action.apply(world);
+    }
+}
+
 #[derive(Debug, Reflect)]
 #[reflect(Action)]
 pub struct CreateLayerAction {
@@ -35,8 +81,15 @@ pub struct CreateLayerAction {
 
 impl CreateLayerAction {
     pub fn new(parent_id: Option<LayerId>) -> Self {
+        Self::with_height_map(parent_id, HeightMap::default())
+    }
+
+    pub fn with_height_map(parent_id: Option<LayerId>, height_map: HeightMap) -> Self {
         Self {
-            layer_bundle: LayerBundle::default(),
+            layer_bundle: LayerBundle {
+                height_map,
+                ..default()
+            },
             masks: vec![],
             parent_id,
         }
@@ -140,6 +193,259 @@ impl Action for DeleteLayerAction {
     }
 }
 
+#[derive(Debug, Reflect)]
+#[reflect(Action)]
+pub enum HeightMapBitmapUpdateAction {
+    ChangeInterpolation {
+        layer_id: LayerId,
+        old_value: BitmapInterpolation,
+        new_value: BitmapInterpolation,
+    },
+    ChangeOutOfBounds {
+        layer_id: LayerId,
+        old_value: BitmapOutOfBounds,
+        new_value: BitmapOutOfBounds,
+    },
+    UpdateBlackHeight {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateImage {
+        layer_id: LayerId,
+        old_value: BitmapImage,
+        new_value: BitmapImage,
+    },
+    UpdatePosition {
+        layer_id: LayerId,
+        old_value: Vec2,
+        new_value: Vec2,
+    },
+    UpdateRotation {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateSize {
+        layer_id: LayerId,
+        old_value: Vec2,
+        new_value: Vec2,
+    },
+    UpdateWhiteHeight {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+}
+
+impl HeightMapBitmapUpdateAction {
+    pub fn change_interpolation(
+        layer_id: LayerId,
+        old_value: BitmapInterpolation,
+        new_value: BitmapInterpolation,
+    ) -> Self {
+        debug_assert!(old_value != new_value);
+        Self::ChangeInterpolation {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn change_out_of_bounds(
+        layer_id: LayerId,
+        old_value: BitmapOutOfBounds,
+        new_value: BitmapOutOfBounds,
+    ) -> Self {
+        debug_assert!(old_value != new_value);
+        Self::ChangeOutOfBounds {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_black_height(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateBlackHeight {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_image(layer_id: LayerId, old_value: BitmapImage, new_value: BitmapImage) -> Self {
+        Self::UpdateImage {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_position(layer_id: LayerId, old_value: Vec2, new_value: Vec2) -> Self {
+        Self::UpdatePosition {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_rotation(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateRotation {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_size(layer_id: LayerId, old_value: Vec2, new_value: Vec2) -> Self {
+        Self::UpdateSize {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_white_height(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateWhiteHeight {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    fn layer_id(&self) -> &LayerId {
+        match self {
+            Self::ChangeInterpolation { layer_id, .. } => layer_id,
+            Self::ChangeOutOfBounds { layer_id, .. } => layer_id,
+            Self::UpdateBlackHeight { layer_id, .. } => layer_id,
+            Self::UpdateImage { layer_id, .. } => layer_id,
+            Self::UpdatePosition { layer_id, .. } => layer_id,
+            Self::UpdateRotation { layer_id, .. } => layer_id,
+            Self::UpdateSize { layer_id, .. } => layer_id,
+            Self::UpdateWhiteHeight { layer_id, .. } => layer_id,
+        }
+    }
+}
+
+impl Action for HeightMapBitmapUpdateAction {
+    fn apply(&self, world: &mut World) {
+        let mut height_map = world
+            .query::<(&Layer, &mut HeightMap)>()
+            .iter_mut(world)
+            .find(|(layer, _)| layer.id() == *self.layer_id())
+            .map(|(_, height_map)| height_map)
+            .expect(&format!("Layer with id {} not found.", self.layer_id()));
+        match (self, height_map.as_mut()) {
+            (
+                Self::ChangeInterpolation { new_value, .. },
+                HeightMap::Bitmap { interpolation, .. },
+            ) => *interpolation = *new_value,
+            (
+                Self::ChangeOutOfBounds { new_value, .. },
+                HeightMap::Bitmap { out_of_bounds, .. },
+            ) => *out_of_bounds = *new_value,
+            (Self::UpdateBlackHeight { new_value, .. }, HeightMap::Bitmap { black_height, .. }) => {
+                *black_height = *new_value
+            }
+            (Self::UpdateImage { new_value, .. }, HeightMap::Bitmap { image, .. }) => {
+                *image = new_value.clone()
+            }
+            (Self::UpdatePosition { new_value, .. }, HeightMap::Bitmap { position, .. }) => {
+                *position = *new_value
+            }
+            (Self::UpdateRotation { new_value, .. }, HeightMap::Bitmap { rotation, .. }) => {
+                // Constrain rotation to [0.0; 1.0)
+                *rotation = new_value.fract()
+            }
+            (Self::UpdateSize { new_value, .. }, HeightMap::Bitmap { size, .. }) => {
+                *size = *new_value
+            }
+            (Self::UpdateWhiteHeight { new_value, .. }, HeightMap::Bitmap { white_height, .. }) => {
+                *white_height = *new_value
+            }
+            (_, _) => unreachable!(),
+        };
+    }
+
+    fn revert(&self, world: &mut World) {
+        let reverse_action: Self = match self {
+            Self::ChangeInterpolation {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::ChangeInterpolation {
+                layer_id: *layer_id,
+                old_value: *new_value,
+                new_value: *old_value,
+            },
+            Self::ChangeOutOfBounds {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::ChangeOutOfBounds {
+                layer_id: *layer_id,
+                old_value: *new_value,
+                new_value: *old_value,
+            },
+            Self::UpdateBlackHeight {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateBlackHeight {
+                layer_id: *layer_id,
+                old_value: *new_value,
+                new_value: *old_value,
+            },
+            Self::UpdateImage {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateImage {
+                layer_id: *layer_id,
+                old_value: new_value.clone(),
+                new_value: old_value.clone(),
+            },
+            Self::UpdatePosition {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdatePosition {
+                layer_id: *layer_id,
+                old_value: *new_value,
+                new_value: *old_value,
+            },
+            Self::UpdateRotation {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateRotation {
+                layer_id: *layer_id,
+                old_value: *new_value,
+                new_value: *old_value,
+            },
+            Self::UpdateSize {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateSize {
+                layer_id: *layer_id,
+                old_value: *new_value,
+                new_value: *old_value,
+            },
+            Self::UpdateWhiteHeight {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateWhiteHeight {
+                layer_id: *layer_id,
+                old_value: *new_value,
+                new_value: *old_value,
+            },
+        };
+        reverse_action.apply(world);
+    }
+}
+
 #[derive(Debug, Reflect)]
 #[reflect(Action)]
 pub struct HeightMapConstantUpdateHeightAction {
@@ -173,6 +479,13 @@ impl Action for HeightMapConstantUpdateHeightAction {
                     ));
                     *height = self.new_height;
                 }
+                HeightMap::Bitmap { .. }
+                | HeightMap::HydraulicErosion { .. }
+                | HeightMap::Noise { .. }
+                | HeightMap::Summit { .. }
+                | HeightMap::ThermalErosion { .. } => {
+                    unreachable!()
+                }
             })
             .expect(&format!("Layer with id {} not found.", self.layer_id));
     }
@@ -187,6 +500,752 @@ impl Action for HeightMapConstantUpdateHeightAction {
     }
 }
 
+#[derive(Debug, Reflect)]
+#[reflect(Action)]
+pub enum HeightMapHydraulicErosionUpdateAction {
+    UpdateDepositionRate {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateErosionRate {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateIterations {
+        layer_id: LayerId,
+        old_value: u32,
+        new_value: u32,
+    },
+    UpdateRainAmount {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateSedimentCapacity {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateSeed {
+        layer_id: LayerId,
+        old_value: u32,
+        new_value: u32,
+    },
+}
+
+impl HeightMapHydraulicErosionUpdateAction {
+    pub fn update_deposition_rate(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateDepositionRate {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_erosion_rate(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateErosionRate {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_iterations(layer_id: LayerId, old_value: u32, new_value: u32) -> Self {
+        Self::UpdateIterations {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_rain_amount(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateRainAmount {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_sediment_capacity(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateSedimentCapacity {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_seed(layer_id: LayerId, old_value: u32, new_value: u32) -> Self {
+        Self::UpdateSeed {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    fn layer_id(&self) -> &LayerId {
+        match self {
+            Self::UpdateDepositionRate { layer_id, .. } => layer_id,
+            Self::UpdateErosionRate { layer_id, .. } => layer_id,
+            Self::UpdateIterations { layer_id, .. } => layer_id,
+            Self::UpdateRainAmount { layer_id, .. } => layer_id,
+            Self::UpdateSedimentCapacity { layer_id, .. } => layer_id,
+            Self::UpdateSeed { layer_id, .. } => layer_id,
+        }
+    }
+}
+
+impl Action for HeightMapHydraulicErosionUpdateAction {
+    fn apply(&self, world: &mut World) {
+        let mut height_map = world
+            .query::<(&Layer, &mut HeightMap)>()
+            .iter_mut(world)
+            .find(|(layer, _)| layer.id() == *self.layer_id())
+            .map(|(_, height_map)| height_map)
+            .expect(&format!("Layer with id {} not found.", self.layer_id()));
+        match (self, height_map.as_mut()) {
+            (
+                Self::UpdateDepositionRate { new_value, .. },
+                HeightMap::HydraulicErosion {
+                    deposition_rate, ..
+                },
+            ) => *deposition_rate = *new_value,
+            (
+                Self::UpdateErosionRate { new_value, .. },
+                HeightMap::HydraulicErosion { erosion_rate, .. },
+            ) => *erosion_rate = *new_value,
+            (
+                Self::UpdateIterations { new_value, .. },
+                HeightMap::HydraulicErosion { iterations, .. },
+            ) => *iterations = *new_value,
+            (
+                Self::UpdateRainAmount { new_value, .. },
+                HeightMap::HydraulicErosion { rain_amount, .. },
+            ) => *rain_amount = *new_value,
+            (
+                Self::UpdateSedimentCapacity { new_value, .. },
+                HeightMap::HydraulicErosion {
+                    sediment_capacity, ..
+                },
+            ) => *sediment_capacity = *new_value,
+            (Self::UpdateSeed { new_value, .. }, HeightMap::HydraulicErosion { seed, .. }) => {
+                *seed = *new_value
+            }
+            (_, _) => unreachable!(),
+        };
+    }
+
+    fn revert(&self, world: &mut World) {
+        let reverse_action: Self = match *self {
+            Self::UpdateDepositionRate {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateDepositionRate {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateErosionRate {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateErosionRate {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateIterations {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateIterations {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateRainAmount {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateRainAmount {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateSedimentCapacity {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateSedimentCapacity {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateSeed {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateSeed {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+        };
+        reverse_action.apply(world);
+    }
+}
+
+#[derive(Debug, Reflect)]
+#[reflect(Action)]
+pub enum HeightMapNoiseUpdateAction {
+    UpdateAmplitude {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateFrequency {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateGain {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateLacunarity {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateOctaves {
+        layer_id: LayerId,
+        old_value: u8,
+        new_value: u8,
+    },
+    UpdateOffset {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateSeed {
+        layer_id: LayerId,
+        old_value: u32,
+        new_value: u32,
+    },
+}
+
+impl HeightMapNoiseUpdateAction {
+    pub fn update_amplitude(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateAmplitude {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_frequency(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateFrequency {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_gain(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateGain {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_lacunarity(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateLacunarity {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_octaves(layer_id: LayerId, old_value: u8, new_value: u8) -> Self {
+        Self::UpdateOctaves {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_offset(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateOffset {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_seed(layer_id: LayerId, old_value: u32, new_value: u32) -> Self {
+        Self::UpdateSeed {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    fn layer_id(&self) -> &LayerId {
+        match self {
+            Self::UpdateAmplitude { layer_id, .. } => layer_id,
+            Self::UpdateFrequency { layer_id, .. } => layer_id,
+            Self::UpdateGain { layer_id, .. } => layer_id,
+            Self::UpdateLacunarity { layer_id, .. } => layer_id,
+            Self::UpdateOctaves { layer_id, .. } => layer_id,
+            Self::UpdateOffset { layer_id, .. } => layer_id,
+            Self::UpdateSeed { layer_id, .. } => layer_id,
+        }
+    }
+}
+
+impl Action for HeightMapNoiseUpdateAction {
+    fn apply(&self, world: &mut World) {
+        let mut height_map = world
+            .query::<(&Layer, &mut HeightMap)>()
+            .iter_mut(world)
+            .find(|(layer, _)| layer.id() == *self.layer_id())
+            .map(|(_, height_map)| height_map)
+            .expect(&format!("Layer with id {} not found.", self.layer_id()));
+        match (self, height_map.as_mut()) {
+            (Self::UpdateAmplitude { new_value, .. }, HeightMap::Noise { amplitude, .. }) => {
+                *amplitude = *new_value
+            }
+            (Self::UpdateFrequency { new_value, .. }, HeightMap::Noise { frequency, .. }) => {
+                *frequency = *new_value
+            }
+            (Self::UpdateGain { new_value, .. }, HeightMap::Noise { gain, .. }) => {
+                *gain = *new_value
+            }
+            (Self::UpdateLacunarity { new_value, .. }, HeightMap::Noise { lacunarity, .. }) => {
+                *lacunarity = *new_value
+            }
+            (Self::UpdateOctaves { new_value, .. }, HeightMap::Noise { octaves, .. }) => {
+                *octaves = *new_value
+            }
+            (Self::UpdateOffset { new_value, .. }, HeightMap::Noise { offset, .. }) => {
+                *offset = *new_value
+            }
+            (Self::UpdateSeed { new_value, .. }, HeightMap::Noise { seed, .. }) => {
+                *seed = *new_value
+            }
+            (_, _) => unreachable!(),
+        };
+    }
+
+    fn revert(&self, world: &mut World) {
+        let reverse_action: Self = match *self {
+            Self::UpdateAmplitude {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateAmplitude {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateFrequency {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateFrequency {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateGain {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateGain {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateLacunarity {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateLacunarity {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateOctaves {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateOctaves {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateOffset {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateOffset {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateSeed {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateSeed {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+        };
+        reverse_action.apply(world);
+    }
+}
+
+#[derive(Debug, Reflect)]
+#[reflect(Action)]
+pub enum HeightMapSummitUpdateAction {
+    ChangeProfile {
+        layer_id: LayerId,
+        old_value: SummitProfile,
+        new_value: SummitProfile,
+    },
+    UpdateBaseRadius {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdatePeakHeight {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdatePosition {
+        layer_id: LayerId,
+        old_value: Vec2,
+        new_value: Vec2,
+    },
+    UpdateRidgeFrequency {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateRidgeStrength {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateSeed {
+        layer_id: LayerId,
+        old_value: u32,
+        new_value: u32,
+    },
+}
+
+impl HeightMapSummitUpdateAction {
+    pub fn change_profile(
+        layer_id: LayerId,
+        old_value: SummitProfile,
+        new_value: SummitProfile,
+    ) -> Self {
+        debug_assert!(old_value != new_value);
+        Self::ChangeProfile {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_base_radius(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateBaseRadius {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_peak_height(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdatePeakHeight {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_position(layer_id: LayerId, old_value: Vec2, new_value: Vec2) -> Self {
+        Self::UpdatePosition {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_ridge_frequency(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateRidgeFrequency {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_ridge_strength(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateRidgeStrength {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_seed(layer_id: LayerId, old_value: u32, new_value: u32) -> Self {
+        Self::UpdateSeed {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    fn layer_id(&self) -> &LayerId {
+        match self {
+            Self::ChangeProfile { layer_id, .. } => layer_id,
+            Self::UpdateBaseRadius { layer_id, .. } => layer_id,
+            Self::UpdatePeakHeight { layer_id, .. } => layer_id,
+            Self::UpdatePosition { layer_id, .. } => layer_id,
+            Self::UpdateRidgeFrequency { layer_id, .. } => layer_id,
+            Self::UpdateRidgeStrength { layer_id, .. } => layer_id,
+            Self::UpdateSeed { layer_id, .. } => layer_id,
+        }
+    }
+}
+
+impl Action for HeightMapSummitUpdateAction {
+    fn apply(&self, world: &mut World) {
+        let mut height_map = world
+            .query::<(&Layer, &mut HeightMap)>()
+            .iter_mut(world)
+            .find(|(layer, _)| layer.id() == *self.layer_id())
+            .map(|(_, height_map)| height_map)
+            .expect(&format!("Layer with id {} not found.", self.layer_id()));
+        match (self, height_map.as_mut()) {
+            (Self::ChangeProfile { new_value, .. }, HeightMap::Summit { profile, .. }) => {
+                *profile = *new_value
+            }
+            (Self::UpdateBaseRadius { new_value, .. }, HeightMap::Summit { base_radius, .. }) => {
+                *base_radius = *new_value
+            }
+            (Self::UpdatePeakHeight { new_value, .. }, HeightMap::Summit { peak_height, .. }) => {
+                *peak_height = *new_value
+            }
+            (Self::UpdatePosition { new_value, .. }, HeightMap::Summit { position, .. }) => {
+                *position = *new_value
+            }
+            (
+                Self::UpdateRidgeFrequency { new_value, .. },
+                HeightMap::Summit {
+                    ridge_frequency, ..
+                },
+            ) => *ridge_frequency = *new_value,
+            (
+                Self::UpdateRidgeStrength { new_value, .. },
+                HeightMap::Summit { ridge_strength, .. },
+            ) => *ridge_strength = *new_value,
+            (Self::UpdateSeed { new_value, .. }, HeightMap::Summit { seed, .. }) => {
+                *seed = *new_value
+            }
+            (_, _) => unreachable!(),
+        };
+    }
+
+    fn revert(&self, world: &mut World) {
+        let reverse_action: Self = match *self {
+            Self::ChangeProfile {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::ChangeProfile {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateBaseRadius {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateBaseRadius {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdatePeakHeight {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdatePeakHeight {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdatePosition {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdatePosition {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateRidgeFrequency {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateRidgeFrequency {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateRidgeStrength {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateRidgeStrength {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateSeed {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateSeed {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+        };
+        reverse_action.apply(world);
+    }
+}
+
+#[derive(Debug, Reflect)]
+#[reflect(Action)]
+pub enum HeightMapThermalErosionUpdateAction {
+    UpdateAngle {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateIterations {
+        layer_id: LayerId,
+        old_value: u32,
+        new_value: u32,
+    },
+    UpdateStrength {
+        layer_id: LayerId,
+        old_value: f32,
+        new_value: f32,
+    },
+}
+
+impl HeightMapThermalErosionUpdateAction {
+    pub fn update_angle(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateAngle {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_iterations(layer_id: LayerId, old_value: u32, new_value: u32) -> Self {
+        Self::UpdateIterations {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_strength(layer_id: LayerId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateStrength {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    fn layer_id(&self) -> &LayerId {
+        match self {
+            Self::UpdateAngle { layer_id, .. } => layer_id,
+            Self::UpdateIterations { layer_id, .. } => layer_id,
+            Self::UpdateStrength { layer_id, .. } => layer_id,
+        }
+    }
+}
+
+impl Action for HeightMapThermalErosionUpdateAction {
+    fn apply(&self, world: &mut World) {
+        let mut height_map = world
+            .query::<(&Layer, &mut HeightMap)>()
+            .iter_mut(world)
+            .find(|(layer, _)| layer.id() == *self.layer_id())
+            .map(|(_, height_map)| height_map)
+            .expect(&format!("Layer with id {} not found.", self.layer_id()));
+        match (self, height_map.as_mut()) {
+            (Self::UpdateAngle { new_value, .. }, HeightMap::ThermalErosion { angle, .. }) => {
+                *angle = *new_value
+            }
+            (
+                Self::UpdateIterations { new_value, .. },
+                HeightMap::ThermalErosion { iterations, .. },
+            ) => *iterations = *new_value,
+            (
+                Self::UpdateStrength { new_value, .. },
+                HeightMap::ThermalErosion { strength, .. },
+            ) => *strength = *new_value,
+            (_, _) => unreachable!(),
+        };
+    }
+
+    fn revert(&self, world: &mut World) {
+        let reverse_action: Self = match *self {
+            Self::UpdateAngle {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateAngle {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateIterations {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateIterations {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateStrength {
+                layer_id,
+                old_value,
+                new_value,
+            } => Self::UpdateStrength {
+                layer_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+        };
+        reverse_action.apply(world);
+    }
+}
+
 #[derive(Debug, Reflect)]
 #[reflect(Action)]
 pub struct RenameLayerAction {
@@ -276,6 +1335,52 @@ impl Action for SwitchLayerPositionsAction {
     }
 }
 
+#[derive(Debug, Reflect)]
+#[reflect(Action)]
+pub struct UpdateLayerChannelsAction {
+    layer_id: LayerId,
+    old_value: Vec<LayerChannel>,
+    new_value: Vec<LayerChannel>,
+}
+
+impl UpdateLayerChannelsAction {
+    pub fn new(
+        layer_id: LayerId,
+        old_value: Vec<LayerChannel>,
+        new_value: Vec<LayerChannel>,
+    ) -> Self {
+        debug_assert!(old_value != new_value);
+        Self {
+            layer_id,
+            old_value,
+            new_value,
+        }
+    }
+}
+
+impl Action for UpdateLayerChannelsAction {
+    fn apply(&self, world: &mut World) {
+        world
+            .query::<&mut Layer>()
+            .iter_mut(world)
+            .find(|layer| layer.id() == self.layer_id)
+            .map(|mut layer| {
+                debug_assert!(layer.channels == self.old_value);
+                layer.channels = self.new_value.clone();
+            })
+            .expect(&format!("Layer with id {} not found.", self.layer_id));
+    }
+
+    fn revert(&self, world: &mut World) {
+        let reverse_action = Self {
+            layer_id: self.layer_id,
+            old_value: self.new_value.clone(),
+            new_value: self.old_value.clone(),
+        };
+        reverse_action.apply(world);
+    }
+}
+
 #[derive(Debug, Reflect)]
 #[reflect(Action)]
 pub struct UpdateLayerAction {
@@ -284,6 +1389,10 @@ pub struct UpdateLayerAction {
     new_enable_baking: bool,
     old_enable_preview: bool,
     new_enable_preview: bool,
+    old_opacity: f32,
+    new_opacity: f32,
+    old_write_height: bool,
+    new_write_height: bool,
 }
 
 impl UpdateLayerAction {
@@ -294,6 +1403,10 @@ impl UpdateLayerAction {
             new_enable_baking: !layer.enable_baking,
             old_enable_preview: layer.enable_preview,
             new_enable_preview: layer.enable_preview,
+            old_opacity: layer.opacity,
+            new_opacity: layer.opacity,
+            old_write_height: layer.write_height,
+            new_write_height: layer.write_height,
         }
     }
 
@@ -304,6 +1417,38 @@ impl UpdateLayerAction {
             new_enable_baking: layer.enable_baking,
             old_enable_preview: layer.enable_preview,
             new_enable_preview: !layer.enable_preview,
+            old_opacity: layer.opacity,
+            new_opacity: layer.opacity,
+            old_write_height: layer.write_height,
+            new_write_height: layer.write_height,
+        }
+    }
+
+    pub fn update_opacity(layer: &Layer, new_opacity: f32) -> Self {
+        Self {
+            layer_id: layer.id(),
+            old_enable_baking: layer.enable_baking,
+            new_enable_baking: layer.enable_baking,
+            old_enable_preview: layer.enable_preview,
+            new_enable_preview: layer.enable_preview,
+            old_opacity: layer.opacity,
+            new_opacity,
+            old_write_height: layer.write_height,
+            new_write_height: layer.write_height,
+        }
+    }
+
+    pub fn toggle_write_height(layer: &Layer) -> Self {
+        Self {
+            layer_id: layer.id(),
+            old_enable_baking: layer.enable_baking,
+            new_enable_baking: layer.enable_baking,
+            old_enable_preview: layer.enable_preview,
+            new_enable_preview: layer.enable_preview,
+            old_opacity: layer.opacity,
+            new_opacity: layer.opacity,
+            old_write_height: layer.write_height,
+            new_write_height: !layer.write_height,
         }
     }
 }
@@ -317,6 +1462,8 @@ impl Action for UpdateLayerAction {
             .map(|mut layer| {
                 layer.enable_baking = self.new_enable_baking;
                 layer.enable_preview = self.new_enable_preview;
+                layer.opacity = self.new_opacity;
+                layer.write_height = self.new_write_height;
             })
             .expect(&format!("Layer with id {} not found.", self.layer_id));
     }
@@ -328,6 +1475,10 @@ impl Action for UpdateLayerAction {
             new_enable_baking: self.old_enable_baking,
             old_enable_preview: self.new_enable_preview,
             new_enable_preview: self.old_enable_preview,
+            old_opacity: self.new_opacity,
+            new_opacity: self.old_opacity,
+            old_write_height: self.new_write_height,
+            new_write_height: self.old_write_height,
         };
         reverse_action.apply(world);
     }
@@ -415,6 +1566,47 @@ mod tests {
         assert_eq!(new_ids[2], initial_ids[1]);
     }
 
+    #[test]
+    fn height_map_noise_update_is_reverted_on_undo() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, LayerPlugin));
+        app.finish();
+        app.cleanup();
+        app.update();
+
+        app.world_mut().spawn((
+            LayerBundle {
+                layer: Layer::new(A),
+                height_map: HeightMap::noise(),
+                ..default()
+            },
+            LayerOrder(LAYER_SPACING),
+        ));
+        app.update();
+
+        let seed = |app: &mut App| -> u32 {
+            match app
+                .world_mut()
+                .query::<&HeightMap>()
+                .single(app.world())
+                .unwrap()
+            {
+                HeightMap::Noise { seed, .. } => *seed,
+                _ => unreachable!(),
+            }
+        };
+        let original_seed = seed(&mut app);
+        app.world_mut().commands().queue(undo::PushAction::from(
+            HeightMapNoiseUpdateAction::update_seed(A, original_seed, original_seed + 1),
+        ));
+        app.update();
+        assert_eq!(seed(&mut app), original_seed + 1);
+
+        app.world_mut().commands().queue(undo::UndoAction);
+        app.update();
+        assert_eq!(seed(&mut app), original_seed);
+    }
+
     #[test]
     fn switch_layer_positions_is_an_involution() {
         let mut app = App::new();
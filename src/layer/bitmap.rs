@@ -0,0 +1,331 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use image::ImageReader;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::math::clamp;
+
+// LIB
+
+#[derive(Debug, Error)]
+pub enum BitmapError {
+    #[error("image error: {0}")]
+    ImageError(image::ImageError),
+    #[error("io error: {0}")]
+    IoError(std::io::Error),
+    #[error("bitmap has {pixels} pixels, but its size is {width}x{height}")]
+    SizeMismatch {
+        height: u32,
+        pixels: usize,
+        width: u32,
+    },
+}
+
+/// A grayscale image, stored as 16-bit samples.
+///
+/// Pixel data is shared, so cloning a `BitmapImage` (which happens every
+/// time a preview is calculated) is cheap.  8-bit images are scaled up to
+/// 16-bit when they are loaded.
+#[derive(Clone, Debug, Default, Deserialize, Reflect, Serialize)]
+#[reflect(opaque, Clone, Debug, Default)]
+#[serde(try_from = "BitmapImageData", into = "BitmapImageData")]
+pub struct BitmapImage {
+    path: Option<PathBuf>,
+    width: u32,
+    height: u32,
+    pixels: Arc<[u16]>,
+}
+
+impl BitmapImage {
+    pub fn load(path: &Path) -> Result<Self, BitmapError> {
+        let image = ImageReader::open(path)
+            .map_err(|e| BitmapError::IoError(e))?
+            .decode()
+            .map_err(|e| BitmapError::ImageError(e))?
+            .into_luma16();
+        Ok(Self {
+            path: Some(path.to_owned()),
+            width: image.width(),
+            height: image.height(),
+            pixels: image.into_raw().into(),
+        })
+    }
+
+    pub fn file_name(&self) -> Option<String> {
+        self.path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|f| f.to_string_lossy().into())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pixels.is_empty()
+    }
+
+    pub fn size(&self) -> UVec2 {
+        UVec2::new(self.width, self.height)
+    }
+
+    /// Sample the image at normalized coordinates `uv`.
+    ///
+    /// `(0, 0)` is the top left corner and `(1, 1)` is the bottom right
+    /// corner of the image.  The result is in `[0.0, 1.0]`, or `None` if the
+    /// image is empty or `uv` is out of bounds and `out_of_bounds` is
+    /// [BitmapOutOfBounds::Transparent].
+    pub fn sample(
+        &self,
+        uv: Vec2,
+        interpolation: BitmapInterpolation,
+        out_of_bounds: BitmapOutOfBounds,
+    ) -> Option<f32> {
+        if self.is_empty() || !uv.is_finite() {
+            return None;
+        }
+        if out_of_bounds == BitmapOutOfBounds::Transparent
+            && (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0)
+        {
+            return None;
+        }
+        // Texel centers are at half-integer coordinates.
+        let p: Vec2 = uv * self.size().as_vec2() - 0.5;
+        let cell: Vec2 = p.floor();
+        let t: Vec2 = p - cell;
+        let (x, y) = (cell.x as i64, cell.y as i64);
+        let texel = |dx: i64, dy: i64| self.texel(x + dx, y + dy, out_of_bounds);
+        let value: f32 = match interpolation {
+            BitmapInterpolation::Bilinear => {
+                let top: f32 = texel(0, 0).lerp(texel(1, 0), t.x);
+                let bottom: f32 = texel(0, 1).lerp(texel(1, 1), t.x);
+                top.lerp(bottom, t.y)
+            }
+            BitmapInterpolation::Bicubic => {
+                let row = |dy: i64| {
+                    catmull_rom(texel(-1, dy), texel(0, dy), texel(1, dy), texel(2, dy), t.x)
+                };
+                catmull_rom(row(-1), row(0), row(1), row(2), t.y)
+            }
+        };
+        Some(clamp(value, 0.0, 1.0))
+    }
+
+    fn texel(&self, x: i64, y: i64, out_of_bounds: BitmapOutOfBounds) -> f32 {
+        let (w, h) = (i64::from(self.width), i64::from(self.height));
+        let (x, y) = match out_of_bounds {
+            // Transparent samples are filtered before this point, only the
+            // neighbours of the edge texels can be out of bounds here.
+            BitmapOutOfBounds::Clamp | BitmapOutOfBounds::Transparent => {
+                (x.clamp(0, w - 1), y.clamp(0, h - 1))
+            }
+            BitmapOutOfBounds::Tile => (x.rem_euclid(w), y.rem_euclid(h)),
+        };
+        f32::from(self.pixels[(y * w + x) as usize]) / f32::from(u16::MAX)
+    }
+}
+
+impl TryFrom<BitmapImageData> for BitmapImage {
+    type Error = BitmapError;
+
+    fn try_from(value: BitmapImageData) -> Result<Self, Self::Error> {
+        if value.pixels.len() != value.width as usize * value.height as usize {
+            return Err(BitmapError::SizeMismatch {
+                height: value.height,
+                pixels: value.pixels.len(),
+                width: value.width,
+            });
+        }
+        Ok(Self {
+            path: value.path,
+            width: value.width,
+            height: value.height,
+            pixels: value.pixels.into(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Reflect, Serialize)]
+pub enum BitmapInterpolation {
+    #[default]
+    Bilinear,
+    Bicubic,
+}
+
+impl BitmapInterpolation {
+    pub const ITEMS: [Self; 2] = [Self::Bilinear, Self::Bicubic];
+}
+
+impl ToString for BitmapInterpolation {
+    fn to_string(&self) -> String {
+        (match self {
+            Self::Bilinear => "Bilinear",
+            Self::Bicubic => "Bicubic",
+        })
+        .into()
+    }
+}
+
+/// What to do when a position outside of the bitmap is sampled.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Reflect, Serialize)]
+pub enum BitmapOutOfBounds {
+    /// The layer is transparent outside of the bitmap.
+    #[default]
+    Transparent,
+    /// Repeat the edge pixels.
+    Clamp,
+    /// Repeat the whole bitmap.
+    Tile,
+}
+
+impl BitmapOutOfBounds {
+    pub const ITEMS: [Self; 3] = [Self::Transparent, Self::Clamp, Self::Tile];
+}
+
+impl ToString for BitmapOutOfBounds {
+    fn to_string(&self) -> String {
+        (match self {
+            Self::Transparent => "Transparent",
+            Self::Clamp => "Clamp",
+            Self::Tile => "Tile",
+        })
+        .into()
+    }
+}
+
+/// Serialized form of [BitmapImage].
+#[derive(Deserialize, Serialize)]
+struct BitmapImageData {
+    path: Option<PathBuf>,
+    width: u32,
+    height: u32,
+    pixels: Vec<u16>,
+}
+
+impl From<BitmapImage> for BitmapImageData {
+    fn from(value: BitmapImage) -> Self {
+        Self {
+            path: value.path,
+            width: value.width,
+            height: value.height,
+            pixels: value.pixels.to_vec(),
+        }
+    }
+}
+
+#[inline]
+fn catmull_rom(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    p1 + 0.5
+        * t
+        * (p2 - p0 + t * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3 + t * (3.0 * (p1 - p2) + p3 - p0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{approx_eq, ONE_IN_TEN_THOUSAND};
+
+    fn checkerboard() -> BitmapImage {
+        BitmapImage {
+            path: None,
+            width: 2,
+            height: 2,
+            pixels: vec![0, u16::MAX, u16::MAX, 0].into(),
+        }
+    }
+
+    #[test]
+    fn empty_image_has_no_samples() {
+        let image = BitmapImage::default();
+        assert_eq!(
+            image.sample(
+                Vec2::splat(0.5),
+                BitmapInterpolation::Bilinear,
+                BitmapOutOfBounds::Clamp
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn sampling_texel_centers_returns_texel_values() {
+        let image = checkerboard();
+        for interpolation in BitmapInterpolation::ITEMS {
+            for (uv, expected) in [
+                (Vec2::new(0.25, 0.25), 0.0),
+                (Vec2::new(0.75, 0.25), 1.0),
+                (Vec2::new(0.25, 0.75), 1.0),
+                (Vec2::new(0.75, 0.75), 0.0),
+            ] {
+                let value = image
+                    .sample(uv, interpolation, BitmapOutOfBounds::Clamp)
+                    .unwrap();
+                assert!(approx_eq(value, expected, ONE_IN_TEN_THOUSAND));
+            }
+        }
+    }
+
+    #[test]
+    fn bilinear_interpolation_blends_neighbours() {
+        let value = checkerboard()
+            .sample(
+                Vec2::splat(0.5),
+                BitmapInterpolation::Bilinear,
+                BitmapOutOfBounds::Clamp,
+            )
+            .unwrap();
+        assert!(approx_eq(value, 0.5, ONE_IN_TEN_THOUSAND));
+    }
+
+    #[test]
+    fn out_of_bounds_behaviors() {
+        let image = checkerboard();
+        let uv = Vec2::new(1.25, 0.25);
+        assert_eq!(
+            image.sample(
+                uv,
+                BitmapInterpolation::Bilinear,
+                BitmapOutOfBounds::Transparent
+            ),
+            None
+        );
+        let clamped = image
+            .sample(uv, BitmapInterpolation::Bilinear, BitmapOutOfBounds::Clamp)
+            .unwrap();
+        assert!(approx_eq(clamped, 1.0, ONE_IN_TEN_THOUSAND));
+        let tiled = image
+            .sample(uv, BitmapInterpolation::Bilinear, BitmapOutOfBounds::Tile)
+            .unwrap();
+        assert!(approx_eq(tiled, 0.0, ONE_IN_TEN_THOUSAND));
+    }
+
+    #[test]
+    fn truncated_bitmap_data_is_rejected() {
+        let data = BitmapImageData {
+            path: None,
+            width: 2,
+            height: 2,
+            pixels: vec![0, u16::MAX, u16::MAX],
+        };
+        assert!(matches!(
+            BitmapImage::try_from(data),
+            Err(BitmapError::SizeMismatch { pixels: 3, .. })
+        ));
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License along
 // with Yer.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::f32::consts::TAU;
 use std::fmt::{self, Display};
 use std::ops::RangeInclusive;
 
@@ -22,7 +23,12 @@ use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::id::LayerId;
-use crate::math::{Alpha, Sample, Sampler2D};
+use crate::math::{
+    clamp, deserialize_ratio, fbm_2d, Alpha, Channel, Filter, HeightGrid, Sample, Sampler2D,
+};
+
+use super::bitmap::{BitmapImage, BitmapInterpolation, BitmapOutOfBounds};
+use super::erosion;
 
 pub const HEIGHT_RANGE: RangeInclusive<f32> = -16000.0..=64000.0;
 pub const LAYER_SPACING: u32 = 100;
@@ -96,7 +102,111 @@ impl Default for LayerBundle {
 #[derive(Component, Clone, Debug, Deserialize, Reflect, Serialize)]
 #[require(Layer)]
 pub enum HeightMap {
+    Bitmap {
+        black_height: f32,
+        image: BitmapImage,
+        interpolation: BitmapInterpolation,
+        out_of_bounds: BitmapOutOfBounds,
+        position: Vec2,
+        rotation: f32,
+        size: Vec2,
+        white_height: f32,
+    },
     Constant(f32),
+    /// Erodes the heights beneath this layer.
+    HydraulicErosion {
+        deposition_rate: f32,
+        erosion_rate: f32,
+        /// Number of droplets per sample.
+        iterations: u32,
+        /// Initial water amount of a droplet.
+        rain_amount: f32,
+        sediment_capacity: f32,
+        seed: u32,
+    },
+    Noise {
+        amplitude: f32,
+        frequency: f32,
+        gain: f32,
+        lacunarity: f32,
+        octaves: u8,
+        offset: f32,
+        seed: u32,
+    },
+    Summit {
+        base_radius: f32,
+        peak_height: f32,
+        position: Vec2,
+        profile: SummitProfile,
+        ridge_frequency: f32,
+        ridge_strength: f32,
+        seed: u32,
+    },
+    /// Moves material downhill where slopes are steeper than the talus angle.
+    ThermalErosion {
+        /// Talus angle, in degrees.
+        angle: f32,
+        iterations: u32,
+        strength: f32,
+    },
+}
+
+impl HeightMap {
+    pub fn bitmap() -> Self {
+        Self::Bitmap {
+            black_height: 0.0,
+            image: BitmapImage::default(),
+            interpolation: BitmapInterpolation::default(),
+            out_of_bounds: BitmapOutOfBounds::default(),
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            size: Vec2::splat(100.0),
+            white_height: 10.0,
+        }
+    }
+
+    pub fn hydraulic_erosion() -> Self {
+        Self::HydraulicErosion {
+            deposition_rate: 0.3,
+            erosion_rate: 0.3,
+            iterations: 1,
+            rain_amount: 1.0,
+            sediment_capacity: 4.0,
+            seed: 0,
+        }
+    }
+
+    pub fn noise() -> Self {
+        Self::Noise {
+            amplitude: 10.0,
+            frequency: 0.02,
+            gain: 0.5,
+            lacunarity: 2.0,
+            octaves: 4,
+            offset: 0.0,
+            seed: 0,
+        }
+    }
+
+    pub fn summit() -> Self {
+        Self::Summit {
+            base_radius: 50.0,
+            peak_height: 100.0,
+            position: Vec2::ZERO,
+            profile: SummitProfile::default(),
+            ridge_frequency: 0.05,
+            ridge_strength: 0.0,
+            seed: 0,
+        }
+    }
+
+    pub fn thermal_erosion() -> Self {
+        Self::ThermalErosion {
+            angle: 35.0,
+            iterations: 50,
+            strength: 0.5,
+        }
+    }
 }
 
 impl Default for HeightMap {
@@ -106,18 +216,130 @@ impl Default for HeightMap {
 }
 
 impl Sampler2D for HeightMap {
-    fn sample(&self, _position: Vec2, _base: &Sample) -> Sample {
+    fn sample(&self, position: Vec2, base: &Sample) -> Sample {
         match self {
+            Self::Bitmap {
+                black_height,
+                image,
+                interpolation,
+                out_of_bounds,
+                position: center,
+                rotation,
+                size,
+                white_height,
+            } => {
+                let local: Vec2 = Vec2::from_angle(-*rotation * TAU).rotate(position - *center);
+                // Image rows go from top to bottom, Y is inverted.
+                let uv: Vec2 = Vec2::new(local.x / size.x + 0.5, 0.5 - local.y / size.y);
+                match image.sample(uv, *interpolation, *out_of_bounds) {
+                    Some(value) => {
+                        Sample::new(black_height.lerp(*white_height, value), Alpha::Opaque)
+                    }
+                    None => Sample::new(*black_height, Alpha::from_factor(0.0)),
+                }
+            }
             Self::Constant(value) => Sample::new(*value, Alpha::Opaque),
+            // Filters cannot be sampled point-wise, see [Self::filter].
+            Self::HydraulicErosion { .. } | Self::ThermalErosion { .. } => {
+                Sample::new(base.height(), Alpha::from_factor(0.0))
+            }
+            Self::Noise {
+                amplitude,
+                frequency,
+                gain,
+                lacunarity,
+                octaves,
+                offset,
+                seed,
+            } => {
+                let noise: f32 = fbm_2d(position * *frequency, *seed, *octaves, *lacunarity, *gain);
+                Sample::new(offset + noise * amplitude, Alpha::Opaque)
+            }
+            Self::Summit {
+                base_radius,
+                peak_height,
+                position: center,
+                profile,
+                ridge_frequency,
+                ridge_strength,
+                seed,
+            } => {
+                // The summit is blended over the terrain beneath it, so that
+                // its base merges with the existing heights.
+                let t: f32 = clamp(position.distance(*center) / base_radius, 0.0, 1.0);
+                let mut factor: f32 = profile.sample(t);
+                if *ridge_strength > 0.0 && factor > 0.0 {
+                    const RIDGE_OCTAVES: u8 = 4;
+                    let ridge: f32 = 1.0
+                        - fbm_2d(position * *ridge_frequency, *seed, RIDGE_OCTAVES, 2.0, 0.5).abs();
+                    factor *= 1.0 - ridge_strength * (1.0 - ridge);
+                }
+                Sample::new(*peak_height, Alpha::from_factor(clamp(factor, 0.0, 1.0)))
+            }
         }
     }
+
+    fn filter<'a>(
+        &'a self,
+        grid: &HeightGrid,
+        _base_samples: &'a [Sample],
+    ) -> Option<Box<dyn Filter + 'a>> {
+        match self {
+            Self::HydraulicErosion {
+                deposition_rate,
+                erosion_rate,
+                iterations,
+                rain_amount,
+                sediment_capacity,
+                seed,
+            } => Some(Box::new(erosion::HydraulicErosion::new(
+                grid.clone(),
+                *deposition_rate,
+                *erosion_rate,
+                *iterations,
+                *rain_amount,
+                *sediment_capacity,
+                *seed,
+            ))),
+            Self::ThermalErosion {
+                angle,
+                iterations,
+                strength,
+            } => Some(Box::new(erosion::ThermalErosion::new(
+                grid.clone(),
+                *angle,
+                *iterations,
+                *strength,
+            ))),
+            _ => None,
+        }
+    }
+
+    fn is_filter(&self) -> bool {
+        matches!(
+            self,
+            Self::HydraulicErosion { .. } | Self::ThermalErosion { .. }
+        )
+    }
 }
 
 #[derive(Component, Clone, Debug, Deserialize, Reflect, Serialize)]
 pub struct Layer {
     pub name: String,
+    #[serde(default)]
+    pub blend_mode: LayerBlendMode,
+    /// Channels, other than the height, the layer writes its height map to.
+    #[serde(default)]
+    pub channels: Vec<LayerChannel>,
     pub enable_baking: bool,
     pub enable_preview: bool,
+    /// Multiplies the alpha of the layer, after masks are applied.
+    #[serde(default = "default_opacity", deserialize_with = "deserialize_ratio")]
+    pub opacity: f32,
+    /// Layers that only write other channels, such as moisture, leave the
+    /// height as it is.
+    #[serde(default = "default_write_height")]
+    pub write_height: bool,
     id: LayerId,
 }
 
@@ -133,8 +355,12 @@ impl Layer {
     pub(super) fn new(id: LayerId) -> Self {
         Self {
             name: DEFAULT_LAYER_NAME.to_owned(),
+            blend_mode: LayerBlendMode::default(),
+            channels: vec![],
             enable_baking: true,
             enable_preview: true,
+            opacity: 1.0,
+            write_height: true,
             id,
         }
     }
@@ -173,6 +399,156 @@ pub struct NeedsLayerOrderNormalization;
 
 // LIB
 
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn default_write_height() -> bool {
+    true
+}
+
+/// A channel a layer writes to and how it is blended with the channel
+/// beneath.
+#[derive(Clone, Debug, Deserialize, PartialEq, Reflect, Serialize)]
+pub struct LayerChannel {
+    pub blend_mode: LayerBlendMode,
+    pub channel: Channel,
+}
+
+impl LayerChannel {
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            blend_mode: LayerBlendMode::default(),
+            channel,
+        }
+    }
+}
+
+/// How a layer's height is combined with the heights beneath it.
+///
+/// Blending is applied before alpha compositing, so masks fade the blended
+/// result in and out.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Reflect, Serialize)]
+pub enum LayerBlendMode {
+    #[default]
+    Normal,
+    Add,
+    Subtract,
+    Multiply,
+    Max,
+    Min,
+    Difference,
+}
+
+impl LayerBlendMode {
+    pub const ITEMS: [Self; 7] = [
+        Self::Normal,
+        Self::Add,
+        Self::Subtract,
+        Self::Multiply,
+        Self::Max,
+        Self::Min,
+        Self::Difference,
+    ];
+
+    pub fn blend(&self, base: f32, height: f32) -> f32 {
+        match self {
+            Self::Normal => height,
+            Self::Add => base + height,
+            Self::Subtract => base - height,
+            Self::Multiply => base * height,
+            Self::Max => base.max(height),
+            Self::Min => base.min(height),
+            Self::Difference => (base - height).abs(),
+        }
+    }
+}
+
+impl ToString for LayerBlendMode {
+    fn to_string(&self) -> String {
+        (match self {
+            Self::Normal => "Normal",
+            Self::Add => "Add",
+            Self::Subtract => "Subtract",
+            Self::Multiply => "Multiply",
+            Self::Max => "Max",
+            Self::Min => "Min",
+            Self::Difference => "Difference",
+        })
+        .into()
+    }
+}
+
+/// Shape of a summit's slope, from its peak to its base.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Reflect, Serialize)]
+pub enum SummitProfile {
+    Cone,
+    Dome,
+    #[default]
+    Bell,
+}
+
+impl SummitProfile {
+    pub const ITEMS: [Self; 3] = [Self::Cone, Self::Dome, Self::Bell];
+
+    /// Relative height at normalized distance `t` from the peak.
+    ///
+    /// `t` is 0.0 at the peak and 1.0 at the base.
+    pub fn sample(&self, t: f32) -> f32 {
+        match self {
+            Self::Cone => 1.0 - t,
+            Self::Dome => (1.0 - t * t).max(0.0).sqrt(),
+            Self::Bell => SmootherStepCurve.sample_clamped(1.0 - t),
+        }
+    }
+}
+
+impl ToString for SummitProfile {
+    fn to_string(&self) -> String {
+        (match self {
+            Self::Cone => "Cone",
+            Self::Dome => "Dome",
+            Self::Bell => "Bell",
+        })
+        .into()
+    }
+}
+
 fn layer_order_on_remove_hook(mut world: DeferredWorld, HookContext { .. }: HookContext) {
     world.commands().spawn(NeedsLayerOrderNormalization);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{approx_eq, ONE_IN_TEN_THOUSAND};
+
+    #[test]
+    fn layer_blend_modes() {
+        let (base, height) = (3.0, 5.0);
+        assert_eq!(LayerBlendMode::Normal.blend(base, height), 5.0);
+        assert_eq!(LayerBlendMode::Add.blend(base, height), 8.0);
+        assert_eq!(LayerBlendMode::Subtract.blend(base, height), -2.0);
+        assert_eq!(LayerBlendMode::Multiply.blend(base, height), 15.0);
+        assert_eq!(LayerBlendMode::Max.blend(base, height), 5.0);
+        assert_eq!(LayerBlendMode::Min.blend(base, height), 3.0);
+        assert_eq!(LayerBlendMode::Difference.blend(base, height), 2.0);
+    }
+
+    #[test]
+    fn summit_profiles_go_from_peak_to_base() {
+        for profile in SummitProfile::ITEMS {
+            assert!(approx_eq(profile.sample(0.0), 1.0, ONE_IN_TEN_THOUSAND));
+            assert!(approx_eq(profile.sample(1.0), 0.0, ONE_IN_TEN_THOUSAND));
+        }
+    }
+
+    #[test]
+    fn summit_is_transparent_beyond_its_base() {
+        let summit = HeightMap::summit();
+        let sample = summit.sample(Vec2::new(1000.0, 0.0), &Sample::default());
+        assert!(approx_eq(sample.alpha().factor(), 0.0, ONE_IN_TEN_THOUSAND));
+        let sample = summit.sample(Vec2::ZERO, &Sample::default());
+        assert!(sample.alpha().is_opaque());
+    }
+}
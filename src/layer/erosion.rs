@@ -0,0 +1,398 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::f32::consts::SQRT_2;
+
+use bevy::prelude::*;
+
+use crate::math::{Filter, HeightGrid, Random};
+
+const DROPLET_EVAPORATION_RATE: f32 = 0.01;
+const DROPLET_GRAVITY: f32 = 4.0;
+const DROPLET_INERTIA: f32 = 0.05;
+const DROPLET_MAX_STEPS: u32 = 32;
+const DROPLET_MIN_SLOPE: f32 = 0.01;
+/// Droplets processed between the checks for cancellation.
+const DROPLETS_PER_STEP: u64 = 4096;
+/// Offsets of the neighbours of a cell and their distances.
+const NEIGHBOURS: [(i64, i64, f32); 8] = [
+    (-1, -1, SQRT_2),
+    (0, -1, 1.0),
+    (1, -1, SQRT_2),
+    (-1, 0, 1.0),
+    (1, 0, 1.0),
+    (-1, 1, SQRT_2),
+    (0, 1, 1.0),
+    (1, 1, SQRT_2),
+];
+
+// LIB
+
+/// Particle based hydraulic erosion.
+///
+/// Droplets are dropped on random cells, `iterations` droplets per cell.
+/// Each droplet flows downhill, picking up sediment on steep slopes and
+/// depositing it where it slows down or flows uphill.
+///
+/// Heights are measured in units of grid spacing while droplets travel, so
+/// that the result does not depend much on the resolution of the grid.  The
+/// same grid and the same `seed` always produce the same result.
+pub struct HydraulicErosion {
+    deposition_rate: f32,
+    droplets_count: u64,
+    droplets_done: u64,
+    erosion_rate: f32,
+    grid: HeightGrid,
+    rain_amount: f32,
+    random: Random,
+    sediment_capacity: f32,
+    terrain: Terrain,
+}
+
+impl HydraulicErosion {
+    pub fn new(
+        grid: HeightGrid,
+        deposition_rate: f32,
+        erosion_rate: f32,
+        iterations: u32,
+        rain_amount: f32,
+        sediment_capacity: f32,
+        seed: u32,
+    ) -> Self {
+        let UVec2 { x: w, y: h } = grid.size();
+        let spacing: f32 = grid.spacing();
+        let is_degenerate: bool = w < 2 || h < 2 || spacing <= 0.0;
+        Self {
+            deposition_rate,
+            droplets_count: if is_degenerate {
+                0
+            } else {
+                u64::from(iterations) * u64::from(w) * u64::from(h)
+            },
+            droplets_done: 0,
+            erosion_rate,
+            rain_amount,
+            random: Random::new(seed.into()),
+            sediment_capacity,
+            terrain: Terrain {
+                heights: if is_degenerate {
+                    vec![]
+                } else {
+                    grid.heights().iter().map(|h| h / spacing).collect()
+                },
+                width: w as usize,
+            },
+            grid,
+        }
+    }
+
+    fn drop_droplet(&mut self) {
+        let UVec2 { x: w, y: h } = self.grid.size();
+        let max: Vec2 = Vec2::new((w - 1) as f32, (h - 1) as f32);
+        let terrain: &mut Terrain = &mut self.terrain;
+        let mut position: Vec2 = Vec2::new(self.random.next_f32(), self.random.next_f32()) * max;
+        let mut direction: Vec2 = Vec2::ZERO;
+        let mut speed: f32 = 1.0;
+        let mut water: f32 = self.rain_amount;
+        let mut sediment: f32 = 0.0;
+        for _ in 0..DROPLET_MAX_STEPS {
+            let (height, gradient) = terrain.height_and_gradient(position);
+            direction = (direction * DROPLET_INERTIA - gradient * (1.0 - DROPLET_INERTIA))
+                .normalize_or_zero();
+            if direction == Vec2::ZERO {
+                // Droplet is stuck in a pit or on a flat area.
+                terrain.add(position, sediment);
+                break;
+            }
+            let new_position: Vec2 = position + direction;
+            if new_position.cmplt(Vec2::ZERO).any() || new_position.cmpge(max).any() {
+                // Droplet has left the grid, the sediment is lost.
+                break;
+            }
+            let delta: f32 = terrain.height_and_gradient(new_position).0 - height;
+            let capacity: f32 =
+                (-delta).max(DROPLET_MIN_SLOPE) * speed * water * self.sediment_capacity;
+            if delta > 0.0 || sediment > capacity {
+                // Fill the pit when going uphill, otherwise drop some of
+                // the excess sediment.
+                let amount: f32 = if delta > 0.0 {
+                    delta.min(sediment)
+                } else {
+                    (sediment - capacity) * self.deposition_rate
+                };
+                sediment -= amount;
+                terrain.add(position, amount);
+            } else {
+                // Do not dig deeper than the height difference.
+                let amount: f32 = ((capacity - sediment) * self.erosion_rate).min(-delta);
+                sediment += amount;
+                terrain.add(position, -amount);
+            }
+            speed = (speed * speed - delta * DROPLET_GRAVITY).max(0.0).sqrt();
+            water *= 1.0 - DROPLET_EVAPORATION_RATE;
+            position = new_position;
+        }
+    }
+}
+
+impl Filter for HydraulicErosion {
+    fn step(&mut self) -> bool {
+        let end: u64 = self
+            .droplets_count
+            .min(self.droplets_done + DROPLETS_PER_STEP);
+        while self.droplets_done < end {
+            self.drop_droplet();
+            self.droplets_done += 1;
+        }
+        self.droplets_done < self.droplets_count
+    }
+
+    fn progress(&self) -> f32 {
+        if self.droplets_count == 0 {
+            1.0
+        } else {
+            self.droplets_done as f32 / self.droplets_count as f32
+        }
+    }
+
+    fn finish(mut self: Box<Self>) -> HeightGrid {
+        while self.step() {}
+        let spacing: f32 = self.grid.spacing();
+        let Self {
+            mut grid, terrain, ..
+        } = *self;
+        grid.heights_mut()
+            .iter_mut()
+            .zip(terrain.heights)
+            .for_each(|(height, h)| *height = h * spacing);
+        grid
+    }
+}
+
+/// Thermal weathering.
+///
+/// Wherever the slope between two neighbouring cells is steeper than the
+/// talus `angle` (in degrees) material slides down, until the slope settles
+/// at the talus angle.  `strength` is the ratio of the excess material that
+/// moves in one iteration.
+///
+/// All cells are updated at once at each iteration, so the result does not
+/// depend on the order cells are processed.  Total height is preserved.
+pub struct ThermalErosion {
+    deltas: Vec<f32>,
+    grid: HeightGrid,
+    iterations: u32,
+    iterations_done: u32,
+    strength: f32,
+    /// Maximum stable height difference between adjacent cells.
+    talus: f32,
+}
+
+impl ThermalErosion {
+    pub fn new(grid: HeightGrid, angle: f32, iterations: u32, strength: f32) -> Self {
+        let UVec2 { x: w, y: h } = grid.size();
+        let spacing: f32 = grid.spacing();
+        let is_degenerate: bool = w < 2 || h < 2 || spacing <= 0.0;
+        Self {
+            deltas: vec![0.0; grid.heights().len()],
+            iterations: if is_degenerate { 0 } else { iterations },
+            iterations_done: 0,
+            strength,
+            talus: angle.to_radians().tan() * spacing,
+            grid,
+        }
+    }
+}
+
+impl Filter for ThermalErosion {
+    /// Run one iteration over the whole grid.
+    fn step(&mut self) -> bool {
+        if self.iterations_done >= self.iterations {
+            return false;
+        }
+        let UVec2 { x: w, y: h } = self.grid.size();
+        let grid: &mut HeightGrid = &mut self.grid;
+        let deltas: &mut Vec<f32> = &mut self.deltas;
+        deltas.fill(0.0);
+        for y in 0..h {
+            for x in 0..w {
+                let idx: usize = grid.index(x, y);
+                let height: f32 = grid.heights()[idx];
+                let mut excesses: [Option<(usize, f32)>; 8] = [None; 8];
+                let (mut total_excess, mut max_excess) = (0.0f32, 0.0f32);
+                for (excess, (dx, dy, distance)) in excesses.iter_mut().zip(NEIGHBOURS) {
+                    let (nx, ny) = (i64::from(x) + dx, i64::from(y) + dy);
+                    if nx < 0 || ny < 0 || nx >= i64::from(w) || ny >= i64::from(h) {
+                        continue;
+                    }
+                    let neighbour_idx: usize = grid.index(nx as u32, ny as u32);
+                    let e: f32 = height - grid.heights()[neighbour_idx] - self.talus * distance;
+                    if e > 0.0 {
+                        *excess = Some((neighbour_idx, e));
+                        total_excess += e;
+                        max_excess = max_excess.max(e);
+                    }
+                }
+                if total_excess > 0.0 {
+                    // Moving half of the excess levels the two cells, moving
+                    // more would overshoot.
+                    let amount: f32 = self.strength * max_excess * 0.5;
+                    deltas[idx] -= amount;
+                    for (neighbour_idx, e) in excesses.into_iter().flatten() {
+                        deltas[neighbour_idx] += amount * e / total_excess;
+                    }
+                }
+            }
+        }
+        grid.heights_mut()
+            .iter_mut()
+            .zip(deltas.iter())
+            .for_each(|(height, delta)| *height += delta);
+        self.iterations_done += 1;
+        self.iterations_done < self.iterations
+    }
+
+    fn progress(&self) -> f32 {
+        if self.iterations == 0 {
+            1.0
+        } else {
+            self.iterations_done as f32 / self.iterations as f32
+        }
+    }
+
+    fn finish(mut self: Box<Self>) -> HeightGrid {
+        while self.step() {}
+        self.grid
+    }
+}
+
+/// Heights in grid units, sampled bilinearly.
+struct Terrain {
+    heights: Vec<f32>,
+    width: usize,
+}
+
+impl Terrain {
+    /// Distribute `amount` to the four corners of the cell at `position`.
+    fn add(&mut self, position: Vec2, amount: f32) {
+        let (idx, t) = self.cell(position);
+        self.heights[idx] += amount * (1.0 - t.x) * (1.0 - t.y);
+        self.heights[idx + 1] += amount * t.x * (1.0 - t.y);
+        self.heights[idx + self.width] += amount * (1.0 - t.x) * t.y;
+        self.heights[idx + self.width + 1] += amount * t.x * t.y;
+    }
+
+    /// Index of the top left corner of the cell and the position within.
+    fn cell(&self, position: Vec2) -> (usize, Vec2) {
+        let cell: Vec2 = position.floor();
+        let idx: usize = cell.y as usize * self.width + cell.x as usize;
+        (idx, position - cell)
+    }
+
+    fn height_and_gradient(&self, position: Vec2) -> (f32, Vec2) {
+        let (idx, t) = self.cell(position);
+        let nw: f32 = self.heights[idx];
+        let ne: f32 = self.heights[idx + 1];
+        let sw: f32 = self.heights[idx + self.width];
+        let se: f32 = self.heights[idx + self.width + 1];
+        let height: f32 = nw.lerp(ne, t.x).lerp(sw.lerp(se, t.x), t.y);
+        let gradient = Vec2::new((ne - nw).lerp(se - sw, t.y), (sw - nw).lerp(se - ne, t.x));
+        (height, gradient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{approx_eq, ONE_IN_TEN_THOUSAND};
+
+    fn slope(k: u32) -> HeightGrid {
+        let heights: Vec<f32> = (0..k * k).map(|idx| (idx % k) as f32 * 2.0).collect();
+        HeightGrid::new(Vec2::ZERO, 1.0, UVec2::splat(k), heights)
+    }
+
+    #[test]
+    fn hydraulic_erosion_is_deterministic() {
+        let erode =
+            || Box::new(HydraulicErosion::new(slope(17), 0.3, 0.3, 1, 1.0, 4.0, 42)).finish();
+        let a = erode();
+        let b = erode();
+        assert_eq!(a, b);
+        assert_ne!(a, slope(17));
+    }
+
+    #[test]
+    fn hydraulic_erosion_does_not_change_flat_terrain() {
+        let flat = HeightGrid::new(Vec2::ZERO, 1.0, UVec2::splat(9), vec![5.0; 81]);
+        let eroded = Box::new(HydraulicErosion::new(
+            flat.clone(),
+            0.3,
+            0.3,
+            2,
+            1.0,
+            4.0,
+            7,
+        ))
+        .finish();
+        assert_eq!(eroded, flat);
+    }
+
+    #[test]
+    fn hydraulic_erosion_runs_in_steps() {
+        let erode = || HydraulicErosion::new(slope(65), 0.3, 0.3, 1, 1.0, 4.0, 42);
+        let mut stepped = erode();
+        let mut steps: u32 = 1;
+        assert_eq!(stepped.progress(), 0.0);
+        while stepped.step() {
+            assert!(stepped.progress() < 1.0);
+            steps += 1;
+        }
+        assert_eq!(stepped.progress(), 1.0);
+        assert_eq!(steps, (65 * 65u64).div_ceil(DROPLETS_PER_STEP) as u32);
+        assert_eq!(Box::new(stepped).finish(), Box::new(erode()).finish());
+    }
+
+    #[test]
+    fn thermal_erosion_flattens_cliffs_and_preserves_material() {
+        let k: u32 = 9;
+        // A cliff of 10 units in the middle.
+        let heights: Vec<f32> = (0..k * k)
+            .map(|idx| if idx % k < k / 2 { 10.0 } else { 0.0 })
+            .collect();
+        let cliff = HeightGrid::new(Vec2::ZERO, 1.0, UVec2::splat(k), heights);
+        let eroded = Box::new(ThermalErosion::new(cliff.clone(), 30.0, 50, 0.5)).finish();
+        let max_difference = |grid: &HeightGrid| {
+            (0..k - 1)
+                .map(|x| (grid.get(x, 0) - grid.get(x + 1, 0)).abs())
+                .fold(0.0f32, f32::max)
+        };
+        assert!(max_difference(&eroded) < max_difference(&cliff));
+        let total = |grid: &HeightGrid| grid.heights().iter().sum::<f32>();
+        assert!(approx_eq(
+            total(&eroded),
+            total(&cliff),
+            ONE_IN_TEN_THOUSAND
+        ));
+    }
+
+    #[test]
+    fn thermal_erosion_does_not_change_slopes_below_talus_angle() {
+        let original = slope(9);
+        // Slope is 2.0, about 63 degrees.
+        let grid = Box::new(ThermalErosion::new(original.clone(), 70.0, 10, 1.0)).finish();
+        assert_eq!(grid, original);
+    }
+}
@@ -20,15 +20,20 @@ use bevy::ecs::{lifecycle::HookContext, world::DeferredWorld};
 use bevy::math::Affine2;
 use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 
-use crate::id::{LayerId, MaskId};
+use crate::id::{BiomeId, LayerId, MaskId};
 // FIXME: Circular dependency
 use crate::layer::Layer;
-use crate::math::clamp;
+use crate::math::{clamp, deserialize_ratio, fbm_2d, slope_angle, Channel, Sample};
 use crate::undo::{Action, ReflectAction};
 
+use super::bitmap::{BitmapImage, BitmapInterpolation, BitmapOutOfBounds};
+
 pub const MASK_SPACING: u32 = 100;
+const NOISE_GAIN: f32 = 0.5;
+const NOISE_LACUNARITY: f32 = 2.0;
+const SPLINE_SUBDIVISIONS: usize = 8;
 
 // PLUGIN
 
@@ -55,11 +60,44 @@ impl Plugin for MaskPlugin {
 // BUNDLES
 
 #[derive(Bundle, Clone, Debug, Deserialize, Reflect, Serialize)]
+#[serde(from = "MaskBundleData")]
 pub struct MaskBundle {
     pub mask: Mask,
     pub mask_source: MaskSource,
 }
 
+/// Migrates the invert flag of bitmaps to [Mask::invert].
+impl From<MaskBundleData> for MaskBundle {
+    fn from(data: MaskBundleData) -> Self {
+        let MaskBundleData {
+            mut mask,
+            mut mask_source,
+        } = data;
+        if let MaskSource::Bitmap {
+            legacy_invert,
+            out_of_bounds_value,
+            ..
+        } = &mut mask_source
+        {
+            if std::mem::take(legacy_invert) {
+                // Bitmaps were inverted before the curve and only inside the
+                // image, masks are inverted after the curve.
+                mask.curve = mask.curve.mirrored();
+                mask.invert = !mask.invert;
+                *out_of_bounds_value = 1.0 - *out_of_bounds_value;
+            }
+        }
+        Self { mask, mask_source }
+    }
+}
+
+/// [MaskBundle] as it is saved.
+#[derive(Deserialize)]
+struct MaskBundleData {
+    mask: Mask,
+    mask_source: MaskSource,
+}
+
 impl MaskBundle {
     pub fn extract_all(world: &mut World) -> HashMap<LayerId, Vec<Self>> {
         let mut result: HashMap<LayerId, Vec<Self>> = HashMap::new();
@@ -113,13 +151,27 @@ impl MaskBundle {
 #[derive(Clone, Component, Debug, Deserialize, Reflect, Serialize)]
 pub struct Mask {
     pub composition_mode: MaskCompositionMode,
+    /// Remaps the mask source, before it is inverted.
+    #[serde(default)]
+    pub curve: MaskCurve,
+    #[serde(default)]
+    pub invert: bool,
     pub is_enabled: bool,
+    #[serde(deserialize_with = "deserialize_ratio")]
     pub strength: f32,
     id: MaskId,
 }
 
 impl Mask {
     pub fn combine(&self, baseline: Option<f32>, sample: f32) -> f32 {
+        let sample: f32 = {
+            let remapped: f32 = self.curve.sample(sample);
+            if self.invert {
+                1.0 - remapped
+            } else {
+                remapped
+            }
+        };
         if let Some(baseline) = baseline {
             self.composition_mode
                 .combine(baseline, sample * self.strength)
@@ -141,6 +193,8 @@ impl Default for Mask {
     fn default() -> Self {
         Self {
             composition_mode: MaskCompositionMode::default(),
+            curve: MaskCurve::default(),
+            invert: false,
             is_enabled: true,
             strength: 1.0,
             id: Self::new_id(),
@@ -180,27 +234,120 @@ struct NeedsMaskOrderNormalization;
 #[derive(Clone, Component, Debug, Deserialize, Reflect, Serialize)]
 #[require(Mask)]
 pub enum MaskSource {
+    /// Selects the samples that are classified as one of the `biomes`.
+    Biome { biomes: Vec<BiomeId> },
+    Bitmap {
+        /// Values below black point are mapped to 0.0.
+        black_point: f32,
+        center: Vec2,
+        image: BitmapImage,
+        /// Bitmaps had their own invert flag before [Mask::invert].  It is
+        /// only read from old saves, [MaskBundle] moves it to the mask.
+        #[reflect(ignore)]
+        #[serde(default, rename = "invert", skip_serializing)]
+        legacy_invert: bool,
+        /// Value of the mask outside of the image.
+        #[serde(deserialize_with = "deserialize_ratio")]
+        out_of_bounds_value: f32,
+        rotation: f32,
+        /// Length of the longer side of the image.
+        size: f32,
+        transform: Affine2,
+        /// Values above white point are mapped to 1.0.
+        white_point: f32,
+    },
     Circle {
         center: Vec2,
         falloff_radius: f32,
         irregularity: f32,
         radius: f32,
         rotation: f32,
+        #[serde(deserialize_with = "deserialize_ratio")]
         smoothness: f32,
         transform: Affine2,
     },
+    /// Fades in from 0.0 at `start` to 1.0 at `end`.
+    Gradient {
+        /// Remaps the ramp, after it is smoothed.
+        #[serde(default)]
+        curve: MaskCurve,
+        end: Vec2,
+        shape: GradientShape,
+        #[serde(deserialize_with = "deserialize_ratio")]
+        smoothness: f32,
+        start: Vec2,
+    },
+    /// Selects the terrain beneath the layer by its height.
+    Height {
+        /// Distance from `[min, max]` where the mask fades out.
+        falloff: f32,
+        max: f32,
+        min: f32,
+    },
+    /// Fractal noise, for organic transitions.
+    Noise {
+        /// Steepness of the transition around `threshold`.
+        contrast: f32,
+        octaves: u8,
+        /// Size of the features in world units.
+        scale: f32,
+        seed: u32,
+        /// Noise value, in `[0.0, 1.0]`, that maps to half strength.
+        threshold: f32,
+    },
+    /// Closed polygon, vertices are in world coordinates.
+    Polygon {
+        #[serde(deserialize_with = "deserialize_ratio")]
+        smoothness: f32,
+        vertices: Vec<MaskVertex>,
+    },
+    /// Selects the terrain beneath the layer by its slope.
+    ///
+    /// Angles are in degrees, 0 is flat.
+    Slope {
+        /// Angle from `[min_angle, max_angle]` where the mask fades out.
+        falloff: f32,
+        max_angle: f32,
+        min_angle: f32,
+    },
+    /// Closed spline passing through its vertices, vertices are in world
+    /// coordinates.
+    Spline {
+        #[serde(deserialize_with = "deserialize_ratio")]
+        smoothness: f32,
+        vertices: Vec<MaskVertex>,
+    },
     Square {
         center: Vec2,
         falloff_radius: f32,
         irregularity: f32,
         rotation: f32,
         size: f32,
+        #[serde(deserialize_with = "deserialize_ratio")]
         smoothness: f32,
         transform: Affine2,
     },
 }
 
 impl MaskSource {
+    pub fn biome() -> Self {
+        Self::Biome { biomes: vec![] }
+    }
+
+    pub fn bitmap() -> Self {
+        Self::Bitmap {
+            black_point: 0.0,
+            center: Vec2::ZERO,
+            image: BitmapImage::default(),
+            legacy_invert: false,
+            out_of_bounds_value: 0.0,
+            rotation: 0.0,
+            size: 10.0,
+            transform: Affine2::default(),
+            white_point: 1.0,
+        }
+    }
+
     pub fn circle() -> Self {
         Self::Circle {
             center: Vec2::ZERO,
@@ -213,6 +360,56 @@ impl MaskSource {
         }
     }
 
+    pub fn gradient() -> Self {
+        Self::Gradient {
+            curve: MaskCurve::default(),
+            end: Vec2::new(1.0, 0.0),
+            shape: GradientShape::default(),
+            smoothness: 1.0,
+            start: Vec2::new(-1.0, 0.0),
+        }
+    }
+
+    pub fn height() -> Self {
+        Self::Height {
+            falloff: 1.0,
+            max: 100.0,
+            min: 0.0,
+        }
+    }
+
+    pub fn noise() -> Self {
+        Self::Noise {
+            contrast: 4.0,
+            octaves: 4,
+            scale: 50.0,
+            seed: 0,
+            threshold: 0.5,
+        }
+    }
+
+    pub fn polygon() -> Self {
+        Self::Polygon {
+            smoothness: 1.0,
+            vertices: MaskVertex::square(2.0, 0.5),
+        }
+    }
+
+    pub fn slope() -> Self {
+        Self::Slope {
+            falloff: 5.0,
+            max_angle: 90.0,
+            min_angle: 45.0,
+        }
+    }
+
+    pub fn spline() -> Self {
+        Self::Spline {
+            smoothness: 1.0,
+            vertices: MaskVertex::square(2.0, 0.5),
+        }
+    }
+
     pub fn square() -> Self {
         Self::Square {
             center: Vec2::ZERO,
@@ -227,8 +424,58 @@ impl MaskSource {
 }
 
 impl MaskSource {
-    pub fn sample(&self, position: Vec2) -> f32 {
+    /// Whether sampling this mask requires the gradient of the terrain
+    /// beneath.
+    pub fn needs_gradient(&self) -> bool {
+        matches!(self, Self::Biome { .. } | Self::Slope { .. })
+    }
+
+    /// Whether sampling this mask requires the base sample to be classified,
+    /// see [BiomeTable::classify_sample](crate::biome::BiomeTable::classify_sample).
+    pub fn needs_biomes(&self) -> bool {
+        matches!(self, Self::Biome { .. })
+    }
+
+    /// Sample the mask at `position`.
+    ///
+    /// `base_sample` is the terrain beneath the layer at `position` and
+    /// `base_gradient` is its gradient.  Gradient is only available when the
+    /// terrain is sampled on a grid, see [Self::needs_gradient].  Terrain is
+    /// treated as flat without it.
+    pub fn sample(&self, position: Vec2, base_sample: &Sample, base_gradient: Option<Vec2>) -> f32 {
         match self {
+            Self::Biome { biomes } => biomes
+                .iter()
+                .map(|id| base_sample.channel(&Channel::Biome(*id)).unwrap_or(0.0))
+                .fold(0.0, f32::max),
+            Self::Bitmap {
+                black_point,
+                image,
+                out_of_bounds_value,
+                size,
+                transform,
+                white_point,
+                ..
+            } => {
+                let extent: Vec2 = {
+                    let image_size: Vec2 = image.size().as_vec2();
+                    image_size / image_size.max_element().max(1.0) * *size
+                };
+                let local: Vec2 = transform.transform_point2(position) / extent;
+                // Image rows go from top to bottom, Y is inverted.
+                let uv: Vec2 = Vec2::new(local.x + 0.5, 0.5 - local.y);
+                match image.sample(
+                    uv,
+                    BitmapInterpolation::Bilinear,
+                    BitmapOutOfBounds::Transparent,
+                ) {
+                    Some(value) => {
+                        let range: f32 = (white_point - black_point).max(f32::EPSILON);
+                        clamp((value - black_point) / range, 0.0, 1.0)
+                    }
+                    None => *out_of_bounds_value,
+                }
+            }
             Self::Circle {
                 falloff_radius,
                 radius,
@@ -241,6 +488,61 @@ impl MaskSource {
                 let smooth_factor = SmootherStepCurve.sample_clamped(factor);
                 factor.lerp(smooth_factor, *smoothness)
             }
+            Self::Gradient {
+                curve,
+                end,
+                shape,
+                smoothness,
+                start,
+            } => {
+                let direction: Vec2 = end - start;
+                let length_squared: f32 = direction.length_squared().max(f32::EPSILON);
+                let t: f32 = match shape {
+                    GradientShape::Linear => (position - start).dot(direction) / length_squared,
+                    GradientShape::Radial => position.distance(*start) / length_squared.sqrt(),
+                };
+                let factor = clamp(t, 0.0, 1.0);
+                let smooth_factor = SmootherStepCurve.sample_clamped(factor);
+                curve.sample(factor.lerp(smooth_factor, *smoothness))
+            }
+            Self::Height { falloff, max, min } => {
+                band_pass(base_sample.height(), *min, *max, *falloff)
+            }
+            Self::Noise {
+                contrast,
+                octaves,
+                scale,
+                seed,
+                threshold,
+            } => {
+                // fbm_2d is in [-1.0, 1.0], map it to [0.0, 1.0].
+                let noise: f32 = fbm_2d(
+                    position / scale.max(f32::EPSILON),
+                    *seed,
+                    *octaves,
+                    NOISE_LACUNARITY,
+                    NOISE_GAIN,
+                ) * 0.5
+                    + 0.5;
+                clamp((noise - threshold) * contrast + 0.5, 0.0, 1.0)
+            }
+            Self::Polygon {
+                smoothness,
+                vertices,
+            } => sample_outline(vertices, position, *smoothness),
+            Self::Slope {
+                falloff,
+                max_angle,
+                min_angle,
+            } => {
+                // Terrain is considered flat where the gradient is unknown.
+                let gradient: Vec2 = base_gradient.unwrap_or(Vec2::ZERO);
+                band_pass(slope_angle(gradient), *min_angle, *max_angle, *falloff)
+            }
+            Self::Spline {
+                smoothness,
+                vertices,
+            } => sample_outline(&spline_outline(vertices), position, *smoothness),
             Self::Square {
                 falloff_radius,
                 size,
@@ -259,18 +561,79 @@ impl MaskSource {
         }
     }
 
+    fn insert_vertex(&mut self, index: usize, vertex: MaskVertex) {
+        self.vertices_mut().insert(index, vertex);
+    }
+
+    fn remove_vertex(&mut self, index: usize) {
+        self.vertices_mut().remove(index);
+    }
+
+    fn set_biomes(&mut self, new_biomes: Vec<BiomeId>) {
+        match self {
+            Self::Biome { biomes } => *biomes = new_biomes,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_black_point(&mut self, new_black_point: f32) {
+        match self {
+            Self::Bitmap { black_point, .. } => *black_point = clamp(new_black_point, 0.0, 1.0),
+            _ => unreachable!(),
+        }
+    }
+
     fn set_center(&mut self, new_center: Vec2) {
         match self {
+            Self::Bitmap { center, .. } => *center = new_center,
             Self::Circle { center, .. } => *center = new_center,
             Self::Square { center, .. } => *center = new_center,
+            _ => unreachable!(),
         }
         self.update_transform();
     }
 
+    fn set_contrast(&mut self, new_contrast: f32) {
+        match self {
+            Self::Noise { contrast, .. } => *contrast = new_contrast.max(0.0),
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_curve(&mut self, new_curve: MaskCurve) {
+        match self {
+            Self::Gradient { curve, .. } => *curve = new_curve,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_end(&mut self, new_end: Vec2) {
+        match self {
+            Self::Gradient { end, .. } => *end = new_end,
+            _ => unreachable!(),
+        }
+    }
+
     fn set_falloff_radius(&mut self, new_radius: f32) {
         match self {
             Self::Circle { falloff_radius, .. } => *falloff_radius = new_radius,
             Self::Square { falloff_radius, .. } => *falloff_radius = new_radius,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_falloff(&mut self, new_falloff: f32) {
+        match self {
+            Self::Height { falloff, .. } => *falloff = new_falloff.max(0.0),
+            Self::Slope { falloff, .. } => *falloff = new_falloff.max(0.0),
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_image(&mut self, new_image: BitmapImage) {
+        match self {
+            Self::Bitmap { image, .. } => *image = new_image,
+            _ => unreachable!(),
         }
     }
 
@@ -279,14 +642,60 @@ impl MaskSource {
         match self {
             Self::Circle { irregularity, .. } => *irregularity = new_irregularity,
             Self::Square { irregularity, .. } => *irregularity = new_irregularity,
+            _ => unreachable!(),
         }
         self.update_transform();
     }
 
+    fn set_max(&mut self, new_max: f32) {
+        match self {
+            Self::Height { max, .. } => *max = new_max,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_max_angle(&mut self, new_max_angle: f32) {
+        match self {
+            Self::Slope { max_angle, .. } => *max_angle = new_max_angle,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_min(&mut self, new_min: f32) {
+        match self {
+            Self::Height { min, .. } => *min = new_min,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_min_angle(&mut self, new_min_angle: f32) {
+        match self {
+            Self::Slope { min_angle, .. } => *min_angle = new_min_angle,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_octaves(&mut self, new_octaves: u8) {
+        match self {
+            Self::Noise { octaves, .. } => *octaves = new_octaves.max(1),
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_out_of_bounds_value(&mut self, new_value: f32) {
+        match self {
+            Self::Bitmap {
+                out_of_bounds_value,
+                ..
+            } => *out_of_bounds_value = clamp(new_value, 0.0, 1.0),
+            _ => unreachable!(),
+        }
+    }
+
     fn set_radius(&mut self, new_radius: f32) {
         match self {
             Self::Circle { radius, .. } => *radius = new_radius,
-            Self::Square { .. } => unreachable!(),
+            _ => unreachable!(),
         }
     }
 
@@ -294,16 +703,40 @@ impl MaskSource {
         // Constrain rotation to [0.0; 1.0)
         new_rotation = new_rotation.fract();
         match self {
+            Self::Bitmap { rotation, .. } => *rotation = new_rotation,
             Self::Circle { rotation, .. } => *rotation = new_rotation,
             Self::Square { rotation, .. } => *rotation = new_rotation,
+            _ => unreachable!(),
         }
         self.update_transform();
     }
 
+    fn set_scale(&mut self, new_scale: f32) {
+        match self {
+            Self::Noise { scale, .. } => *scale = new_scale.max(f32::EPSILON),
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        match self {
+            Self::Noise { seed, .. } => *seed = new_seed,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_shape(&mut self, new_shape: GradientShape) {
+        match self {
+            Self::Gradient { shape, .. } => *shape = new_shape,
+            _ => unreachable!(),
+        }
+    }
+
     fn set_size(&mut self, new_size: f32) {
         match self {
-            Self::Circle { .. } => unreachable!(),
+            Self::Bitmap { size, .. } => *size = new_size,
             Self::Square { size, .. } => *size = new_size,
+            _ => unreachable!(),
         }
     }
 
@@ -311,12 +744,72 @@ impl MaskSource {
         let clamped_smoothness = new_smoothness.clamp(0.0, 1.0);
         match self {
             Self::Circle { smoothness, .. } => *smoothness = clamped_smoothness,
+            Self::Gradient { smoothness, .. } => *smoothness = clamped_smoothness,
+            Self::Polygon { smoothness, .. } => *smoothness = clamped_smoothness,
+            Self::Spline { smoothness, .. } => *smoothness = clamped_smoothness,
             Self::Square { smoothness, .. } => *smoothness = clamped_smoothness,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_start(&mut self, new_start: Vec2) {
+        match self {
+            Self::Gradient { start, .. } => *start = new_start,
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_threshold(&mut self, new_threshold: f32) {
+        match self {
+            Self::Noise { threshold, .. } => *threshold = clamp(new_threshold, 0.0, 1.0),
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_vertex_falloff(&mut self, index: usize, new_falloff: f32) {
+        self.vertices_mut()[index].falloff = new_falloff.max(0.0);
+    }
+
+    fn set_vertex_position(&mut self, index: usize, new_position: Vec2) {
+        self.vertices_mut()[index].position = new_position;
+    }
+
+    fn set_white_point(&mut self, new_white_point: f32) {
+        match self {
+            Self::Bitmap { white_point, .. } => *white_point = clamp(new_white_point, 0.0, 1.0),
+            _ => unreachable!(),
+        }
+    }
+
+    fn vertices_mut(&mut self) -> &mut Vec<MaskVertex> {
+        match self {
+            Self::Polygon { vertices, .. } => vertices,
+            Self::Spline { vertices, .. } => vertices,
+            _ => unreachable!(),
         }
     }
 
     fn update_transform(&mut self) {
         let (center, irregularity, rotation, transform) = match self {
+            &mut Self::Bitmap {
+                ref center,
+                ref rotation,
+                ref mut transform,
+                ..
+            } => {
+                // Bitmaps are not scaled here, their size depends on the
+                // image and it is applied when sampling.
+                *transform =
+                    Affine2::from_angle(-*rotation * TAU) * Affine2::from_translation(-*center);
+                return;
+            }
+            &mut Self::Biome { .. }
+            | &mut Self::Gradient { .. }
+            | &mut Self::Height { .. }
+            | &mut Self::Noise { .. }
+            | &mut Self::Polygon { .. }
+            | &mut Self::Slope { .. }
+            | &mut Self::Spline { .. } => return,
             &mut Self::Circle {
                 ref center,
                 ref irregularity,
@@ -346,6 +839,58 @@ impl MaskSource {
     }
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Reflect, Serialize)]
+pub struct MaskVertex {
+    /// Distance outside the outline where the mask fades out.  It is
+    /// interpolated between the vertices.
+    pub falloff: f32,
+    pub position: Vec2,
+}
+
+impl MaskVertex {
+    pub fn new(position: Vec2, falloff: f32) -> Self {
+        Self { falloff, position }
+    }
+
+    /// Vertices of a square centered at the origin, in counter-clockwise
+    /// order.
+    fn square(size: f32, falloff: f32) -> Vec<Self> {
+        let half_size: f32 = size * 0.5;
+        [
+            Vec2::new(-half_size, -half_size),
+            Vec2::new(half_size, -half_size),
+            Vec2::new(half_size, half_size),
+            Vec2::new(-half_size, half_size),
+        ]
+        .into_iter()
+        .map(|position| Self::new(position, falloff))
+        .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Reflect, Serialize)]
+pub enum GradientShape {
+    /// Along the line from start to end.
+    #[default]
+    Linear,
+    /// Around start, end is on the circumference.
+    Radial,
+}
+
+impl GradientShape {
+    pub const ITEMS: [Self; 2] = [Self::Linear, Self::Radial];
+}
+
+impl ToString for GradientShape {
+    fn to_string(&self) -> String {
+        (match self {
+            Self::Linear => "Linear",
+            Self::Radial => "Radial",
+        })
+        .into()
+    }
+}
+
 // ACTIONS
 
 #[derive(Debug, Reflect)]
@@ -479,6 +1024,15 @@ pub enum UpdateMaskAction {
         mask_id: MaskId,
         new_value: bool,
     },
+    ToggleInvert {
+        mask_id: MaskId,
+        new_value: bool,
+    },
+    UpdateCurve {
+        mask_id: MaskId,
+        old_value: MaskCurve,
+        new_value: MaskCurve,
+    },
     UpdateStrength {
         mask_id: MaskId,
         old_value: f32,
@@ -504,6 +1058,18 @@ impl UpdateMaskAction {
         Self::ToggleEnabled { mask_id, new_value }
     }
 
+    pub fn toggle_invert(mask_id: MaskId, new_value: bool) -> Self {
+        Self::ToggleInvert { mask_id, new_value }
+    }
+
+    pub fn update_curve(mask_id: MaskId, old_value: MaskCurve, new_value: MaskCurve) -> Self {
+        Self::UpdateCurve {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
     pub fn update_strength(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
         Self::UpdateStrength {
             mask_id,
@@ -516,6 +1082,8 @@ impl UpdateMaskAction {
         match self {
             Self::ChangeCompositionMode { mask_id, .. } => mask_id,
             Self::ToggleEnabled { mask_id, .. } => mask_id,
+            Self::ToggleInvert { mask_id, .. } => mask_id,
+            Self::UpdateCurve { mask_id, .. } => mask_id,
             Self::UpdateStrength { mask_id, .. } => mask_id,
         }
     }
@@ -531,6 +1099,8 @@ impl Action for UpdateMaskAction {
         match self {
             Self::ChangeCompositionMode { new_value, .. } => mask.composition_mode = *new_value,
             Self::ToggleEnabled { new_value, .. } => mask.is_enabled = *new_value,
+            Self::ToggleInvert { new_value, .. } => mask.invert = *new_value,
+            Self::UpdateCurve { new_value, .. } => mask.curve = new_value.clone(),
             Self::UpdateStrength { new_value, .. } => mask.strength = *new_value,
         };
     }
@@ -550,6 +1120,19 @@ impl Action for UpdateMaskAction {
                 mask_id,
                 new_value: !new_value,
             },
+            Self::ToggleInvert { mask_id, new_value } => Self::ToggleInvert {
+                mask_id,
+                new_value: !new_value,
+            },
+            Self::UpdateCurve {
+                mask_id,
+                ref old_value,
+                ref new_value,
+            } => Self::UpdateCurve {
+                mask_id,
+                old_value: new_value.clone(),
+                new_value: old_value.clone(),
+            },
             Self::UpdateStrength {
                 mask_id,
                 old_value,
@@ -567,52 +1150,258 @@ impl Action for UpdateMaskAction {
 #[derive(Debug, Reflect)]
 #[reflect(Action)]
 pub enum UpdateMaskSourceAction {
+    ChangeShape {
+        mask_id: MaskId,
+        old_value: GradientShape,
+        new_value: GradientShape,
+    },
+    DeleteVertex {
+        mask_id: MaskId,
+        index: usize,
+        vertex: MaskVertex,
+    },
+    InsertVertex {
+        mask_id: MaskId,
+        index: usize,
+        vertex: MaskVertex,
+    },
+    MoveVertex {
+        mask_id: MaskId,
+        index: usize,
+        old_value: Vec2,
+        new_value: Vec2,
+    },
+    UpdateBiomes {
+        mask_id: MaskId,
+        old_value: Vec<BiomeId>,
+        new_value: Vec<BiomeId>,
+    },
+    UpdateBlackPoint {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
     UpdateCenter {
         mask_id: MaskId,
         old_value: Vec2,
         new_value: Vec2,
     },
+    UpdateContrast {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateCurve {
+        mask_id: MaskId,
+        old_value: MaskCurve,
+        new_value: MaskCurve,
+    },
+    UpdateEnd {
+        mask_id: MaskId,
+        old_value: Vec2,
+        new_value: Vec2,
+    },
+    UpdateFalloff {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
     UpdateFalloffRadius {
         mask_id: MaskId,
         old_value: f32,
         new_value: f32,
     },
+    UpdateImage {
+        mask_id: MaskId,
+        old_value: BitmapImage,
+        new_value: BitmapImage,
+    },
     UpdateIrregularity {
         mask_id: MaskId,
         old_value: f32,
         new_value: f32,
     },
-    UpdateRadius {
+    UpdateMax {
         mask_id: MaskId,
         old_value: f32,
         new_value: f32,
     },
-    UpdateRotation {
+    UpdateMaxAngle {
         mask_id: MaskId,
         old_value: f32,
         new_value: f32,
     },
-    UpdateSize {
+    UpdateMin {
         mask_id: MaskId,
         old_value: f32,
         new_value: f32,
     },
-    UpdateSmoothness {
+    UpdateMinAngle {
         mask_id: MaskId,
         old_value: f32,
         new_value: f32,
     },
-}
-
-impl UpdateMaskSourceAction {
-    pub fn update_center(mask_id: MaskId, old_value: Vec2, new_value: Vec2) -> Self {
-        Self::UpdateCenter {
-            mask_id,
-            old_value,
-            new_value,
-        }
-    }
-
+    UpdateOctaves {
+        mask_id: MaskId,
+        old_value: u8,
+        new_value: u8,
+    },
+    UpdateOutOfBoundsValue {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateRadius {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateRotation {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateScale {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateSeed {
+        mask_id: MaskId,
+        old_value: u32,
+        new_value: u32,
+    },
+    UpdateSize {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateSmoothness {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateStart {
+        mask_id: MaskId,
+        old_value: Vec2,
+        new_value: Vec2,
+    },
+    UpdateThreshold {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateVertexFalloff {
+        mask_id: MaskId,
+        index: usize,
+        old_value: f32,
+        new_value: f32,
+    },
+    UpdateWhitePoint {
+        mask_id: MaskId,
+        old_value: f32,
+        new_value: f32,
+    },
+}
+
+impl UpdateMaskSourceAction {
+    pub fn change_shape(
+        mask_id: MaskId,
+        old_value: GradientShape,
+        new_value: GradientShape,
+    ) -> Self {
+        Self::ChangeShape {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn delete_vertex(mask_id: MaskId, index: usize, vertex: MaskVertex) -> Self {
+        Self::DeleteVertex {
+            mask_id,
+            index,
+            vertex,
+        }
+    }
+
+    pub fn insert_vertex(mask_id: MaskId, index: usize, vertex: MaskVertex) -> Self {
+        Self::InsertVertex {
+            mask_id,
+            index,
+            vertex,
+        }
+    }
+
+    pub fn move_vertex(mask_id: MaskId, index: usize, old_value: Vec2, new_value: Vec2) -> Self {
+        Self::MoveVertex {
+            mask_id,
+            index,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_biomes(
+        mask_id: MaskId,
+        old_value: Vec<BiomeId>,
+        new_value: Vec<BiomeId>,
+    ) -> Self {
+        Self::UpdateBiomes {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_black_point(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateBlackPoint {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_center(mask_id: MaskId, old_value: Vec2, new_value: Vec2) -> Self {
+        Self::UpdateCenter {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_contrast(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateContrast {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_curve(mask_id: MaskId, old_value: MaskCurve, new_value: MaskCurve) -> Self {
+        Self::UpdateCurve {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_end(mask_id: MaskId, old_value: Vec2, new_value: Vec2) -> Self {
+        Self::UpdateEnd {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_falloff(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateFalloff {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
     pub fn update_falloff_radius(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
         Self::UpdateFalloffRadius {
             mask_id,
@@ -621,6 +1410,14 @@ impl UpdateMaskSourceAction {
         }
     }
 
+    pub fn update_image(mask_id: MaskId, old_value: BitmapImage, new_value: BitmapImage) -> Self {
+        Self::UpdateImage {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
     pub fn update_irregularity(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
         Self::UpdateIrregularity {
             mask_id,
@@ -629,6 +1426,54 @@ impl UpdateMaskSourceAction {
         }
     }
 
+    pub fn update_max(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateMax {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_max_angle(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateMaxAngle {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_min(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateMin {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_min_angle(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateMinAngle {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_octaves(mask_id: MaskId, old_value: u8, new_value: u8) -> Self {
+        Self::UpdateOctaves {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_out_of_bounds_value(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateOutOfBoundsValue {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
     pub fn update_radius(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
         Self::UpdateRadius {
             mask_id,
@@ -645,6 +1490,22 @@ impl UpdateMaskSourceAction {
         }
     }
 
+    pub fn update_scale(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateScale {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_seed(mask_id: MaskId, old_value: u32, new_value: u32) -> Self {
+        Self::UpdateSeed {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
     pub fn update_size(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
         Self::UpdateSize {
             mask_id,
@@ -661,15 +1522,76 @@ impl UpdateMaskSourceAction {
         }
     }
 
+    pub fn update_start(mask_id: MaskId, old_value: Vec2, new_value: Vec2) -> Self {
+        Self::UpdateStart {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_threshold(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateThreshold {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_vertex_falloff(
+        mask_id: MaskId,
+        index: usize,
+        old_value: f32,
+        new_value: f32,
+    ) -> Self {
+        Self::UpdateVertexFalloff {
+            mask_id,
+            index,
+            old_value,
+            new_value,
+        }
+    }
+
+    pub fn update_white_point(mask_id: MaskId, old_value: f32, new_value: f32) -> Self {
+        Self::UpdateWhitePoint {
+            mask_id,
+            old_value,
+            new_value,
+        }
+    }
+
     fn mask_id(&self) -> &MaskId {
         match self {
+            Self::ChangeShape { mask_id, .. } => mask_id,
+            Self::DeleteVertex { mask_id, .. } => mask_id,
+            Self::InsertVertex { mask_id, .. } => mask_id,
+            Self::MoveVertex { mask_id, .. } => mask_id,
+            Self::UpdateBiomes { mask_id, .. } => mask_id,
+            Self::UpdateBlackPoint { mask_id, .. } => mask_id,
             Self::UpdateCenter { mask_id, .. } => mask_id,
+            Self::UpdateContrast { mask_id, .. } => mask_id,
+            Self::UpdateCurve { mask_id, .. } => mask_id,
+            Self::UpdateEnd { mask_id, .. } => mask_id,
+            Self::UpdateFalloff { mask_id, .. } => mask_id,
             Self::UpdateFalloffRadius { mask_id, .. } => mask_id,
+            Self::UpdateImage { mask_id, .. } => mask_id,
             Self::UpdateIrregularity { mask_id, .. } => mask_id,
+            Self::UpdateMax { mask_id, .. } => mask_id,
+            Self::UpdateMaxAngle { mask_id, .. } => mask_id,
+            Self::UpdateMin { mask_id, .. } => mask_id,
+            Self::UpdateMinAngle { mask_id, .. } => mask_id,
+            Self::UpdateOctaves { mask_id, .. } => mask_id,
+            Self::UpdateOutOfBoundsValue { mask_id, .. } => mask_id,
             Self::UpdateRadius { mask_id, .. } => mask_id,
             Self::UpdateRotation { mask_id, .. } => mask_id,
+            Self::UpdateScale { mask_id, .. } => mask_id,
+            Self::UpdateSeed { mask_id, .. } => mask_id,
             Self::UpdateSize { mask_id, .. } => mask_id,
             Self::UpdateSmoothness { mask_id, .. } => mask_id,
+            Self::UpdateStart { mask_id, .. } => mask_id,
+            Self::UpdateThreshold { mask_id, .. } => mask_id,
+            Self::UpdateVertexFalloff { mask_id, .. } => mask_id,
+            Self::UpdateWhitePoint { mask_id, .. } => mask_id,
         }
     }
 }
@@ -683,20 +1605,105 @@ impl Action for UpdateMaskSourceAction {
             .map(|(_, mask_source)| mask_source)
             .expect(&format!("Mask with id {} not found.", self.mask_id()));
         match self {
+            Self::ChangeShape { new_value, .. } => mask_source.set_shape(*new_value),
+            Self::DeleteVertex { index, .. } => mask_source.remove_vertex(*index),
+            Self::InsertVertex { index, vertex, .. } => mask_source.insert_vertex(*index, *vertex),
+            Self::MoveVertex {
+                index, new_value, ..
+            } => mask_source.set_vertex_position(*index, *new_value),
+            Self::UpdateBiomes { new_value, .. } => mask_source.set_biomes(new_value.clone()),
+            Self::UpdateBlackPoint { new_value, .. } => mask_source.set_black_point(*new_value),
             Self::UpdateCenter { new_value, .. } => mask_source.set_center(*new_value),
+            Self::UpdateContrast { new_value, .. } => mask_source.set_contrast(*new_value),
+            Self::UpdateCurve { new_value, .. } => mask_source.set_curve(new_value.clone()),
+            Self::UpdateEnd { new_value, .. } => mask_source.set_end(*new_value),
+            Self::UpdateFalloff { new_value, .. } => mask_source.set_falloff(*new_value),
             Self::UpdateFalloffRadius { new_value, .. } => {
                 mask_source.set_falloff_radius(*new_value)
             }
+            Self::UpdateImage { new_value, .. } => mask_source.set_image(new_value.clone()),
             Self::UpdateIrregularity { new_value, .. } => mask_source.set_irregularity(*new_value),
+            Self::UpdateMax { new_value, .. } => mask_source.set_max(*new_value),
+            Self::UpdateMaxAngle { new_value, .. } => mask_source.set_max_angle(*new_value),
+            Self::UpdateMin { new_value, .. } => mask_source.set_min(*new_value),
+            Self::UpdateMinAngle { new_value, .. } => mask_source.set_min_angle(*new_value),
+            Self::UpdateOctaves { new_value, .. } => mask_source.set_octaves(*new_value),
+            Self::UpdateOutOfBoundsValue { new_value, .. } => {
+                mask_source.set_out_of_bounds_value(*new_value)
+            }
             Self::UpdateRadius { new_value, .. } => mask_source.set_radius(*new_value),
             Self::UpdateRotation { new_value, .. } => mask_source.set_rotation(*new_value),
+            Self::UpdateScale { new_value, .. } => mask_source.set_scale(*new_value),
+            Self::UpdateSeed { new_value, .. } => mask_source.set_seed(*new_value),
             Self::UpdateSize { new_value, .. } => mask_source.set_size(*new_value),
             Self::UpdateSmoothness { new_value, .. } => mask_source.set_smoothness(*new_value),
+            Self::UpdateStart { new_value, .. } => mask_source.set_start(*new_value),
+            Self::UpdateThreshold { new_value, .. } => mask_source.set_threshold(*new_value),
+            Self::UpdateVertexFalloff {
+                index, new_value, ..
+            } => mask_source.set_vertex_falloff(*index, *new_value),
+            Self::UpdateWhitePoint { new_value, .. } => mask_source.set_white_point(*new_value),
         };
     }
 
     fn revert(&self, world: &mut World) {
         let reverse_action: Self = match *self {
+            Self::ChangeShape {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::ChangeShape {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::DeleteVertex {
+                mask_id,
+                index,
+                vertex,
+            } => Self::InsertVertex {
+                mask_id,
+                index,
+                vertex,
+            },
+            Self::InsertVertex {
+                mask_id,
+                index,
+                vertex,
+            } => Self::DeleteVertex {
+                mask_id,
+                index,
+                vertex,
+            },
+            Self::MoveVertex {
+                mask_id,
+                index,
+                old_value,
+                new_value,
+            } => Self::MoveVertex {
+                mask_id,
+                index,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateBiomes {
+                mask_id,
+                ref old_value,
+                ref new_value,
+            } => Self::UpdateBiomes {
+                mask_id,
+                old_value: new_value.clone(),
+                new_value: old_value.clone(),
+            },
+            Self::UpdateBlackPoint {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateBlackPoint {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
             Self::UpdateCenter {
                 mask_id,
                 old_value,
@@ -706,6 +1713,42 @@ impl Action for UpdateMaskSourceAction {
                 old_value: new_value,
                 new_value: old_value,
             },
+            Self::UpdateContrast {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateContrast {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateCurve {
+                mask_id,
+                ref old_value,
+                ref new_value,
+            } => Self::UpdateCurve {
+                mask_id,
+                old_value: new_value.clone(),
+                new_value: old_value.clone(),
+            },
+            Self::UpdateEnd {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateEnd {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateFalloff {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateFalloff {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
             Self::UpdateFalloffRadius {
                 mask_id,
                 old_value,
@@ -715,6 +1758,15 @@ impl Action for UpdateMaskSourceAction {
                 old_value: new_value,
                 new_value: old_value,
             },
+            Self::UpdateImage {
+                mask_id,
+                ref old_value,
+                ref new_value,
+            } => Self::UpdateImage {
+                mask_id,
+                old_value: new_value.clone(),
+                new_value: old_value.clone(),
+            },
             Self::UpdateIrregularity {
                 mask_id,
                 old_value,
@@ -724,6 +1776,60 @@ impl Action for UpdateMaskSourceAction {
                 old_value: new_value,
                 new_value: old_value,
             },
+            Self::UpdateMax {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateMax {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateMaxAngle {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateMaxAngle {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateMin {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateMin {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateMinAngle {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateMinAngle {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateOctaves {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateOctaves {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateOutOfBoundsValue {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateOutOfBoundsValue {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
             Self::UpdateRadius {
                 mask_id,
                 old_value,
@@ -742,6 +1848,24 @@ impl Action for UpdateMaskSourceAction {
                 old_value: new_value,
                 new_value: old_value,
             },
+            Self::UpdateScale {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateScale {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateSeed {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateSeed {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
             Self::UpdateSize {
                 mask_id,
                 old_value,
@@ -760,6 +1884,44 @@ impl Action for UpdateMaskSourceAction {
                 old_value: new_value,
                 new_value: old_value,
             },
+            Self::UpdateStart {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateStart {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateThreshold {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateThreshold {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateVertexFalloff {
+                mask_id,
+                index,
+                old_value,
+                new_value,
+            } => Self::UpdateVertexFalloff {
+                mask_id,
+                index,
+                old_value: new_value,
+                new_value: old_value,
+            },
+            Self::UpdateWhitePoint {
+                mask_id,
+                old_value,
+                new_value,
+            } => Self::UpdateWhitePoint {
+                mask_id,
+                old_value: new_value,
+                new_value: old_value,
+            },
         };
         reverse_action.apply(world);
     }
@@ -810,10 +1972,25 @@ pub enum MaskCompositionMode {
     Sub,
     Min,
     Max,
+    Multiply,
+    Difference,
+    Screen,
+    Overlay,
+    Replace,
 }
 
 impl MaskCompositionMode {
-    pub const ITEMS: [Self; 4] = [Self::Add, Self::Sub, Self::Min, Self::Max];
+    pub const ITEMS: [Self; 9] = [
+        Self::Add,
+        Self::Sub,
+        Self::Min,
+        Self::Max,
+        Self::Multiply,
+        Self::Difference,
+        Self::Screen,
+        Self::Overlay,
+        Self::Replace,
+    ];
 
     pub fn combine(&self, baseline: f32, sample: f32) -> f32 {
         match self {
@@ -821,6 +1998,19 @@ impl MaskCompositionMode {
             Self::Sub => clamp(baseline - sample, 0.0, 1.0),
             Self::Min => clamp(baseline.min(sample), 0.0, 1.0),
             Self::Max => clamp(baseline.max(sample), 0.0, 1.0),
+            Self::Multiply => clamp(baseline * sample, 0.0, 1.0),
+            Self::Difference => clamp((baseline - sample).abs(), 0.0, 1.0),
+            Self::Screen => clamp(1.0 - (1.0 - baseline) * (1.0 - sample), 0.0, 1.0),
+            // Multiply the dark parts of the baseline, screen the light parts.
+            Self::Overlay => {
+                let overlay: f32 = if baseline < 0.5 {
+                    2.0 * baseline * sample
+                } else {
+                    1.0 - 2.0 * (1.0 - baseline) * (1.0 - sample)
+                };
+                clamp(overlay, 0.0, 1.0)
+            }
+            Self::Replace => clamp(sample, 0.0, 1.0),
         }
     }
 }
@@ -832,11 +2022,186 @@ impl ToString for MaskCompositionMode {
             Self::Sub => "Sub",
             Self::Min => "Min",
             Self::Max => "Max",
+            Self::Multiply => "Multiply",
+            Self::Difference => "Difference",
+            Self::Screen => "Screen",
+            Self::Overlay => "Overlay",
+            Self::Replace => "Replace",
         })
         .into()
     }
 }
 
+/// Piecewise linear remapping of mask values.
+///
+/// Points are sorted by their input, both coordinates are within
+/// `[0.0, 1.0]`.  Inputs before the first point and after the last point are
+/// flat, so moving the end points works like levels.
+#[derive(Clone, Debug, Deserialize, PartialEq, Reflect, Serialize)]
+pub struct MaskCurve {
+    #[serde(deserialize_with = "deserialize_curve_points")]
+    points: Vec<Vec2>,
+}
+
+impl MaskCurve {
+    pub fn new(mut points: Vec<Vec2>) -> Self {
+        points
+            .iter_mut()
+            .for_each(|point| *point = point.clamp(Vec2::ZERO, Vec2::ONE));
+        points.sort_by(|a, b| a.x.total_cmp(&b.x));
+        Self { points }
+    }
+
+    /// Curve that maps `1.0 - x` to `1.0 - y`, for each `x` mapped to `y`.
+    fn mirrored(&self) -> Self {
+        Self {
+            points: self
+                .points
+                .iter()
+                .rev()
+                .map(|point| Vec2::ONE - *point)
+                .collect(),
+        }
+    }
+
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    pub fn sample(&self, value: f32) -> f32 {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return value;
+        };
+        if value <= first.x {
+            return first.y;
+        }
+        for (a, b) in self.points.iter().zip(self.points.iter().skip(1)) {
+            if value <= b.x {
+                let t: f32 = (value - a.x) / (b.x - a.x).max(f32::EPSILON);
+                return a.y.lerp(b.y, t);
+            }
+        }
+        last.y
+    }
+}
+
+impl Default for MaskCurve {
+    fn default() -> Self {
+        Self {
+            points: vec![Vec2::ZERO, Vec2::ONE],
+        }
+    }
+}
+
+/// Deserialize the points of a [MaskCurve], rejecting the points that
+/// [MaskCurve::new] would not produce.
+fn deserialize_curve_points<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Vec2>, D::Error> {
+    let points: Vec<Vec2> = Vec::deserialize(deserializer)?;
+    if !points
+        .iter()
+        .all(|point| point.cmpge(Vec2::ZERO).all() && point.cmple(Vec2::ONE).all())
+    {
+        Err(de::Error::custom(
+            "curve points are not between 0.0 and 1.0",
+        ))
+    } else if !points.is_sorted_by(|a, b| a.x <= b.x) {
+        Err(de::Error::custom(
+            "curve points are not sorted by their input",
+        ))
+    } else {
+        Ok(points)
+    }
+}
+
+/// 1.0 within `[min, max]`, fading out to 0.0 over `falloff` outside.
+fn band_pass(value: f32, min: f32, max: f32, falloff: f32) -> f32 {
+    let distance: f32 = (min - value).max(value - max).max(0.0);
+    1.0 - clamp(distance / falloff.max(f32::EPSILON), 0.0, 1.0)
+}
+
+/// Sample a closed outline.
+///
+/// The mask is 1.0 inside and fades out over the falloff of the closest point
+/// on the outline outside.  Outlines with less than 3 vertices are empty.
+fn sample_outline(outline: &[MaskVertex], position: Vec2, smoothness: f32) -> f32 {
+    if outline.len() < 3 {
+        return 0.0;
+    }
+    let (distance, falloff) = signed_distance(outline, position);
+    let factor = 1.0 - clamp(distance / falloff.max(f32::EPSILON), 0.0, 1.0);
+    let smooth_factor = SmootherStepCurve.sample_clamped(factor);
+    factor.lerp(smooth_factor, smoothness)
+}
+
+/// Signed distance from `position` to a closed outline, negative inside, and
+/// the falloff at the closest point on the outline.
+fn signed_distance(outline: &[MaskVertex], position: Vec2) -> (f32, f32) {
+    let mut distance: f32 = f32::INFINITY;
+    let mut falloff: f32 = 0.0;
+    let mut is_inside: bool = false;
+    for (a, b) in outline.iter().zip(outline.iter().cycle().skip(1)) {
+        let edge: Vec2 = b.position - a.position;
+        let t: f32 = if edge.length_squared() > 0.0 {
+            clamp(
+                (position - a.position).dot(edge) / edge.length_squared(),
+                0.0,
+                1.0,
+            )
+        } else {
+            0.0
+        };
+        let edge_distance: f32 = position.distance(a.position + edge * t);
+        if edge_distance < distance {
+            distance = edge_distance;
+            falloff = a.falloff.lerp(b.falloff, t);
+        }
+        // Count the edges crossing the ray towards +X.
+        if (a.position.y > position.y) != (b.position.y > position.y) {
+            let x: f32 =
+                a.position.x + (position.y - a.position.y) / (b.position.y - a.position.y) * edge.x;
+            if position.x < x {
+                is_inside = !is_inside;
+            }
+        }
+    }
+    if is_inside {
+        (-distance, falloff)
+    } else {
+        (distance, falloff)
+    }
+}
+
+/// Flatten a closed Catmull-Rom spline passing through `vertices`.
+///
+/// Each segment is converted to a cubic Bézier curve and subdivided into
+/// [SPLINE_SUBDIVISIONS] edges.
+fn spline_outline(vertices: &[MaskVertex]) -> Vec<MaskVertex> {
+    let n: usize = vertices.len();
+    if n < 3 {
+        return vertices.to_vec();
+    }
+    let mut outline: Vec<MaskVertex> = Vec::with_capacity(n * SPLINE_SUBDIVISIONS);
+    for idx in 0..n {
+        let p0: Vec2 = vertices[(idx + n - 1) % n].position;
+        let (a, b) = (vertices[idx], vertices[(idx + 1) % n]);
+        let p3: Vec2 = vertices[(idx + 2) % n].position;
+        let c1: Vec2 = a.position + (b.position - p0) / 6.0;
+        let c2: Vec2 = b.position - (p3 - a.position) / 6.0;
+        for step in 0..SPLINE_SUBDIVISIONS {
+            let t: f32 = step as f32 / SPLINE_SUBDIVISIONS as f32;
+            let u: f32 = 1.0 - t;
+            let position: Vec2 = a.position * (u * u * u)
+                + c1 * (3.0 * u * u * t)
+                + c2 * (3.0 * u * t * t)
+                + b.position * (t * t * t);
+            outline.push(MaskVertex::new(position, a.falloff.lerp(b.falloff, t)));
+        }
+    }
+    outline
+}
+
 fn mask_order_on_remove_hook(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
     if let Some(ChildOf(parent)) = world.entity(entity).get::<ChildOf>().cloned() {
         // The layer might be deleted, so we cannot assume the parent entity exists
@@ -852,3 +2217,322 @@ fn mask_order_on_remove_hook(mut world: DeferredWorld, HookContext { entity, ..
         error!("Mask {} has no parent.", entity);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{approx_eq, Alpha, ONE_IN_TEN_THOUSAND};
+
+    #[test]
+    fn composition_modes_combine() {
+        let combine =
+            |mode: MaskCompositionMode, baseline: f32, sample: f32| mode.combine(baseline, sample);
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Add, 0.75, 0.5),
+            1.0,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Sub, 0.25, 0.5),
+            0.0,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Min, 0.25, 0.5),
+            0.25,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Max, 0.25, 0.5),
+            0.5,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Multiply, 0.5, 0.5),
+            0.25,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Difference, 0.25, 0.75),
+            0.5,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Screen, 0.5, 0.5),
+            0.75,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Overlay, 0.25, 0.5),
+            0.25,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Overlay, 0.75, 0.5),
+            0.75,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            combine(MaskCompositionMode::Replace, 0.25, 0.5),
+            0.5,
+            ONE_IN_TEN_THOUSAND
+        ));
+        // Identity elements.
+        for baseline in [0.0, 0.3, 1.0] {
+            assert!(approx_eq(
+                combine(MaskCompositionMode::Multiply, baseline, 1.0),
+                baseline,
+                ONE_IN_TEN_THOUSAND
+            ));
+            assert!(approx_eq(
+                combine(MaskCompositionMode::Screen, baseline, 0.0),
+                baseline,
+                ONE_IN_TEN_THOUSAND
+            ));
+            assert!(approx_eq(
+                combine(MaskCompositionMode::Difference, baseline, 0.0),
+                baseline,
+                ONE_IN_TEN_THOUSAND
+            ));
+        }
+    }
+
+    #[test]
+    fn gradient_mask_fades_in_from_start_to_end() {
+        let gradient = |shape: GradientShape| MaskSource::Gradient {
+            curve: MaskCurve::default(),
+            end: Vec2::new(10.0, 0.0),
+            shape,
+            smoothness: 0.0,
+            start: Vec2::ZERO,
+        };
+        let linear = gradient(GradientShape::Linear);
+        let sample = |mask_source: &MaskSource, position: Vec2| {
+            mask_source.sample(position, &Sample::default(), None)
+        };
+        assert_eq!(sample(&linear, Vec2::new(-5.0, 3.0)), 0.0);
+        assert_eq!(sample(&linear, Vec2::new(5.0, 3.0)), 0.5);
+        assert_eq!(sample(&linear, Vec2::new(15.0, -3.0)), 1.0);
+        let radial = gradient(GradientShape::Radial);
+        assert_eq!(sample(&radial, Vec2::ZERO), 0.0);
+        assert_eq!(sample(&radial, Vec2::new(0.0, -5.0)), 0.5);
+        assert_eq!(sample(&radial, Vec2::new(-15.0, 0.0)), 1.0);
+        let remapped = MaskSource::Gradient {
+            curve: MaskCurve::new(vec![Vec2::new(0.5, 0.0), Vec2::new(1.0, 1.0)]),
+            ..linear
+        };
+        assert_eq!(sample(&remapped, Vec2::new(5.0, 3.0)), 0.0);
+        assert_eq!(sample(&remapped, Vec2::new(7.5, 3.0)), 0.5);
+    }
+
+    #[test]
+    fn height_mask_fades_out_around_its_range() {
+        let mask_source = MaskSource::Height {
+            falloff: 10.0,
+            max: 100.0,
+            min: 0.0,
+        };
+        let sample =
+            |height: f32| mask_source.sample(Vec2::ZERO, &Sample::new(height, Alpha::Opaque), None);
+        assert_eq!(sample(50.0), 1.0);
+        assert_eq!(sample(105.0), 0.5);
+        assert_eq!(sample(-5.0), 0.5);
+        assert_eq!(sample(200.0), 0.0);
+        assert_eq!(sample(-20.0), 0.0);
+    }
+
+    #[test]
+    fn spline_mask_passes_through_its_vertices() {
+        let mask_source = MaskSource::Spline {
+            smoothness: 0.0,
+            vertices: MaskVertex::square(2.0, 0.5),
+        };
+        let sample = |position: Vec2| mask_source.sample(position, &Sample::default(), None);
+        assert_eq!(sample(Vec2::ZERO), 1.0);
+        assert_eq!(sample(Vec2::ONE), 1.0);
+        // Edges are curved.
+        assert_eq!(sample(Vec2::new(0.0, -1.2)), 1.0);
+        assert!(sample(Vec2::splat(1.1)) < 1.0);
+        assert_eq!(sample(Vec2::new(2.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn mask_curves_out_of_range_are_rejected() {
+        let curve = |text: &str| toml::from_str::<MaskCurve>(text);
+        assert_eq!(
+            curve("points = [[0.0, 0.0], [1.0, 1.0]]").unwrap(),
+            MaskCurve::default()
+        );
+        assert!(curve("points = [[0.0, 0.0], [1.0, 2.0]]").is_err());
+        assert!(curve("points = [[-0.5, 0.0], [1.0, 1.0]]").is_err());
+        assert!(curve("points = [[1.0, 1.0], [0.0, 0.0]]").is_err());
+    }
+
+    #[test]
+    fn bitmap_invert_is_migrated_to_the_mask() {
+        let curve = MaskCurve::new(vec![Vec2::new(0.2, 0.1), Vec2::new(0.6, 0.9)]);
+        let legacy: toml::Table = {
+            let mut table = toml::Table::try_from(MaskBundle {
+                mask: Mask {
+                    curve: curve.clone(),
+                    ..default()
+                },
+                mask_source: MaskSource::bitmap(),
+            })
+            .unwrap();
+            let bitmap: &mut toml::Table = table["mask_source"]["Bitmap"].as_table_mut().unwrap();
+            assert!(!bitmap.contains_key("invert"));
+            bitmap.insert("invert".to_owned(), toml::Value::Boolean(true));
+            bitmap["out_of_bounds_value"] = toml::Value::Float(0.25);
+            table
+        };
+        let migrated: MaskBundle = legacy.try_into().unwrap();
+        assert!(migrated.mask.invert);
+        let MaskSource::Bitmap {
+            legacy_invert,
+            out_of_bounds_value,
+            ..
+        } = migrated.mask_source
+        else {
+            unreachable!()
+        };
+        assert!(!legacy_invert);
+        assert_eq!(out_of_bounds_value, 0.75);
+        // Inside the image, the bitmap value was inverted before the curve.
+        for value in [0.0, 0.1, 0.3, 0.5, 0.7, 1.0] {
+            assert!(approx_eq(
+                migrated.mask.combine(None, value),
+                curve.sample(1.0 - value),
+                ONE_IN_TEN_THOUSAND
+            ));
+        }
+        // Outside, the value was not inverted.
+        assert!(approx_eq(
+            migrated.mask.combine(None, out_of_bounds_value),
+            curve.sample(0.25),
+            ONE_IN_TEN_THOUSAND
+        ));
+    }
+
+    #[test]
+    fn mask_curve_and_invert_remap_samples() {
+        let mut mask = Mask::default();
+        assert!(approx_eq(mask.combine(None, 0.3), 0.3, ONE_IN_TEN_THOUSAND));
+        mask.invert = true;
+        assert!(approx_eq(mask.combine(None, 0.3), 0.7, ONE_IN_TEN_THOUSAND));
+        // Levels.
+        mask.curve = MaskCurve::new(vec![Vec2::new(0.75, 1.0), Vec2::new(0.25, 0.0)]);
+        mask.invert = false;
+        assert_eq!(mask.combine(None, 0.1), 0.0);
+        assert!(approx_eq(mask.combine(None, 0.5), 0.5, ONE_IN_TEN_THOUSAND));
+        assert_eq!(mask.combine(None, 0.9), 1.0);
+        // Curve is applied before inverting and composition.
+        mask.curve = MaskCurve::new(vec![Vec2::ZERO, Vec2::new(0.5, 0.8), Vec2::ONE]);
+        mask.invert = true;
+        assert!(approx_eq(
+            mask.combine(None, 0.25),
+            0.6,
+            ONE_IN_TEN_THOUSAND
+        ));
+        mask.composition_mode = MaskCompositionMode::Multiply;
+        assert!(approx_eq(
+            mask.combine(Some(0.5), 0.25),
+            0.3,
+            ONE_IN_TEN_THOUSAND
+        ));
+    }
+
+    #[test]
+    fn noise_mask_follows_threshold() {
+        let noise = |contrast: f32, threshold: f32| MaskSource::Noise {
+            contrast,
+            octaves: 4,
+            scale: 10.0,
+            seed: 42,
+            threshold,
+        };
+        let positions: Vec<Vec2> = (0..100)
+            .map(|i| Vec2::new((i % 10) as f32, (i / 10) as f32) * 3.7)
+            .collect();
+        let coverage = |mask_source: MaskSource| {
+            positions
+                .iter()
+                .map(|p| {
+                    let value: f32 = mask_source.sample(*p, &Sample::default(), None);
+                    assert!(value >= 0.0 && value <= 1.0);
+                    value
+                })
+                .sum::<f32>()
+        };
+        assert_eq!(coverage(noise(100.0, 0.0)), 100.0);
+        assert!(coverage(noise(4.0, 0.3)) > coverage(noise(4.0, 0.7)));
+        assert_eq!(coverage(noise(100.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn polygon_mask_fades_out_with_vertex_falloff() {
+        let mut mask_source = MaskSource::polygon();
+        let sample = |mask_source: &MaskSource, position: Vec2| {
+            mask_source.sample(position, &Sample::default(), None)
+        };
+        assert_eq!(sample(&mask_source, Vec2::ZERO), 1.0);
+        assert_eq!(sample(&mask_source, Vec2::new(0.0, 1.0)), 1.0);
+        assert_eq!(sample(&mask_source, Vec2::new(2.0, 0.0)), 0.0);
+        mask_source.set_smoothness(0.0);
+        assert!(approx_eq(
+            sample(&mask_source, Vec2::new(0.0, -1.25)),
+            0.5,
+            ONE_IN_TEN_THOUSAND
+        ));
+        // Falloff is interpolated along the edge.
+        mask_source.set_vertex_falloff(0, 1.5);
+        assert!(approx_eq(
+            sample(&mask_source, Vec2::new(0.0, -1.5)),
+            0.5,
+            ONE_IN_TEN_THOUSAND
+        ));
+        mask_source.remove_vertex(3);
+        assert_eq!(sample(&mask_source, Vec2::new(-0.9, 0.9)), 0.0);
+        mask_source.remove_vertex(2);
+        assert_eq!(sample(&mask_source, Vec2::ZERO), 0.0);
+    }
+
+    #[test]
+    fn slope_mask_selects_by_angle() {
+        let mask_source = MaskSource::Slope {
+            falloff: 10.0,
+            max_angle: 90.0,
+            min_angle: 45.0,
+        };
+        let sample =
+            |gradient: Vec2| mask_source.sample(Vec2::ZERO, &Sample::default(), Some(gradient));
+        assert_eq!(sample(Vec2::ZERO), 0.0);
+        assert_eq!(sample(Vec2::new(0.0, -2.0)), 1.0);
+        let at_40_degrees: f32 = sample(Vec2::X * 40.0f32.to_radians().tan());
+        assert!(approx_eq(at_40_degrees, 0.5, 0.001));
+        // Without a gradient the terrain is flat.
+        assert_eq!(
+            mask_source.sample(Vec2::ZERO, &Sample::default(), None),
+            0.0
+        );
+    }
+
+    #[test]
+    fn biome_mask_selects_classified_samples() {
+        let (forest, meadow, tundra) = (BiomeId::now_v7(), BiomeId::now_v7(), BiomeId::now_v7());
+        let mask_source = MaskSource::Biome {
+            biomes: vec![forest, meadow],
+        };
+        let sample = |biome: Option<BiomeId>| {
+            let mut base_sample = Sample::default();
+            if let Some(id) = biome {
+                base_sample.set_channel(Channel::Biome(id), 1.0);
+            }
+            mask_source.sample(Vec2::ZERO, &base_sample, Some(Vec2::ZERO))
+        };
+        assert_eq!(sample(Some(forest)), 1.0);
+        assert_eq!(sample(Some(meadow)), 1.0);
+        assert_eq!(sample(Some(tundra)), 0.0);
+        assert_eq!(sample(None), 0.0);
+    }
+}
@@ -13,6 +13,7 @@
 //
 // You should have received a copy of the GNU General Public License along
 // with Yer.  If not, see <https://www.gnu.org/licenses/>.
+use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -20,6 +21,9 @@ use bevy::prelude::*;
 #[cfg(feature = "embed-assets")]
 use bevy_embedded_assets::{self, EmbeddedAssetPlugin};
 
+mod bake;
+mod biome;
+mod cli;
 mod constants;
 mod id;
 mod layer;
@@ -32,7 +36,12 @@ mod ui;
 mod undo;
 mod viewport;
 
-fn main() {
+fn main() -> AppExit {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some(cli::BAKE_COMMAND) {
+        return cli::bake(args.into_iter().skip(1).collect());
+    }
+
     let data_dir: PathBuf = find_data_dir();
     ensure_dir(&data_dir.join(constants::VERSION));
 
@@ -50,23 +59,29 @@ fn main() {
                 title: constants::APPLICATION_TITLE.to_owned(),
                 ..Default::default()
             }),
+            // Closing the window asks to save the unsaved changes first.
+            close_when_requested: false,
             ..Default::default()
         }),
         MeshPickingPlugin,
     ));
     app.add_plugins((
+        bake::BakePlugin,
+        biome::BiomePlugin,
         layer::LayerPlugin,
         preferences::PreferencesPlugin {
             config_file_path: data_dir.join(constants::VERSION).join("config.toml"),
         },
         preview::PreviewPlugin,
-        session::SessionPlugin,
+        session::SessionPlugin {
+            autosave_dir: data_dir.join(constants::VERSION),
+        },
         theme::ThemePlugin,
         ui::UiPlugin,
         undo::UndoPlugin,
         viewport::ViewportPlugin,
     ));
-    app.run();
+    app.run()
 }
 
 // LIB
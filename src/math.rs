@@ -15,12 +15,132 @@
 // with Yer.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::f32;
+use std::f32::consts::{SQRT_2, TAU};
 
-use bevy::math::Vec2;
+use bevy::math::{FloatExt, UVec2, Vec2};
+use bevy::reflect::Reflect;
+use bevy::tasks::futures_lite::future;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::id::BiomeId;
 
 pub const ONE_IN_TEN_THOUSAND: f32 = 0.0001f32;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Heights sampled on a regular grid.
+///
+/// Rows are ordered from top to bottom, so the Y coordinate decreases as the
+/// row index increases, just like the preview grid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightGrid {
+    heights: Vec<f32>,
+    origin: Vec2,
+    size: UVec2,
+    spacing: f32,
+}
+
+impl HeightGrid {
+    /// `origin` is the position of the top left sample and `spacing` is the
+    /// distance between two adjacent samples.
+    pub fn new(origin: Vec2, spacing: f32, size: UVec2, heights: Vec<f32>) -> Self {
+        assert_eq!(
+            heights.len(),
+            size.x as usize * size.y as usize,
+            "Number of heights does not match the grid size."
+        );
+        Self {
+            heights,
+            origin,
+            size,
+            spacing,
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        self.heights[self.index(x, y)]
+    }
+
+    /// Gradient of the heights at `idx`, in world units.
+    ///
+    /// Central differences are used inside the grid and one-sided
+    /// differences at its edges.
+    pub fn gradient(&self, idx: usize) -> Vec2 {
+        let x: u32 = (idx % self.size.x as usize) as u32;
+        let y: u32 = (idx / self.size.x as usize) as u32;
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(self.size.x - 1));
+        let (y0, y1) = (y.saturating_sub(1), (y + 1).min(self.size.y - 1));
+        let dx: f32 = if x1 > x0 {
+            (self.get(x1, y) - self.get(x0, y)) / ((x1 - x0) as f32 * self.spacing)
+        } else {
+            0.0
+        };
+        // Y decreases as the row index increases.
+        let dy: f32 = if y1 > y0 {
+            (self.get(x, y0) - self.get(x, y1)) / ((y1 - y0) as f32 * self.spacing)
+        } else {
+            0.0
+        };
+        Vec2::new(dx, dy)
+    }
+
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    pub fn heights_mut(&mut self) -> &mut [f32] {
+        &mut self.heights
+    }
+
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        debug_assert!(x < self.size.x && y < self.size.y);
+        y as usize * self.size.x as usize + x as usize
+    }
+
+    pub fn into_heights(self) -> Vec<f32> {
+        self.heights
+    }
+
+    /// World position of the sample at `idx`.
+    pub fn position(&self, idx: usize) -> Vec2 {
+        let x = (idx % self.size.x as usize) as f32;
+        let y = (idx / self.size.x as usize) as f32;
+        self.origin + Vec2::new(x, -y) * self.spacing
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn spacing(&self) -> f32 {
+        self.spacing
+    }
+}
+
+/// A small seedable pseudo random number generator. (SplitMix64)
+///
+/// Filters use this to produce the same result for the same seed.
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z: u64 = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `[0.0, 1.0)`.
+    pub fn next_f32(&mut self) -> f32 {
+        // Use the top 24 bits, f32 cannot represent more.
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
 pub enum Alpha {
     Opaque,
     Transparent(f32),
@@ -51,30 +171,88 @@ impl Alpha {
     }
 }
 
-#[derive(Clone, Debug)]
+/// Named channel of a [Sample], carried alongside its height.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Reflect, Serialize)]
+pub enum Channel {
+    Moisture,
+    Temperature,
+    /// Weight of the biome with the given id.
+    Biome(BiomeId),
+    Custom(String),
+}
+
+impl ToString for Channel {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Moisture => "Moisture".into(),
+            Self::Temperature => "Temperature".into(),
+            Self::Biome(id) => format!("Biome: {}", id),
+            Self::Custom(name) => name.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Reflect)]
 pub struct Sample {
     height: f32,
     alpha: Alpha,
+    /// Channels other than the height.  Channels that are not written by any
+    /// of the layers are missing.
+    channels: Vec<(Channel, f32)>,
 }
 
 impl Sample {
     pub fn new(height: f32, alpha: Alpha) -> Self {
-        Self { height, alpha }
+        Self {
+            height,
+            alpha,
+            channels: vec![],
+        }
     }
 
     pub fn alpha(&self) -> Alpha {
         self.alpha
     }
 
+    pub fn channel(&self, channel: &Channel) -> Option<f32> {
+        self.channels
+            .iter()
+            .find(|(c, _)| c == channel)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn channels(&self) -> &[(Channel, f32)] {
+        &self.channels
+    }
+
     pub fn height(&self) -> f32 {
         self.height
     }
 
+    pub fn set_channel(&mut self, channel: Channel, value: f32) {
+        match self.channels.iter_mut().find(|(c, _)| *c == channel) {
+            Some((_, v)) => *v = value,
+            None => self.channels.push((channel, value)),
+        }
+    }
+
+    pub fn set_height(&mut self, height: f32) {
+        self.height = height;
+    }
+
     /// Mix `other` above `self`.
     ///
     /// See [reference](https://en.wikipedia.org/wiki/Alpha_compositing#Description).
+    ///
+    /// Channels of `other` are interpolated by its alpha, missing channels
+    /// of `self` are taken as 0.0.  Channels missing in `other` are not
+    /// changed.
     pub fn mix_in_place(&mut self, other: &Self) {
         let mix_factor = other.alpha.factor();
+        for (channel, value) in other.channels.iter() {
+            let base: f32 = self.channel(channel).unwrap_or(0.0);
+            self.set_channel(channel.clone(), base.lerp(*value, mix_factor));
+        }
         let new_alpha = Alpha::from_factor(mix_factor + self.alpha().factor() * (1.0 - mix_factor));
         self.height =
             other.height * mix_factor + self.height * self.alpha().factor() * (1.0 - mix_factor);
@@ -95,17 +273,82 @@ impl Default for Sample {
         Self {
             height: 0.0,
             alpha: Alpha::Opaque,
+            channels: vec![],
         }
     }
 }
 
 // We cannot just return a single f32.
 //
-// Minimum; we need an alpha value.
-//
-// Design this in a way we can return additional channels.
+// Minimum; we need an alpha value.  Additional channels, such as moisture,
+// are carried in the Sample.
 pub trait Sampler2D: Send + Sync {
     fn sample(&self, position: Vec2, base_sample: &Sample) -> Sample;
+
+    /// Sample with the gradient of the heights beneath.
+    ///
+    /// Gradients are only available when the whole grid is sampled at once,
+    /// see [Self::needs_gradient].
+    fn sample_with_gradient(
+        &self,
+        position: Vec2,
+        base_sample: &Sample,
+        _base_gradient: Option<Vec2>,
+    ) -> Sample {
+        self.sample(position, base_sample)
+    }
+
+    fn needs_gradient(&self) -> bool {
+        false
+    }
+
+    /// Process all the heights beneath at once.
+    ///
+    /// Filters, such as erosion, depend on the neighbourhood of a position
+    /// and they cannot be sampled point by point.  `base_samples` are the
+    /// samples beneath, in the order of `grid`, with all their channels.
+    /// Point-wise samplers return `None`.
+    fn filter<'a>(
+        &'a self,
+        _grid: &HeightGrid,
+        _base_samples: &'a [Sample],
+    ) -> Option<Box<dyn Filter + 'a>> {
+        None
+    }
+
+    fn is_filter(&self) -> bool {
+        false
+    }
+}
+
+/// Work of a [Sampler2D::filter], processed in steps.
+///
+/// Filters can take long on large grids.  Callers yield between the steps, so
+/// that a preview or a bake can be cancelled while a filter runs.
+pub trait Filter: Send {
+    /// Process the next chunk of work, returns whether there is more left.
+    fn step(&mut self) -> bool;
+
+    /// Ratio of the work done, between 0 and 1.
+    fn progress(&self) -> f32;
+
+    /// Filtered heights.  Remaining steps are processed first.
+    fn finish(self: Box<Self>) -> HeightGrid;
+}
+
+/// Run `filter` to the end, yielding after each step.
+///
+/// `on_progress` is called with the progress of the filter after each step.
+pub async fn run_filter(
+    mut filter: Box<dyn Filter + '_>,
+    mut on_progress: impl FnMut(f32),
+) -> HeightGrid {
+    while filter.step() {
+        on_progress(filter.progress());
+        future::yield_now().await;
+    }
+    on_progress(1.0);
+    filter.finish()
 }
 
 pub fn approx_eq(a: f32, b: f32, ratio: f32) -> bool {
@@ -119,27 +362,217 @@ pub fn clamp(x: f32, min: f32, max: f32) -> f32 {
     max.min(min.max(x))
 }
 
+/// Deserialize a ratio, such as an opacity, that must be within `[0.0, 1.0]`.
+///
+/// Saved files can be edited by hand, and ratios out of range would panic
+/// when they reach [Alpha].
+pub fn deserialize_ratio<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value: f32 = f32::deserialize(deserializer)?;
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(de::Error::custom(format!(
+            "{} is not between 0.0 and 1.0",
+            value
+        )))
+    }
+}
+
+/// Direction the slope with the given `gradient` faces, in degrees.
+///
+/// Angle of the downslope direction, measured clockwise from +Y.  Flat
+/// terrain does not face any direction.
+pub fn aspect_angle(gradient: Vec2) -> Option<f32> {
+    (gradient != Vec2::ZERO).then(|| {
+        let downslope: Vec2 = -gradient;
+        downslope
+            .x
+            .atan2(downslope.y)
+            .to_degrees()
+            .rem_euclid(360.0)
+    })
+}
+
+/// Angle of the slope with the given `gradient`, in degrees.
+pub fn slope_angle(gradient: Vec2) -> f32 {
+    gradient.length().atan().to_degrees()
+}
+
+/// Fractal Brownian motion built from [perlin_noise_2d].
+///
+/// Each octave gets its own seed.  The result is normalized by the sum of
+/// the octave amplitudes, so it stays within `[-1.0, 1.0]` regardless of
+/// `octaves` and `gain`.
+pub fn fbm_2d(position: Vec2, seed: u32, octaves: u8, lacunarity: f32, gain: f32) -> f32 {
+    let mut value: f32 = 0.0;
+    let mut amplitude: f32 = 1.0;
+    let mut frequency: f32 = 1.0;
+    let mut total_amplitude: f32 = 0.0;
+    for octave in 0..octaves {
+        value +=
+            perlin_noise_2d(position * frequency, seed.wrapping_add(octave.into())) * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= gain;
+        frequency *= lacunarity;
+    }
+    if total_amplitude > 0.0 {
+        value / total_amplitude
+    } else {
+        0.0
+    }
+}
+
+/// Gradient noise, sampled at `position`.
+///
+/// Lattice gradients are derived from a hash of the cell coordinates and
+/// `seed`, so the same inputs always produce the same output.  The result
+/// is in `[-1.0, 1.0]` and it is zero on integer coordinates.
+pub fn perlin_noise_2d(position: Vec2, seed: u32) -> f32 {
+    let cell: Vec2 = position.floor();
+    let local: Vec2 = position - cell;
+    let (ix, iy) = (cell.x as i32, cell.y as i32);
+    let corner = |dx: i32, dy: i32| -> f32 {
+        let angle: f32 =
+            hash_2d(ix.wrapping_add(dx), iy.wrapping_add(dy), seed) as f32 / u32::MAX as f32 * TAU;
+        Vec2::from_angle(angle).dot(local - Vec2::new(dx as f32, dy as f32))
+    };
+    let u: f32 = quintic_fade(local.x);
+    let v: f32 = quintic_fade(local.y);
+    let bottom: f32 = corner(0, 0).lerp(corner(1, 0), u);
+    let top: f32 = corner(0, 1).lerp(corner(1, 1), u);
+    // Unit gradients produce values within ±√2/2.
+    clamp(bottom.lerp(top, v) * SQRT_2, -1.0, 1.0)
+}
+
+fn hash_2d(x: i32, y: i32, seed: u32) -> u32 {
+    let mut h: u32 = seed.wrapping_mul(0x9e37_79b9)
+        ^ (x as u32).wrapping_mul(0x85eb_ca6b)
+        ^ (y as u32).wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h
+}
+
+#[inline]
+fn quintic_fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn aspect_is_the_downslope_direction() {
+        assert_eq!(aspect_angle(Vec2::ZERO), None);
+        // Rising towards +Y, faces -Y.
+        assert!(approx_eq(
+            aspect_angle(Vec2::new(0.0, 1.0)).unwrap(),
+            180.0,
+            ONE_IN_TEN_THOUSAND
+        ));
+        // Rising towards -X, faces +X.
+        assert!(approx_eq(
+            aspect_angle(Vec2::new(-1.0, 0.0)).unwrap(),
+            90.0,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert!(approx_eq(
+            aspect_angle(Vec2::new(1.0, 0.0)).unwrap(),
+            270.0,
+            ONE_IN_TEN_THOUSAND
+        ));
+    }
+
+    #[test]
+    fn height_grid_positions() {
+        let grid = HeightGrid::new(Vec2::new(-1.0, 1.0), 0.5, UVec2::new(5, 5), vec![0.0; 25]);
+        assert_eq!(grid.position(0), Vec2::new(-1.0, 1.0));
+        assert_eq!(grid.position(grid.index(4, 0)), Vec2::new(1.0, 1.0));
+        assert_eq!(grid.position(24), Vec2::new(1.0, -1.0));
+    }
+
+    #[test]
+    fn height_grid_gradient_of_a_plane() {
+        let size = UVec2::new(4, 3);
+        let mut grid = HeightGrid::new(Vec2::ZERO, 2.0, size, vec![0.0; 12]);
+        for idx in 0..12 {
+            let p = grid.position(idx);
+            grid.heights_mut()[idx] = 3.0 * p.x - 0.5 * p.y;
+        }
+        for idx in 0..12 {
+            let gradient = grid.gradient(idx);
+            assert!(approx_eq(gradient.x, 3.0, ONE_IN_TEN_THOUSAND));
+            assert!(approx_eq(gradient.y, -0.5, ONE_IN_TEN_THOUSAND));
+        }
+    }
+
+    #[test]
+    fn random_is_deterministic_and_in_unit_range() {
+        let mut a = Random::new(7);
+        let mut b = Random::new(7);
+        for _ in 0..1000 {
+            let value = a.next_f32();
+            assert_eq!(value, b.next_f32());
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn fbm_stays_within_unit_range() {
+        for octaves in 1..8 {
+            for idx in 0..500 {
+                let p = Vec2::new(idx as f32 * 0.37, idx as f32 * -0.73);
+                let value = fbm_2d(p, 42, octaves, 2.0, 0.5);
+                assert!((-1.0..=1.0).contains(&value));
+            }
+        }
+    }
+
+    #[test]
+    fn fbm_with_zero_octaves_is_zero() {
+        assert_eq!(fbm_2d(Vec2::new(0.25, 0.75), 7, 0, 2.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn perlin_noise_is_deterministic_and_seeded() {
+        let p = Vec2::new(12.34, -56.78);
+        assert_eq!(perlin_noise_2d(p, 1), perlin_noise_2d(p, 1));
+        assert_ne!(perlin_noise_2d(p, 1), perlin_noise_2d(p, 2));
+    }
+
+    #[test]
+    fn perlin_noise_is_zero_on_lattice_points() {
+        for (x, y) in [(0, 0), (1, 0), (-3, 7), (100, -100)] {
+            let value = perlin_noise_2d(Vec2::new(x as f32, y as f32), 13);
+            assert!(approx_eq(value, 0.0, ONE_IN_TEN_THOUSAND));
+        }
+    }
+
     #[test]
     fn mix_samples() {
         let a = Sample {
             height: 8.5,
             alpha: Alpha::Opaque,
+            channels: vec![],
         };
         let b = Sample {
             height: 7.5,
             alpha: Alpha::Opaque,
+            channels: vec![],
         };
         let c = Sample {
             height: 10.5,
             alpha: Alpha::from_factor(0.5),
+            channels: vec![],
         };
         let d = Sample {
             height: 4.5,
             alpha: Alpha::from_factor(0.4),
+            channels: vec![],
         };
 
         // If the 2nd operand is opaque, then the result is equal to 2nd
@@ -180,4 +613,29 @@ mod tests {
             assert_eq!(mixed.alpha(), Alpha::from_factor(0.7));
         }
     }
+
+    #[test]
+    fn mix_sample_channels() {
+        let mut base = Sample::default();
+        base.set_channel(Channel::Moisture, 0.5);
+        base.set_channel(Channel::Temperature, 20.0);
+        let mut layer = Sample::new(0.0, Alpha::from_factor(0.5));
+        layer.set_channel(Channel::Moisture, 1.0);
+        layer.set_channel(Channel::Custom("Snow".into()), 1.0);
+        base.mix_in_place(&layer);
+        assert!(approx_eq(
+            base.channel(&Channel::Moisture).unwrap(),
+            0.75,
+            ONE_IN_TEN_THOUSAND
+        ));
+        // Channels the layer does not write are not changed.
+        assert_eq!(base.channel(&Channel::Temperature), Some(20.0));
+        // Missing channels start from 0.0.
+        assert!(approx_eq(
+            base.channel(&Channel::Custom("Snow".into())).unwrap(),
+            0.5,
+            ONE_IN_TEN_THOUSAND
+        ));
+        assert_eq!(base.channel(&Channel::Biome(BiomeId::nil())), None);
+    }
 }
@@ -63,6 +63,9 @@ impl Plugin for PreferencesPlugin {
 #[derive(Clone, Deserialize, PartialEq, Resource, Reflect, Serialize)]
 #[reflect(Resource)]
 pub struct Preferences {
+    /// Seconds between autosaves, 0 disables autosave.
+    #[serde(default = "default_autosave_interval")]
+    pub autosave_interval: u32,
     pub max_undo_stack_size: NonZeroUsize,
 
     #[serde(skip)]
@@ -72,6 +75,7 @@ pub struct Preferences {
 impl Default for Preferences {
     fn default() -> Self {
         Preferences {
+            autosave_interval: constants::AUTOSAVE_INTERVAL_DEFAULT,
             max_undo_stack_size: constants::UNDO_STACK_SIZE_DEFAULT,
             file_path: None,
         }
@@ -117,6 +121,10 @@ pub enum PreferencesError {
     SerializeError(#[from] TomlSerializeError),
 }
 
+fn default_autosave_interval() -> u32 {
+    constants::AUTOSAVE_INTERVAL_DEFAULT
+}
+
 fn read_or_create<P: AsRef<Path>>(file_path: P) -> Result<Preferences, PreferencesError> {
     let mut preferences = if fs::exists(&file_path)
         .map_err(|e| PreferencesError::CannotReadFile(file_path.as_ref().into(), e))?
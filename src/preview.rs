@@ -15,6 +15,7 @@
 // with Yer.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::num::NonZeroU8;
+use std::path::PathBuf;
 use std::sync::{mpsc, Arc, Mutex, TryLockError};
 use std::time::Duration;
 
@@ -23,8 +24,9 @@ use bevy::prelude::*;
 use bevy::tasks::{futures_lite::future, AsyncComputeTaskPool, Task, TaskPool};
 use serde::{Deserialize, Serialize};
 
+use crate::biome;
 use crate::layer;
-use crate::math::{Sample, Sampler2D};
+use crate::math::{aspect_angle, run_filter, slope_angle, HeightGrid, Sample, Sampler2D};
 use crate::undo;
 use crate::viewport;
 
@@ -62,14 +64,25 @@ impl Plugin for PreviewPlugin {
         app.register_type::<ActivePreview>()
             .register_type::<Preview>()
             .register_type::<PreviewGrid2D>()
-            .register_type::<PreviewRegion>();
+            .register_type::<PreviewOverlay>()
+            .register_type::<PreviewRegion>()
+            .register_type::<TerrainInfo>();
         app.add_message::<UpdatePreviewRegion>();
-        app.init_resource::<Preview>();
+        app.init_resource::<Preview>()
+            .init_resource::<PreviewOverlay>()
+            .init_resource::<TerrainInfo>();
         app.add_systems(
             Update,
             (
                 manage_preview_system,
+                update_preview_overlay_system.run_if(
+                    resource_changed::<PreviewOverlay>.or(resource_changed::<biome::BiomeTable>),
+                ),
                 update_preview_region_system.run_if(on_message::<UpdatePreviewRegion>),
+                update_terrain_info_system.run_if(
+                    resource_changed::<viewport::TerrainCursor>
+                        .or(any_match_filter::<(With<ActivePreview>, Changed<PreviewGrid2D>)>),
+                ),
             ),
         );
     }
@@ -113,6 +126,20 @@ impl Preview {
     }
 }
 
+/// What is drawn on the preview mesh, besides its shape.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Reflect, Resource)]
+#[reflect(Resource)]
+pub enum PreviewOverlay {
+    #[default]
+    None,
+    Biomes,
+}
+
+/// Terrain under the cursor, see [viewport::TerrainCursor].
+#[derive(Clone, Debug, Default, PartialEq, Reflect, Resource)]
+#[reflect(Resource)]
+pub struct TerrainInfo(pub Option<TerrainPoint>);
+
 // BUNDLES
 
 #[derive(Bundle, Deserialize, Serialize)]
@@ -121,11 +148,18 @@ pub struct PreviewBundle {
     // ActivePreview should only be on the active preview but
     // since we're having only one preview region now, this
     // should be okay.
+    //
+    // ActivePreview has no data, and TOML cannot represent a unit value.
+    #[serde(default, skip_serializing)]
     active_preview: ActivePreview,
     preview_region: PreviewRegion,
 }
 
 impl PreviewBundle {
+    pub fn preview_region(&self) -> &PreviewRegion {
+        &self.preview_region
+    }
+
     pub fn extract_all(world: &mut World) -> Vec<Self> {
         world
             .query::<(&Name, &ActivePreview, &PreviewRegion)>()
@@ -142,7 +176,7 @@ impl PreviewBundle {
 // COMPONENTS
 
 /// Marker trait for active preview.
-#[derive(Clone, Component, Copy, Debug, Deserialize, Reflect, Serialize)]
+#[derive(Clone, Component, Copy, Debug, Default, Deserialize, Reflect, Serialize)]
 #[reflect(Component)]
 struct ActivePreview;
 
@@ -150,12 +184,12 @@ struct ActivePreview;
 #[reflect(Component)]
 struct PreviewGrid2D {
     bounds: Rect,
-    samples: Vec<(Vec2, f32)>,
+    samples: Vec<(Vec2, Sample)>,
     subdivisions: u8,
 }
 
 impl PreviewGrid2D {
-    fn new(samples: Vec<(Vec2, f32)>) -> Self {
+    fn new(samples: Vec<(Vec2, Sample)>) -> Self {
         let subdivisions = {
             let samples_count: u32 = u32::try_from(samples.len()).unwrap();
             SUBDIVISIONS_VERTS_TABLE
@@ -193,7 +227,8 @@ impl PreviewGrid2D {
         }
     }
 
-    fn build_mesh(&self) -> Mesh {
+    /// Build the preview mesh, colored by biome if `biome_table` is given.
+    fn build_mesh(&self, biome_table: Option<&biome::BiomeTable>) -> Mesh {
         let mut mesh = PlaneMeshBuilder::new(Dir3::Z, self.bounds.size())
             .subdivisions(2u32.pow(self.subdivisions.into()) - 1)
             .build();
@@ -203,11 +238,26 @@ impl PreviewGrid2D {
                     // Preview mesh is Z-up.
                     p[0] = self.samples[idx].0.x;
                     p[1] = self.samples[idx].0.y;
-                    p[2] = self.samples[idx].1;
+                    p[2] = self.samples[idx].1.height();
                 }
             }
             _ => unreachable!(),
         }
+        if let Some(biome_table) = biome_table {
+            let height_grid: HeightGrid = self.height_grid();
+            let colors: Vec<[f32; 4]> = self
+                .samples
+                .iter()
+                .enumerate()
+                .map(|(idx, (_, sample))| {
+                    biome_table
+                        .classify_color(sample, height_grid.gradient(idx))
+                        .to_linear()
+                        .to_f32_array()
+                })
+                .collect();
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        }
         // TODO: Implement proper flat shaded quads.
         //
         //       This renders triangles as flat shaded,
@@ -217,6 +267,62 @@ impl PreviewGrid2D {
         mesh.compute_flat_normals();
         mesh
     }
+
+    /// Heights of the samples, rows are ordered from top to bottom.
+    fn height_grid(&self) -> HeightGrid {
+        let k: u32 = SUBDIVISIONS_SQRT_VERTS_TABLE[self.subdivisions as usize];
+        HeightGrid::new(
+            self.samples[0].0,
+            self.spacing(),
+            UVec2::splat(k),
+            self.samples
+                .iter()
+                .map(|(_, sample)| sample.height())
+                .collect(),
+        )
+    }
+
+    /// Heights of the samples adjacent to `idx` and the index of `idx` in it.
+    ///
+    /// Gradient at `idx` is the same as the one [Self::height_grid] gives,
+    /// without building the whole grid.
+    fn height_grid_around(&self, idx: usize) -> (HeightGrid, usize) {
+        let k: u32 = SUBDIVISIONS_SQRT_VERTS_TABLE[self.subdivisions as usize];
+        let (x, y) = (idx as u32 % k, idx as u32 / k);
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(k - 1));
+        let (y0, y1) = (y.saturating_sub(1), (y + 1).min(k - 1));
+        let heights: Vec<f32> = (y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| (y * k + x) as usize))
+            .map(|idx| self.samples[idx].1.height())
+            .collect();
+        let height_grid = HeightGrid::new(
+            self.samples[(y0 * k + x0) as usize].0,
+            self.spacing(),
+            UVec2::new(x1 - x0 + 1, y1 - y0 + 1),
+            heights,
+        );
+        let center: usize = height_grid.index(x - x0, y - y0);
+        (height_grid, center)
+    }
+
+    /// Index of the sample nearest to `position`.
+    ///
+    /// Positions outside the bounds are clamped.
+    fn nearest_index(&self, position: Vec2) -> usize {
+        let k: u32 = SUBDIVISIONS_SQRT_VERTS_TABLE[self.subdivisions as usize];
+        let last: f32 = (k - 1) as f32;
+        // Y is inverted.
+        let offset: Vec2 = (position - self.samples[0].0) * Vec2::new(1.0, -1.0);
+        let x = (offset.x / self.spacing()).round().clamp(0.0, last) as u32;
+        let y = (offset.y / self.spacing()).round().clamp(0.0, last) as u32;
+        (y * k + x) as usize
+    }
+
+    /// Distance between two adjacent samples.
+    fn spacing(&self) -> f32 {
+        let k: u32 = SUBDIVISIONS_SQRT_VERTS_TABLE[self.subdivisions as usize];
+        self.bounds.width() / (k - 1) as f32
+    }
 }
 
 #[derive(Component, Clone, Debug, Deserialize, Reflect, Serialize)]
@@ -267,6 +373,34 @@ impl Default for PreviewRegion {
 
 // COMMANDS
 
+/// Export the biomes of the active preview as an index map.
+///
+/// See [biome::write_index_map].
+pub struct ExportBiomeMap(pub PathBuf);
+
+impl Command for ExportBiomeMap {
+    fn apply(self, world: &mut World) {
+        let path = self.0;
+        let mut previews = world.query_filtered::<&PreviewGrid2D, With<ActivePreview>>();
+        let Some(preview_grid) = previews.iter(world).next() else {
+            error!("There is no preview to export the biomes of.");
+            return;
+        };
+        let biome_table = world.resource::<biome::BiomeTable>();
+        let height_grid: HeightGrid = preview_grid.height_grid();
+        let indices: Vec<Option<usize>> = preview_grid
+            .samples
+            .iter()
+            .enumerate()
+            .map(|(idx, (_, sample))| biome_table.classify(sample, height_grid.gradient(idx)))
+            .collect();
+        info!("Exporting biome map to '{}'", path.display());
+        if let Err(e) = biome::write_index_map(path.as_path(), height_grid.size(), &indices) {
+            error!(error = &e as &dyn core::error::Error);
+        }
+    }
+}
+
 struct CalculatePreview;
 
 impl Command for CalculatePreview {
@@ -278,42 +412,11 @@ impl Command for CalculatePreview {
             .map(|(e, p)| (e, p.clone()))
             .next()
             .unwrap();
-        let layers: Vec<Box<dyn Sampler2D>> = {
-            let entities_and_height_maps: Vec<(Entity, layer::HeightMap)> = world
-                .query::<(Entity, &layer::Layer, &layer::LayerOrder, &layer::HeightMap)>()
-                .iter(world)
-                .sort_unstable::<&layer::LayerOrder>()
-                .filter(|(_, layer, _, _)| layer.enable_preview)
-                .map(|(entity, _, _, height_map)| (entity, height_map.clone()))
-                .collect();
-            entities_and_height_maps
+        let layers: Vec<Box<dyn Sampler2D>> =
+            layer::extract_samplers(world, |layer| layer.enable_preview)
                 .into_iter()
-                .map(|(entity, height_map)| {
-                    let children: Vec<Entity> = world
-                        .entity(entity)
-                        .get::<Children>()
-                        .map(|children| children.to_vec())
-                        .unwrap_or_default();
-                    let masks: Vec<(layer::Mask, layer::MaskSource)> = world
-                        .entity(children.as_slice())
-                        .iter()
-                        .map(|entity_ref| {
-                            (
-                                entity_ref.get::<layer::Mask>(),
-                                entity_ref.get::<layer::MaskSource>(),
-                            )
-                        })
-                        .filter(|(mask, mask_source)| {
-                            mask.map(|m| m.is_enabled).unwrap_or(false) && mask_source.is_some()
-                        })
-                        .map(|(mask, mask_source)| {
-                            (mask.unwrap().clone(), mask_source.unwrap().clone())
-                        })
-                        .collect();
-                    Box::new(layer::LayerSampler { height_map, masks }) as Box<dyn Sampler2D>
-                })
-                .collect()
-        };
+                .map(|(_, layer)| layer)
+                .collect();
         let task_pool = AsyncComputeTaskPool::get();
         world.resource_mut::<Preview>().start_new_task(
             task_pool,
@@ -336,13 +439,19 @@ impl Command for UpdatePreviewMesh {
                 .map(|preview_data| preview_data.subdivisions)
                 .unwrap()
         );
-        let mesh: Mesh = world
+        let mesh: Mesh = {
             // We want to update the mesh only if the preview region is still
             // the active region, hence With<ActivePreview>.
-            .query_filtered::<&PreviewGrid2D, With<ActivePreview>>()
-            .get(world, self.0)
-            .map(|preview_data| preview_data.build_mesh())
-            .unwrap();
+            let mut previews = world.query_filtered::<&PreviewGrid2D, With<ActivePreview>>();
+            let biome_table: Option<&biome::BiomeTable> = match world.resource::<PreviewOverlay>() {
+                PreviewOverlay::None => None,
+                PreviewOverlay::Biomes => Some(world.resource::<biome::BiomeTable>()),
+            };
+            previews
+                .get(world, self.0)
+                .map(|preview_data| preview_data.build_mesh(biome_table))
+                .unwrap()
+        };
 
         let preview_mesh_entity: Entity = world
             .query_filtered::<Entity, With<viewport::PreviewMesh>>()
@@ -422,6 +531,16 @@ fn manage_preview_system(
     }
 }
 
+/// Rebuild the preview mesh when its overlay needs to be redrawn.
+fn update_preview_overlay_system(
+    mut commands: Commands,
+    previews: Query<Entity, (With<ActivePreview>, With<PreviewGrid2D>)>,
+) {
+    for entity in previews.iter() {
+        commands.queue(UpdatePreviewMesh(entity));
+    }
+}
+
 fn update_preview_region_system(
     mut preview_regions: Query<&mut PreviewRegion>,
     mut update_preview_region_events: MessageReader<UpdatePreviewRegion>,
@@ -447,6 +566,17 @@ fn update_preview_region_system(
     }
 }
 
+/// Update terrain info when the cursor moves or the preview is updated.
+fn update_terrain_info_system(world: &mut World) {
+    let terrain_point: Option<TerrainPoint> = world
+        .resource::<viewport::TerrainCursor>()
+        .0
+        .and_then(|position| TerrainPoint::extract(world, position));
+    world
+        .resource_mut::<TerrainInfo>()
+        .set_if_neq(TerrainInfo(terrain_point));
+}
+
 // LIB
 
 #[derive(Debug)]
@@ -545,6 +675,71 @@ pub fn create_default_preview_region(world: &mut World) {
     });
 }
 
+/// Sample of a layer at a [TerrainPoint].
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct LayerInfo {
+    /// Whether a filter is applied before the layer.  Its effect is not
+    /// included in the sample, so it may differ from the preview.
+    pub approximate: bool,
+    pub name: String,
+    /// Filters cannot be sampled at a single point, their sample is `None`.
+    pub sample: Option<Sample>,
+}
+
+/// Terrain at a point of the active preview.
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct TerrainPoint {
+    /// See [aspect_angle].
+    pub aspect: Option<f32>,
+    pub biome: Option<String>,
+    /// Samples of the individual layers, in the order they are applied.
+    pub layers: Vec<LayerInfo>,
+    pub position: Vec2,
+    /// Sample of all the layers composited, as it is previewed.
+    pub sample: Sample,
+    /// Slope angle, in degrees.
+    pub slope: f32,
+}
+
+impl TerrainPoint {
+    /// Terrain at `position` if there is an active preview.
+    ///
+    /// Composited values are read from the preview sample nearest to
+    /// `position`, the layers are sampled exactly at `position`.
+    fn extract(world: &mut World, position: Vec2) -> Option<Self> {
+        let (sample, gradient, spacing) = {
+            let mut previews = world.query_filtered::<&PreviewGrid2D, With<ActivePreview>>();
+            let preview_grid: &PreviewGrid2D = previews.iter(world).next()?;
+            let idx: usize = preview_grid.nearest_index(position);
+            let (height_grid, center) = preview_grid.height_grid_around(idx);
+            (
+                preview_grid.samples[idx].1.clone(),
+                height_grid.gradient(center),
+                preview_grid.spacing(),
+            )
+        };
+        let biome: Option<String> = {
+            let biome_table = world.resource::<biome::BiomeTable>();
+            biome_table
+                .classify(&sample, gradient)
+                .map(|idx| biome_table.biomes()[idx].name.clone())
+        };
+        let layers: Vec<LayerInfo> = sample_layers_at(
+            layer::extract_samplers(world, |layer| layer.enable_preview),
+            position,
+            spacing,
+        );
+        Some(Self {
+            aspect: aspect_angle(gradient),
+            biome,
+            layers,
+            position,
+            sample,
+            slope: slope_angle(gradient),
+        })
+    }
+}
+
 #[inline]
 fn even(x: u32) -> bool {
     x % 2 == 0
@@ -574,7 +769,18 @@ async fn sample_layers(
         Vec2::new(g, -g)
     };
 
-    let mut samples: Vec<(Vec2, f32)> = vec![];
+    if layers
+        .iter()
+        .any(|layer| layer.is_filter() || layer.needs_gradient())
+    {
+        // Filters are expensive, they are only applied at the final level.
+        let apply_filters: bool = subdivisions == preview_region.subdivisions;
+        return PreviewGrid2D::new(
+            sample_layers_with_filters(k, start, gap.x, layers, apply_filters).await,
+        );
+    }
+
+    let mut samples: Vec<(Vec2, Sample)> = vec![];
     for y in 0..k {
         for x in 0..k {
             // Y is inverted.
@@ -589,8 +795,7 @@ async fn sample_layers(
                     let idx: usize = ((y / 2)
                         * SUBDIVISIONS_SQRT_VERTS_TABLE[*subdivisions as usize] as u32
                         + (x / 2)) as usize;
-                    let h: f32 = previous_samples[idx].1;
-                    samples.push((p, h));
+                    samples.push((p, previous_samples[idx].1.clone()));
                 } else {
                     unreachable!()
                 }
@@ -600,7 +805,7 @@ async fn sample_layers(
                     sample.mix_in_place(&layer.sample(p, &sample));
                 }
 
-                samples.push((p, sample.height()));
+                samples.push((p, sample));
                 // Yield control at every calculated sample.
                 future::yield_now().await;
             }
@@ -609,6 +814,119 @@ async fn sample_layers(
     PreviewGrid2D::new(samples)
 }
 
+/// Sample layers one at a time over the whole grid.
+///
+/// Filters and layers with slope dependent masks need all the heights beneath
+/// them, so the samples of previous previews cannot be reused either; their
+/// output depends on the resolution.  Filters are skipped unless
+/// `apply_filters` is set.
+async fn sample_layers_with_filters(
+    k: u32,
+    start: Vec2,
+    gap: f32,
+    layers: &Layers,
+    apply_filters: bool,
+) -> Vec<(Vec2, Sample)> {
+    let size = UVec2::splat(k);
+    let positions: Vec<Vec2> = {
+        let grid = HeightGrid::new(start, gap, size, vec![0.0; (k * k) as usize]);
+        (0..(k * k) as usize)
+            .map(|idx| grid.position(idx))
+            .collect()
+    };
+    let mut samples: Vec<Sample> = vec![Sample::default(); positions.len()];
+    for layer in layers.iter() {
+        if layer.is_filter() && !apply_filters {
+            continue;
+        }
+        let grid: Option<HeightGrid> = (layer.is_filter() || layer.needs_gradient()).then(|| {
+            HeightGrid::new(
+                start,
+                gap,
+                size,
+                samples.iter().map(|sample| sample.height()).collect(),
+            )
+        });
+        if layer.is_filter() {
+            if let Some(filter) = layer.filter(grid.as_ref().unwrap(), &samples) {
+                let filtered: HeightGrid = run_filter(filter, |_| ()).await;
+                for (sample, height) in samples.iter_mut().zip(filtered.into_heights()) {
+                    sample.set_height(height);
+                }
+            }
+            future::yield_now().await;
+        } else {
+            for (idx, (p, sample)) in positions.iter().zip(samples.iter_mut()).enumerate() {
+                let layer_sample = layer.sample_with_gradient(
+                    *p,
+                    sample,
+                    grid.as_ref().map(|grid| grid.gradient(idx)),
+                );
+                sample.mix_in_place(&layer_sample);
+                // Yield control at every calculated sample.
+                future::yield_now().await;
+            }
+        }
+    }
+    positions.into_iter().zip(samples).collect()
+}
+
+/// Sample layers one at a time at `position`, returns the sample of each.
+///
+/// Gradients are estimated from the samples `spacing` away from `position`.
+///
+/// Filters need the whole grid, so they are skipped and their samples are
+/// `None`.  Layers after a filter are sampled on top of the unfiltered
+/// terrain, they are marked as [LayerInfo::approximate].  The composited
+/// sample of [TerrainPoint] is read from the preview, so it does include
+/// the filters.
+fn sample_layers_at(
+    layers: Vec<(String, Box<dyn Sampler2D>)>,
+    position: Vec2,
+    spacing: f32,
+) -> Vec<LayerInfo> {
+    // Position itself, followed by +X, -X, +Y and -Y.
+    let positions: [Vec2; 5] = [
+        position,
+        position + Vec2::X * spacing,
+        position - Vec2::X * spacing,
+        position + Vec2::Y * spacing,
+        position - Vec2::Y * spacing,
+    ];
+    let mut samples: [Sample; 5] = Default::default();
+    let mut layer_infos: Vec<LayerInfo> = vec![];
+    let mut approximate: bool = false;
+    for (name, layer) in layers.into_iter() {
+        if layer.is_filter() {
+            layer_infos.push(LayerInfo {
+                approximate,
+                name,
+                sample: None,
+            });
+            approximate = true;
+            continue;
+        }
+        let gradient: Option<Vec2> = layer.needs_gradient().then(|| {
+            Vec2::new(
+                samples[1].height() - samples[2].height(),
+                samples[3].height() - samples[4].height(),
+            ) / (2.0 * spacing)
+        });
+        for (idx, (p, sample)) in positions.iter().zip(samples.iter_mut()).enumerate() {
+            let layer_sample = layer.sample_with_gradient(*p, sample, gradient);
+            sample.mix_in_place(&layer_sample);
+            if idx == 0 {
+                layer_infos.push(LayerInfo {
+                    approximate,
+                    name: name.clone(),
+                    sample: Some(layer_sample),
+                });
+            }
+        }
+    }
+    layer_infos
+}
+
 #[cfg(test)]
 mod tests {
     use std::thread;
@@ -618,7 +936,7 @@ mod tests {
 
     use super::*;
     use crate::layer;
-    use crate::math::approx_eq;
+    use crate::math::{approx_eq, Filter};
 
     #[test]
     fn compute_preview_returns_a_result_and_gets_finished() {
@@ -670,7 +988,7 @@ mod tests {
 
     #[test]
     fn preview_grid_subdivisions_are_calculated_correctly() {
-        let p = (Vec2::ZERO, 0.0f32);
+        let p = (Vec2::ZERO, Sample::default());
         let cases: Vec<(usize, u8)> = vec![
             (4, 0),
             (9, 1),
@@ -727,9 +1045,9 @@ mod tests {
             &layers,
             Some(&previous_preview),
         ));
-        assert_eq!(preview.samples[0].1, PREVIOUS_HEIGHT);
+        assert_eq!(preview.samples[0].1.height(), PREVIOUS_HEIGHT);
         assert!(approx_eq(
-            preview.samples[preview.samples.len() - 1].1,
+            preview.samples[preview.samples.len() - 1].1.height(),
             PREVIOUS_HEIGHT,
             0.001 // 0.1%
         ));
@@ -737,9 +1055,158 @@ mod tests {
             preview
                 .samples
                 .iter()
-                .filter(|(_, h)| approx_eq(*h, PREVIOUS_HEIGHT, 0.001))
+                .filter(|(_, s)| approx_eq(s.height(), PREVIOUS_HEIGHT, 0.001))
                 .count(),
             previous_preview.samples.len()
         );
     }
+
+    #[test]
+    fn sample_layers_do_not_reuse_samples_with_filters() {
+        const PREVIOUS_HEIGHT: f32 = 1.0;
+        const HEIGHT: f32 = 0.0;
+
+        let previous_subdivisions = MIN_SUBDIVISIONS;
+        let subdivisions = previous_subdivisions.checked_add(1).unwrap();
+        let preview_region = PreviewRegion {
+            subdivisions,
+            ..default()
+        };
+        let previous_layers: Layers =
+            Arc::new([Box::new(layer::HeightMap::Constant(PREVIOUS_HEIGHT))]);
+        let layers: Layers = Arc::new([
+            Box::new(layer::HeightMap::Constant(HEIGHT)),
+            Box::new(layer::HeightMap::hydraulic_erosion()),
+        ]);
+        let previous_preview = block_on(sample_layers(
+            previous_subdivisions,
+            &preview_region,
+            &previous_layers,
+            None,
+        ));
+        let preview = block_on(sample_layers(
+            subdivisions,
+            &preview_region,
+            &layers,
+            Some(&previous_preview),
+        ));
+        // Flat terrain is not eroded.
+        assert!(preview.samples.iter().all(|(_, s)| s.height() == HEIGHT));
+    }
+
+    #[test]
+    fn sample_layers_apply_filters_only_at_the_final_level() {
+        /// Raises all the heights by its value.
+        struct RaiseFilter(f32);
+
+        impl Sampler2D for RaiseFilter {
+            fn sample(&self, _position: Vec2, base_sample: &Sample) -> Sample {
+                base_sample.clone()
+            }
+
+            fn filter<'a>(
+                &'a self,
+                grid: &HeightGrid,
+                _base_samples: &'a [Sample],
+            ) -> Option<Box<dyn Filter + 'a>> {
+                let mut grid: HeightGrid = grid.clone();
+                grid.heights_mut().iter_mut().for_each(|h| *h += self.0);
+                Some(Box::new(Raised(grid)))
+            }
+
+            fn is_filter(&self) -> bool {
+                true
+            }
+        }
+
+        struct Raised(HeightGrid);
+
+        impl Filter for Raised {
+            fn step(&mut self) -> bool {
+                false
+            }
+
+            fn progress(&self) -> f32 {
+                1.0
+            }
+
+            fn finish(self: Box<Self>) -> HeightGrid {
+                self.0
+            }
+        }
+
+        const HEIGHT: f32 = 10.0;
+
+        let preview_region = PreviewRegion {
+            subdivisions: MIN_SUBDIVISIONS.checked_add(1).unwrap(),
+            ..default()
+        };
+        let layers: Layers = Arc::new([
+            Box::new(layer::HeightMap::Constant(HEIGHT)),
+            Box::new(RaiseFilter(1.0)),
+        ]);
+        let preview = block_on(sample_layers(
+            MIN_SUBDIVISIONS,
+            &preview_region,
+            &layers,
+            None,
+        ));
+        assert!(preview.samples.iter().all(|(_, s)| s.height() == HEIGHT));
+        let preview = block_on(sample_layers(
+            preview_region.subdivisions,
+            &preview_region,
+            &layers,
+            Some(&preview),
+        ));
+        assert!(preview
+            .samples
+            .iter()
+            .all(|(_, s)| s.height() == HEIGHT + 1.0));
+    }
+
+    #[test]
+    fn height_grid_around_gives_the_same_gradients() {
+        let preview_region = PreviewRegion::default();
+        let layers: Layers = Arc::new([Box::new(layer::HeightMap::summit())]);
+        let preview = block_on(sample_layers(
+            MIN_SUBDIVISIONS,
+            &preview_region,
+            &layers,
+            None,
+        ));
+        let height_grid: HeightGrid = preview.height_grid();
+        for idx in 0..preview.samples.len() {
+            let (neighbours, center) = preview.height_grid_around(idx);
+            assert_eq!(neighbours.gradient(center), height_grid.gradient(idx));
+            assert_eq!(preview.nearest_index(preview.samples[idx].0), idx);
+        }
+    }
+
+    #[test]
+    fn sample_layers_at_marks_the_layers_after_filters() {
+        let layers: Vec<(String, Box<dyn Sampler2D>)> = vec![
+            (
+                "Base".to_owned(),
+                Box::new(layer::HeightMap::Constant(10.0)),
+            ),
+            (
+                "Erosion".to_owned(),
+                Box::new(layer::HeightMap::hydraulic_erosion()),
+            ),
+            ("Top".to_owned(), Box::new(layer::HeightMap::Constant(20.0))),
+        ];
+        let layer_infos: Vec<(Option<f32>, bool)> = sample_layers_at(layers, Vec2::ZERO, 1.0)
+            .iter()
+            .map(|layer_info| {
+                (
+                    layer_info.sample.as_ref().map(|s| s.height()),
+                    layer_info.approximate,
+                )
+            })
+            .collect();
+        assert_eq!(
+            layer_infos,
+            vec![(Some(10.0), false), (None, false), (Some(20.0), true)]
+        );
+    }
 }
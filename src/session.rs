@@ -20,16 +20,26 @@ use std::path::{Path, PathBuf};
 use bevy::prelude::*;
 use thiserror::Error;
 
+use crate::bake;
+use crate::biome;
 use crate::layer;
 use crate::preview;
 use crate::undo;
 
-mod save;
+mod autosave;
+pub mod save;
 
-pub struct SessionPlugin;
+pub use autosave::PendingRecovery;
+
+pub struct SessionPlugin {
+    pub autosave_dir: PathBuf,
+}
 
 impl Plugin for SessionPlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins(autosave::AutosavePlugin {
+            autosave_dir: self.autosave_dir.clone(),
+        });
         app.register_type::<Session>()
             .init_resource::<Session>()
             .add_systems(Startup, startup_system)
@@ -52,6 +62,9 @@ pub struct Session {
     /// applied action.  `None` if we don't have an action to refer to as the
     /// last saved action.
     saved_action_idx: Option<i32>,
+    /// Whether the session is restored from an autosave.  Restored changes
+    /// are not saved to `loaded_from` until the user saves.
+    recovered: bool,
 }
 
 impl Session {
@@ -64,7 +77,7 @@ impl Session {
     }
 
     pub fn has_unsaved_changes(&self) -> bool {
-        !self.new_project && self.saved_action_idx != Some(0)
+        self.recovered || (!self.new_project && self.saved_action_idx != Some(0))
     }
 
     pub fn set_file_path<T: Into<PathBuf>>(&mut self, path: T) {
@@ -76,10 +89,8 @@ impl Session {
 
 /// Clears existing session and creates a new, empty one.
 ///
-/// Note that the app should ask to save an edited project first before
-/// clearing everything.  But this is not implemented yet because Undo system
-/// is not implemented and there is no way of knowing whether suggesting a
-/// save is necessary or not.
+/// Unsaved changes are lost, the UI asks to save them before queueing this.
+/// See [Session::has_unsaved_changes].
 pub struct InitializeNewSession;
 
 impl Command for InitializeNewSession {
@@ -90,13 +101,30 @@ impl Command for InitializeNewSession {
             session.loaded_from = None;
             session.saved_action_idx = None;
             session.new_project = true;
+            session.recovered = false;
         }
         clear_session(world);
+        autosave::remove_recovery(world);
         layer::create_initial_layer(world);
         preview::create_default_preview_region(world);
     }
 }
 
+/// Removes the files of a pending recovery, given its index in
+/// [PendingRecovery].
+pub struct DiscardRecovery(pub usize);
+
+impl Command for DiscardRecovery {
+    fn apply(self, world: &mut World) {
+        let mut pending_recovery = world.resource_mut::<PendingRecovery>();
+        if self.0 < pending_recovery.0.len() {
+            let recovery = pending_recovery.0.remove(self.0);
+            info!("Discarding '{}'", recovery.path.to_string_lossy());
+            recovery.remove();
+        }
+    }
+}
+
 pub struct LoadSession(pub PathBuf);
 
 impl Command for LoadSession {
@@ -105,12 +133,62 @@ impl Command for LoadSession {
         info!("Loading file '{}'", path.to_str().unwrap());
         clear_session(world);
         match save::load(path.as_path(), world).map_err(|e| SessionError::SaveError(e)) {
-            Ok(_) => world.resource_mut::<Session>().set_file_path(path),
+            Ok(_) => {
+                let mut session = world.resource_mut::<Session>();
+                session.recovered = false;
+                session.set_file_path(path);
+                autosave::remove_recovery(world);
+            }
             Err(e) => error!(error = &e as &dyn core::error::Error),
         }
     }
 }
 
+/// Keeps the files of the pending recoveries and resumes autosave.
+///
+/// They are offered again on the next start.
+pub struct PostponeRecovery;
+
+impl Command for PostponeRecovery {
+    fn apply(self, world: &mut World) {
+        world.resource_mut::<PendingRecovery>().0.clear();
+    }
+}
+
+/// Replaces the session with a pending recovery, given its index in
+/// [PendingRecovery].
+///
+/// Recovery files are kept until the restored session is saved.  Other
+/// pending recoveries are postponed.
+pub struct RestoreRecovery(pub usize);
+
+impl Command for RestoreRecovery {
+    fn apply(self, world: &mut World) {
+        let mut pending_recovery = world.resource_mut::<PendingRecovery>();
+        if self.0 >= pending_recovery.0.len() {
+            return;
+        }
+        let recovery = pending_recovery.0.remove(self.0);
+        pending_recovery.0.clear();
+        info!("Restoring from '{}'", recovery.path.to_string_lossy());
+        let path: PathBuf = recovery.path.clone();
+        let source: Option<PathBuf> = recovery.source.clone();
+        autosave::adopt_recovery(world, recovery);
+        clear_session(world);
+        match save::load(path.as_path(), world).map_err(|e| SessionError::SaveError(e)) {
+            Ok(_) => {
+                let mut session = world.resource_mut::<Session>();
+                session.loaded_from = source;
+                session.recovered = true;
+            }
+            Err(e) => {
+                error!(error = &e as &dyn core::error::Error);
+                InitializeNewSession.apply(world);
+            }
+        }
+    }
+}
+
 pub struct SaveSession(pub Option<PathBuf>);
 
 impl Command for SaveSession {
@@ -126,7 +204,9 @@ impl Command for SaveSession {
                     Ok(_) => {
                         let mut session = world.resource_mut::<Session>();
                         session.saved_action_idx = Some(0);
-                        session.set_file_path(path)
+                        session.recovered = false;
+                        session.set_file_path(path);
+                        autosave::remove_recovery(world);
                     }
                     Err(e) => {
                         error!(error = &e as &dyn core::error::Error)
@@ -206,6 +286,12 @@ fn clear_session(world: &mut World) {
     // Clear undo stack.
     undo::ClearStack.apply(world);
 
+    // Clear biomes.
+    *world.resource_mut::<biome::BiomeTable>() = biome::BiomeTable::default();
+
+    // Reset bake settings.
+    *world.resource_mut::<bake::BakeSettings>() = bake::BakeSettings::default();
+
     // Despawn all layers.
     {
         let layers: Vec<Entity> = world
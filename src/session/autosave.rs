@@ -0,0 +1,455 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::constants;
+use crate::preferences::Preferences;
+use crate::undo;
+
+use super::save;
+use super::Session;
+
+const RECOVERY_EXTENSION: &str = "yer";
+const RECOVERY_FILE_PREFIX: &str = "recovery-";
+const RECOVERY_INFO_EXTENSION: &str = "toml";
+const RECOVERY_LOCK_EXTENSION: &str = "lock";
+
+// PLUGIN
+
+pub struct AutosavePlugin {
+    pub autosave_dir: PathBuf,
+}
+
+impl Plugin for AutosavePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Autosave>()
+            .register_type::<PendingRecovery>()
+            .insert_resource(Autosave::new(self.autosave_dir.clone()))
+            .insert_resource(PendingRecovery(find_recoveries(&self.autosave_dir)))
+            .add_systems(
+                Update,
+                (
+                    update_autosave_interval_system
+                        .run_if(resource_exists_and_changed::<Preferences>),
+                    process_undo_events_system,
+                    tick_autosave_timer_system,
+                    autosave_system.run_if(is_autosave_due),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Last,
+                remove_recovery_on_exit_system.run_if(on_message::<AppExit>),
+            );
+    }
+}
+
+// RESOURCES
+
+#[derive(Debug, Reflect, Resource)]
+#[reflect(Resource)]
+pub struct Autosave {
+    autosave_dir: PathBuf,
+    /// Whether there are changes since the last autosave.
+    has_changes: bool,
+    /// Lock of the recovery files of this session, held while they exist.
+    #[reflect(ignore)]
+    lock: Option<Arc<fs::File>>,
+    /// Recovery files are named after the session, so that instances running
+    /// at the same time do not overwrite each other's.
+    session_id: String,
+    timer: Timer,
+}
+
+impl Autosave {
+    fn new(autosave_dir: PathBuf) -> Self {
+        let mut autosave = Self {
+            autosave_dir,
+            has_changes: false,
+            lock: None,
+            session_id: uuid::Uuid::now_v7().to_string(),
+            timer: Timer::default(),
+        };
+        autosave.set_interval(constants::AUTOSAVE_INTERVAL_DEFAULT);
+        autosave
+    }
+
+    /// Lock the recovery files of this session, unless they are locked
+    /// already.
+    fn lock(&mut self) -> Result<(), AutosaveError> {
+        if self.lock.is_none() {
+            let path: PathBuf = self.recovery_files().lock_path;
+            let lock: fs::File = try_lock(&path)?.ok_or(AutosaveError::Locked(path))?;
+            self.lock = Some(Arc::new(lock));
+        }
+        Ok(())
+    }
+
+    fn recovery_files(&self) -> RecoveryFiles {
+        RecoveryFiles::new(&self.autosave_dir, &self.session_id)
+    }
+
+    /// Set the interval in seconds, 0 disables autosave.
+    fn set_interval(&mut self, seconds: u32) {
+        self.timer = Timer::new(
+            Duration::from_secs(seconds.max(1).into()),
+            TimerMode::Repeating,
+        );
+        if seconds == 0 {
+            self.timer.pause();
+        }
+    }
+}
+
+/// Autosaves left behind by sessions that did not exit cleanly, the most
+/// recent session first.
+///
+/// They are offered to the user on startup.  Autosave is suspended until
+/// they are either restored, discarded or postponed.
+#[derive(Debug, Default, Reflect, Resource)]
+#[reflect(Resource)]
+pub struct PendingRecovery(pub Vec<Recovery>);
+
+// SYSTEMS
+
+fn autosave_system(world: &mut World) {
+    let source: Option<PathBuf> = world
+        .resource::<Session>()
+        .get_file_path()
+        .map(|p| p.into_owned());
+    let files: RecoveryFiles = {
+        let mut autosave = world.resource_mut::<Autosave>();
+        if let Err(e) = autosave.lock() {
+            error!(error = &e as &dyn core::error::Error);
+            return;
+        }
+        autosave.recovery_files()
+    };
+    debug!("Autosaving to '{}'", files.path.to_string_lossy());
+    match save::save(&files.path, world)
+        .map_err(|e| AutosaveError::SaveError(e))
+        .and_then(|_| write_recovery_info(&files.info_path, &RecoveryInfo { source }))
+    {
+        Ok(()) => world.resource_mut::<Autosave>().has_changes = false,
+        Err(e) => error!(error = &e as &dyn core::error::Error),
+    }
+}
+
+fn is_autosave_due(autosave: Res<Autosave>, pending_recovery: Res<PendingRecovery>) -> bool {
+    pending_recovery.0.is_empty() && autosave.has_changes && autosave.timer.just_finished()
+}
+
+fn process_undo_events_system(
+    mut autosave: ResMut<Autosave>,
+    mut undo_events: MessageReader<undo::UndoEvent>,
+) {
+    for event in undo_events.read() {
+        match event {
+            undo::UndoEvent::ActionPushed { .. }
+            | undo::UndoEvent::ActionReapplied
+            | undo::UndoEvent::ActionReverted => {
+                // Count the interval from the first unsaved change.
+                if !autosave.has_changes {
+                    autosave.has_changes = true;
+                    autosave.timer.reset();
+                }
+            }
+            // A new session is created or a file is loaded.
+            undo::UndoEvent::StackCleared => autosave.has_changes = false,
+            undo::UndoEvent::StackSizeChanged { .. } => (),
+        }
+    }
+}
+
+fn remove_recovery_on_exit_system(world: &mut World) {
+    remove_recovery(world);
+}
+
+fn tick_autosave_timer_system(time: Res<Time<Real>>, mut autosave: ResMut<Autosave>) {
+    autosave.timer.tick(time.delta());
+}
+
+fn update_autosave_interval_system(preferences: Res<Preferences>, mut autosave: ResMut<Autosave>) {
+    autosave.set_interval(preferences.autosave_interval);
+}
+
+// LIB
+
+#[derive(Debug, Error)]
+pub enum AutosaveError {
+    #[error("io error: {0}")]
+    IoError(std::io::Error),
+    #[error("'{}' is locked by another instance", .0.display())]
+    Locked(PathBuf),
+    #[error("save error: {0}")]
+    SaveError(save::SaveError),
+    #[error("text decode error: {0}")]
+    TextDecodeError(toml::de::Error),
+    #[error("text encode error: {0}")]
+    TextEncodeError(toml::ser::Error),
+}
+
+#[derive(Clone, Debug, Reflect)]
+pub struct Recovery {
+    pub path: PathBuf,
+    /// The project that was being edited, if it was saved before.
+    pub source: Option<PathBuf>,
+    /// Held while the recovery is pending, so that other instances do not
+    /// offer it too.
+    #[reflect(ignore)]
+    lock: Option<Arc<fs::File>>,
+    session_id: String,
+}
+
+impl Recovery {
+    /// Remove the files of the recovery.
+    pub fn remove(self) {
+        let files = RecoveryFiles::new(
+            self.path.parent().unwrap_or(Path::new("")),
+            &self.session_id,
+        );
+        drop(self.lock);
+        files.remove();
+    }
+}
+
+/// Written next to the recovery file.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+struct RecoveryInfo {
+    source: Option<PathBuf>,
+}
+
+/// Files of the autosave of a session.
+struct RecoveryFiles {
+    info_path: PathBuf,
+    lock_path: PathBuf,
+    path: PathBuf,
+}
+
+impl RecoveryFiles {
+    fn new(autosave_dir: &Path, session_id: &str) -> Self {
+        let path = |extension: &str| {
+            autosave_dir.join(format!(
+                "{}{}.{}",
+                RECOVERY_FILE_PREFIX, session_id, extension
+            ))
+        };
+        Self {
+            info_path: path(RECOVERY_INFO_EXTENSION),
+            lock_path: path(RECOVERY_LOCK_EXTENSION),
+            path: path(RECOVERY_EXTENSION),
+        }
+    }
+
+    /// Remove the files, the lock must be released first.
+    fn remove(&self) {
+        for path in [&self.path, &self.info_path, &self.lock_path] {
+            if path.exists() {
+                if let Err(e) = fs::remove_file(path) {
+                    error!(
+                        "Cannot remove recovery file '{}': {}",
+                        path.to_string_lossy(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Make the recovery of `recovery` the recovery of the current session.
+///
+/// The restored session is autosaved to the same files, so they are removed
+/// when the restored session is saved or exits cleanly.
+pub fn adopt_recovery(world: &mut World, recovery: Recovery) {
+    remove_recovery(world);
+    let mut autosave = world.resource_mut::<Autosave>();
+    autosave.lock = recovery.lock;
+    autosave.session_id = recovery.session_id;
+}
+
+/// Remove the recovery files of the current session.
+///
+/// Recoveries of other sessions, pending or not, are left alone.
+pub fn remove_recovery(world: &mut World) {
+    let mut autosave = world.resource_mut::<Autosave>();
+    autosave.has_changes = false;
+    // Open files cannot be removed on some platforms.
+    autosave.lock = None;
+    autosave.recovery_files().remove();
+}
+
+/// Find the recovery files in `autosave_dir` that should be offered to the
+/// user, and remove the ones that are older than their source.
+///
+/// Recoveries of the instances that are still running are locked, they are
+/// skipped.
+fn find_recoveries(autosave_dir: &Path) -> Vec<Recovery> {
+    let Ok(entries) = fs::read_dir(autosave_dir) else {
+        return vec![];
+    };
+    let mut recoveries: Vec<Recovery> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name: String = entry.file_name().to_string_lossy().into_owned();
+            file_name
+                .strip_prefix(RECOVERY_FILE_PREFIX)?
+                .strip_suffix(&format!(".{}", RECOVERY_EXTENSION))
+                .map(|session_id| session_id.to_owned())
+        })
+        .filter_map(|session_id| find_recovery(autosave_dir, session_id))
+        .collect();
+    // Session ids are ordered by their creation time.
+    recoveries.sort_by_key(|recovery| Reverse(recovery.session_id.clone()));
+    recoveries
+}
+
+fn find_recovery(autosave_dir: &Path, session_id: String) -> Option<Recovery> {
+    let files = RecoveryFiles::new(autosave_dir, &session_id);
+    let lock: fs::File = match try_lock(&files.lock_path) {
+        Ok(Some(lock)) => lock,
+        // Its instance is still running.
+        Ok(None) => return None,
+        Err(e) => {
+            warn!(error = &e as &dyn core::error::Error);
+            return None;
+        }
+    };
+    let info: RecoveryInfo = read_recovery_info(&files.info_path).unwrap_or_else(|e| {
+        warn!(error = &e as &dyn core::error::Error);
+        RecoveryInfo::default()
+    });
+    let is_newer_than_source: bool = match &info.source {
+        Some(source) => is_newer(&files.path, source),
+        None => true,
+    };
+    if is_newer_than_source {
+        info!("Found recovery file '{}'", files.path.to_string_lossy());
+        Some(Recovery {
+            path: files.path,
+            source: info.source,
+            lock: Some(Arc::new(lock)),
+            session_id,
+        })
+    } else {
+        drop(lock);
+        files.remove();
+        None
+    }
+}
+
+/// Whether `path` is modified after `other`.  A missing `other` is always
+/// older.
+fn is_newer(path: &Path, other: &Path) -> bool {
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(path), modified(other)) {
+        (Some(modified), Some(other_modified)) => modified > other_modified,
+        (_, None) => true,
+        (None, Some(_)) => false,
+    }
+}
+
+fn read_recovery_info(path: &Path) -> Result<RecoveryInfo, AutosaveError> {
+    let text: String = fs::read_to_string(path).map_err(|e| AutosaveError::IoError(e))?;
+    toml::from_str(&text).map_err(|e| AutosaveError::TextDecodeError(e))
+}
+
+/// Lock the file at `path`, creating it if it does not exist.
+///
+/// Returns `None` if another instance holds the lock.  Locks are released
+/// when the file is closed, even if the editor crashes.
+fn try_lock(path: &Path) -> Result<Option<fs::File>, AutosaveError> {
+    let file: fs::File = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| AutosaveError::IoError(e))?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(fs::TryLockError::WouldBlock) => Ok(None),
+        Err(fs::TryLockError::Error(e)) => Err(AutosaveError::IoError(e)),
+    }
+}
+
+fn write_recovery_info(path: &Path, info: &RecoveryInfo) -> Result<(), AutosaveError> {
+    let text: String = toml::to_string(info).map_err(|e| AutosaveError::TextEncodeError(e))?;
+    fs::write(path, text).map_err(|e| AutosaveError::IoError(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovery_info_round_trips() {
+        let info = RecoveryInfo {
+            source: Some(PathBuf::from("terrain.yer")),
+        };
+        let text: String = toml::to_string(&info).unwrap();
+        assert_eq!(toml::from_str::<RecoveryInfo>(&text).unwrap(), info);
+        assert_eq!(
+            toml::from_str::<RecoveryInfo>(&toml::to_string(&RecoveryInfo::default()).unwrap())
+                .unwrap(),
+            RecoveryInfo::default()
+        );
+    }
+
+    #[test]
+    fn recoveries_of_running_sessions_are_not_offered() {
+        let autosave_dir: PathBuf =
+            std::env::temp_dir().join(format!("yer-autosave-test-{}", uuid::Uuid::now_v7()));
+        fs::create_dir_all(&autosave_dir).unwrap();
+        let crashed = Autosave::new(autosave_dir.clone());
+        let mut running = Autosave::new(autosave_dir.clone());
+        running.lock().unwrap();
+        for autosave in [&crashed, &running] {
+            fs::write(autosave.recovery_files().path, b"").unwrap();
+        }
+
+        let recoveries: Vec<Recovery> = find_recoveries(&autosave_dir);
+        assert_eq!(recoveries.len(), 1);
+        assert_eq!(recoveries[0].session_id, crashed.session_id);
+        // Pending recoveries are not offered twice.
+        assert!(find_recoveries(&autosave_dir).is_empty());
+        recoveries.into_iter().for_each(Recovery::remove);
+        assert!(!crashed.recovery_files().path.exists());
+        assert!(running.recovery_files().path.exists());
+
+        drop(running);
+        fs::remove_dir_all(&autosave_dir).unwrap();
+    }
+
+    #[test]
+    fn zero_interval_disables_autosave() {
+        let mut autosave = Autosave::new(PathBuf::new());
+        assert!(!autosave.timer.is_paused());
+        autosave.set_interval(0);
+        assert!(autosave.timer.is_paused());
+        autosave.set_interval(30);
+        assert!(!autosave.timer.is_paused());
+        assert_eq!(autosave.timer.duration(), Duration::from_secs(30));
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License along
 // with Yer.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -23,11 +24,16 @@ use rmp_serde;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::bake;
+use crate::biome;
 use crate::id::LayerId;
 use crate::layer;
 use crate::preview;
 
-const CURRENT_SAVE_VERSION: u16 = 1;
+const CURRENT_SAVE_VERSION: u16 = 2;
+/// Extension of the text save files, files with other extensions are saved
+/// as MessagePack.
+pub const TEXT_EXTENSION: &str = "toml";
 
 // LIB
 
@@ -39,33 +45,71 @@ pub enum SaveError {
     EncodeError(rmp_serde::encode::Error),
     #[error("io error: {0}")]
     IoError(std::io::Error),
+    #[error("text decode error: {0}")]
+    TextDecodeError(toml::de::Error),
+    #[error("text encode error: {0}")]
+    TextEncodeError(toml::ser::Error),
+    #[error(
+        "unsupported save version {0}, the latest supported version is {CURRENT_SAVE_VERSION}"
+    )]
+    UnsupportedVersion(u16),
 }
 
 pub fn load(path: &Path, world: &mut World) -> Result<(), SaveError> {
-    let save_container: SaveContainer = fs::read(path)
-        .map_err(|e| SaveError::IoError(e))
-        .map(|bytes| SaveContainer::from_bytes(&bytes))??;
-    assert!(save_container.version == CURRENT_SAVE_VERSION);
-    let save_data = SaveV1::from_bytes(&save_container.data)?;
+    let save_data: SaveData = match SaveFormat::from_path(path) {
+        SaveFormat::MessagePack => fs::read(path)
+            .map_err(|e| SaveError::IoError(e))
+            .map(|bytes| SaveContainer::from_bytes(&bytes))??
+            .decode()?,
+        SaveFormat::Toml => fs::read_to_string(path)
+            .map_err(|e| SaveError::IoError(e))
+            .map(|text| TextSaveHeader::decode(&text))??,
+    };
+    world.insert_resource(save_data.bake);
+    world.insert_resource(save_data.biomes);
     layer::LayerBundle::insert_all(world, save_data.layers);
     world.spawn_batch(save_data.preview_regions);
-    layer::MaskBundle::insert_all(world, save_data.masks);
+    layer::MaskBundle::insert_all(world, save_data.masks.into_iter().collect());
     Ok(())
 }
 
 pub fn save(path: &Path, world: &mut World) -> Result<(), SaveError> {
-    let container = SaveContainer {
-        version: CURRENT_SAVE_VERSION,
-        data: (SaveV1 {
-            layers: layer::LayerBundle::extract_all(world),
-            preview_regions: preview::PreviewBundle::extract_all(world),
-            masks: layer::MaskBundle::extract_all(world),
-        })
+    let save_data = SaveData {
+        bake: world.resource::<bake::BakeSettings>().clone(),
+        biomes: world.resource::<biome::BiomeTable>().clone(),
+        layers: layer::LayerBundle::extract_all(world),
+        preview_regions: preview::PreviewBundle::extract_all(world),
+        masks: layer::MaskBundle::extract_all(world).into_iter().collect(),
+    };
+    let bytes: Vec<u8> = match SaveFormat::from_path(path) {
+        SaveFormat::MessagePack => SaveContainer {
+            version: CURRENT_SAVE_VERSION,
+            data: save_data.to_bytes()?,
+        }
         .to_bytes()?,
+        SaveFormat::Toml => TextSaveHeader::encode(&save_data)?.into_bytes(),
     };
-    fs::write(path, container.to_bytes()?.as_slice()).map_err(|e| SaveError::IoError(e))
+    fs::write(path, bytes.as_slice()).map_err(|e| SaveError::IoError(e))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SaveFormat {
+    MessagePack,
+    Toml,
 }
 
+impl SaveFormat {
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(TEXT_EXTENSION) => Self::Toml,
+            _ => Self::MessagePack,
+        }
+    }
+}
+
+/// Payload of the current save version.
+type SaveData = SaveV2;
+
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 struct SaveContainer {
     version: u16,
@@ -83,10 +127,53 @@ impl SaveContainer {
     fn from_bytes(bytes: &[u8]) -> Result<Self, SaveError> {
         rmp_serde::decode::from_slice(bytes).map_err(|e| SaveError::DecodeError(e))
     }
+
+    /// Decode the payload, migrating older versions to the current one.
+    ///
+    /// Each version is upgraded to the next one, one step at a time, with
+    /// its `From` implementation.  Old versions must never be modified.
+    fn decode(&self) -> Result<SaveData, SaveError> {
+        match self.version {
+            1 => SaveV1::from_bytes(&self.data).map(SaveV2::from),
+            2 => SaveV2::from_bytes(&self.data),
+            version => Err(SaveError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// Header of the text save files.
+///
+/// Text save files are TOML documents that start with the version, followed
+/// by the fields of the payload.  Field order is the declaration order, and
+/// masks are sorted by their layer, so that saving an unchanged project does
+/// not change the file.
+#[derive(Deserialize, Serialize)]
+struct TextSaveHeader {
+    version: u16,
+}
+
+impl TextSaveHeader {
+    fn decode(text: &str) -> Result<SaveData, SaveError> {
+        // The payload is decoded from the same document, its decoder ignores
+        // the version.
+        let header: Self = toml::from_str(text).map_err(|e| SaveError::TextDecodeError(e))?;
+        match header.version {
+            2 => toml::from_str::<SaveV2>(text).map_err(|e| SaveError::TextDecodeError(e)),
+            version => Err(SaveError::UnsupportedVersion(version)),
+        }
+    }
+
+    fn encode(save_data: &SaveData) -> Result<String, SaveError> {
+        let header: String = toml::to_string(&Self {
+            version: CURRENT_SAVE_VERSION,
+        })
+        .map_err(|e| SaveError::TextEncodeError(e))?;
+        let payload: String =
+            toml::to_string_pretty(save_data).map_err(|e| SaveError::TextEncodeError(e))?;
+        Ok(format!("{}\n{}", header, payload))
+    }
 }
 
-// TODO: Store bake config
-// TODO: Store cached preview mesh
 #[derive(Deserialize, Serialize)]
 struct SaveV1 {
     layers: Vec<layer::LayerBundle>,
@@ -95,6 +182,24 @@ struct SaveV1 {
 }
 
 impl SaveV1 {
+    #[inline]
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SaveError> {
+        rmp_serde::decode::from_slice(bytes).map_err(|e| SaveError::DecodeError(e))
+    }
+}
+
+// TODO: Store cached preview mesh
+#[derive(Deserialize, Serialize)]
+struct SaveV2 {
+    #[serde(deserialize_with = "bake::deserialize_bake_settings")]
+    bake: bake::BakeSettings,
+    biomes: biome::BiomeTable,
+    layers: Vec<layer::LayerBundle>,
+    preview_regions: Vec<preview::PreviewBundle>,
+    masks: BTreeMap<LayerId, Vec<layer::MaskBundle>>,
+}
+
+impl SaveV2 {
     #[inline]
     fn to_bytes(&self) -> Result<Vec<u8>, SaveError> {
         rmp_serde::encode::to_vec_named(self).map_err(|e| SaveError::EncodeError(e))
@@ -106,6 +211,29 @@ impl SaveV1 {
     }
 }
 
+/// Version 2 adds bake settings and biomes.
+impl From<SaveV1> for SaveV2 {
+    fn from(save_v1: SaveV1) -> Self {
+        // Bake the preview region, rather than an arbitrary one.
+        let bake: bake::BakeSettings = save_v1
+            .preview_regions
+            .first()
+            .map(|preview_bundle| bake::BakeSettings {
+                center: preview_bundle.preview_region().center(),
+                size: preview_bundle.preview_region().size(),
+                ..default()
+            })
+            .unwrap_or_default();
+        Self {
+            bake,
+            biomes: biome::BiomeTable::default(),
+            layers: save_v1.layers,
+            preview_regions: save_v1.preview_regions,
+            masks: save_v1.masks.into_iter().collect(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,4 +259,223 @@ mod tests {
         assert!(load_result.is_ok());
         assert_eq!(save_data, load_result.unwrap());
     }
+
+    fn decode_fixture(bytes: &[u8]) -> SaveData {
+        SaveContainer::from_bytes(bytes)
+            .and_then(|save_container| save_container.decode())
+            .unwrap()
+    }
+
+    fn assert_fixture_layers_and_preview_regions(save_data: &SaveData) {
+        assert_eq!(save_data.layers.len(), 1);
+        assert_eq!(save_data.layers[0].layer.name, "Base");
+        assert!(matches!(
+            save_data.layers[0].height_map,
+            layer::HeightMap::Constant(10.0)
+        ));
+        assert_eq!(save_data.preview_regions.len(), 1);
+        let preview_region = save_data.preview_regions[0].preview_region();
+        assert_eq!(preview_region.center(), Vec2::new(5.0, -5.0));
+        assert_eq!(preview_region.size(), 200.0);
+        assert!(save_data.masks.is_empty());
+    }
+
+    #[test]
+    fn save_v1_is_migrated() {
+        let save_data = decode_fixture(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/save_v1.yer"
+        )));
+        assert_fixture_layers_and_preview_regions(&save_data);
+        assert!(save_data.biomes.is_empty());
+        assert_eq!(
+            save_data.bake,
+            bake::BakeSettings {
+                center: Vec2::new(5.0, -5.0),
+                size: 200.0,
+                ..default()
+            }
+        );
+    }
+
+    #[test]
+    fn save_v2_is_decoded() {
+        let save_data = decode_fixture(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/save_v2.yer"
+        )));
+        assert_fixture_layers_and_preview_regions(&save_data);
+        assert!(save_data.biomes.is_empty());
+        assert_eq!(
+            save_data.bake,
+            bake::BakeSettings {
+                center: Vec2::new(5.0, -5.0),
+                format: bake::BakeFormat::Exr,
+                mesh: bake::MeshSettings {
+                    base_depth: Some(2.0),
+                    scale: 0.5,
+                },
+                normalization: bake::HeightNormalization::Range {
+                    max: 100.0,
+                    min: 0.0,
+                },
+                resolution: UVec2::new(513, 257),
+                size: 150.0,
+            }
+        );
+    }
+
+    fn arbitrary_save_data() -> SaveData {
+        let layer = layer::Layer::default();
+        let layer_id: LayerId = layer.id();
+        let mut world = World::new();
+        preview::create_default_preview_region(&mut world);
+        SaveData {
+            bake: bake::BakeSettings {
+                normalization: bake::HeightNormalization::Range {
+                    max: 100.0,
+                    min: -20.0,
+                },
+                ..default()
+            },
+            biomes: biome::BiomeTable::new(vec![biome::Biome {
+                height: Some(biome::BiomeRange::new(50.0, 100.0)),
+                ..biome::Biome::new("Snow", Color::WHITE)
+            }]),
+            layers: vec![layer::LayerBundle {
+                name: layer.name_component(),
+                layer,
+                height_map: layer::HeightMap::summit(),
+            }],
+            preview_regions: preview::PreviewBundle::extract_all(&mut world),
+            masks: BTreeMap::from([(
+                layer_id,
+                vec![
+                    layer::MaskBundle {
+                        mask: layer::Mask::default(),
+                        mask_source: layer::MaskSource::circle(),
+                    },
+                    layer::MaskBundle {
+                        mask: layer::Mask::default(),
+                        mask_source: layer::MaskSource::bitmap(),
+                    },
+                ],
+            )]),
+        }
+    }
+
+    #[test]
+    fn message_pack_and_toml_hold_the_same_data() {
+        let save_data: SaveData = arbitrary_save_data();
+        let message_pack: Vec<u8> = save_data.to_bytes().unwrap();
+        let text: String = TextSaveHeader::encode(&save_data).unwrap();
+        assert!(text.starts_with(&format!("version = {}", CURRENT_SAVE_VERSION)));
+        assert_eq!(
+            SaveV2::from_bytes(&message_pack)
+                .unwrap()
+                .to_bytes()
+                .unwrap(),
+            message_pack
+        );
+        assert_eq!(
+            TextSaveHeader::decode(&text).unwrap().to_bytes().unwrap(),
+            message_pack
+        );
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        fn first_masks(table: &mut toml::Table) -> &mut toml::Value {
+            table["masks"]
+                .as_table_mut()
+                .unwrap()
+                .values_mut()
+                .next()
+                .unwrap()
+        }
+
+        let text: String = TextSaveHeader::encode(&arbitrary_save_data()).unwrap();
+        let edits: [fn(&mut toml::Table); 4] = [
+            |table| {
+                table["bake"]["resolution"] =
+                    toml::Value::Array(vec![toml::Value::Integer(0), toml::Value::Integer(0)])
+            },
+            |table| table["layers"][0]["layer"]["opacity"] = toml::Value::Float(1.5),
+            |table| first_masks(table)[0]["mask"]["strength"] = toml::Value::Float(-0.5),
+            |table| {
+                first_masks(table)[1]["mask_source"]["Bitmap"]["out_of_bounds_value"] =
+                    toml::Value::Float(2.0)
+            },
+        ];
+        for edit in edits {
+            let mut table: toml::Table = text.parse().unwrap();
+            edit(&mut table);
+            assert!(matches!(
+                TextSaveHeader::decode(&toml::to_string(&table).unwrap()),
+                Err(SaveError::TextDecodeError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_bake_settings_are_rejected() {
+        fn mesh(base_depth: Option<f32>, scale: f32) -> bake::MeshSettings {
+            bake::MeshSettings { base_depth, scale }
+        }
+
+        fn range(min: f32, max: f32) -> bake::HeightNormalization {
+            bake::HeightNormalization::Range { max, min }
+        }
+
+        let edits: [fn(&mut bake::BakeSettings); 12] = [
+            |settings| settings.resolution = UVec2::new(0, 0),
+            |settings| settings.resolution = UVec2::new(1, 1025),
+            |settings| settings.resolution = UVec2::new(1025, bake::MAX_RESOLUTION + 1),
+            |settings| settings.size = 0.0,
+            |settings| settings.size = -100.0,
+            |settings| settings.size = f32::NAN,
+            |settings| settings.mesh = mesh(None, 0.0),
+            |settings| settings.mesh = mesh(None, f32::INFINITY),
+            |settings| settings.mesh = mesh(Some(-1.0), 1.0),
+            |settings| settings.mesh = mesh(Some(f32::NAN), 1.0),
+            |settings| settings.normalization = range(10.0, 10.0),
+            |settings| settings.normalization = range(10.0, -10.0),
+        ];
+        for edit in edits {
+            let mut save_data: SaveData = arbitrary_save_data();
+            edit(&mut save_data.bake);
+            assert!(matches!(
+                SaveV2::from_bytes(&save_data.to_bytes().unwrap()),
+                Err(SaveError::DecodeError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn save_format_is_chosen_by_extension() {
+        assert_eq!(
+            SaveFormat::from_path(Path::new("terrain.yer")),
+            SaveFormat::MessagePack
+        );
+        assert_eq!(
+            SaveFormat::from_path(Path::new("terrain.toml")),
+            SaveFormat::Toml
+        );
+        assert_eq!(
+            SaveFormat::from_path(Path::new("terrain")),
+            SaveFormat::MessagePack
+        );
+    }
+
+    #[test]
+    fn newer_versions_are_not_supported() {
+        let save_container = SaveContainer {
+            version: CURRENT_SAVE_VERSION + 1,
+            data: vec![],
+        };
+        assert!(matches!(
+            save_container.decode(),
+            Err(SaveError::UnsupportedVersion(version)) if version == CURRENT_SAVE_VERSION + 1
+        ));
+    }
 }
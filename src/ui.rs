@@ -15,25 +15,33 @@
 // with Yer.  If not, see <https://www.gnu.org/licenses/>.
 
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
+use bevy::window::{PrimaryWindow, WindowCloseRequested};
 use bevy_egui::{egui, EguiContexts, EguiPlugin, EguiPrimaryContextPass};
 #[cfg(feature = "inspector")]
 use bevy_egui::{EguiContext, PrimaryEguiContext};
 #[cfg(feature = "inspector")]
 use bevy_inspector_egui::{bevy_inspector, DefaultInspectorConfigPlugin};
 
+use crate::bake as crate_bake;
+use crate::biome as crate_biome;
 use crate::constants;
 use crate::layer as crate_layer;
+use crate::preview as crate_preview;
 use crate::session;
 use crate::theme;
 use crate::undo;
 
+mod bake;
+mod biome;
 mod egui_ext;
 mod file_dialog;
 mod layer;
 mod preferences_dialog;
 mod preview;
+mod recovery_dialog;
+mod terrain_info;
 mod toolbar;
+mod unsaved_changes_dialog;
 
 // PLUGIN
 
@@ -41,9 +49,13 @@ pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<UiState>()
+        app.register_type::<PendingGuardedAction>()
+            .register_type::<UiState>()
+            .init_resource::<PendingGuardedAction>()
             .add_plugins((
                 EguiPlugin::default(),
+                bake::BakeUiPlugin,
+                biome::BiomeUiPlugin,
                 egui_ext::UiBevyExtPlugin,
                 file_dialog::UiFileDialogPlugin,
                 layer::LayerUiPlugin,
@@ -55,7 +67,19 @@ impl Plugin for UiPlugin {
             )
             .add_systems(
                 Update,
-                update_window_title_system.run_if(resource_exists_and_changed::<session::Session>),
+                (
+                    guard_window_close_system.run_if(on_message::<WindowCloseRequested>),
+                    update_window_title_system
+                        .run_if(resource_exists_and_changed::<session::Session>),
+                ),
+            )
+            .add_systems(
+                OnEnter(UiState::ShowingExportBiomeMapFileDialog),
+                show_export_biome_map_file_dialog_system,
+            )
+            .add_systems(
+                OnEnter(UiState::ShowingExportHeightMapFileDialog),
+                show_export_height_map_file_dialog_system,
             )
             .add_systems(
                 OnEnter(UiState::ShowingLoadFileDialog),
@@ -65,10 +89,19 @@ impl Plugin for UiPlugin {
                 OnEnter(UiState::ShowingPreferencesDialog),
                 show_preferences_dialog_system,
             )
+            .add_systems(
+                OnEnter(UiState::ShowingRecoveryDialog),
+                show_recovery_dialog_system,
+            )
             .add_systems(
                 OnEnter(UiState::ShowingSaveFileDialog),
                 show_save_file_dialog_system,
-            );
+            )
+            .add_systems(
+                OnExit(UiState::ShowingSaveFileDialog),
+                clear_pending_guarded_action_system,
+            )
+            .add_systems(Startup, offer_recovery_system.run_if(has_pending_recovery));
 
         #[cfg(feature = "inspector")]
         app.add_plugins(DefaultInspectorConfigPlugin)
@@ -78,13 +111,24 @@ impl Plugin for UiPlugin {
 
 // RESOURCES
 
+/// Action to continue with once the unsaved changes are saved with the save
+/// file dialog.
+#[derive(Debug, Default, Reflect, Resource)]
+#[reflect(Resource)]
+struct PendingGuardedAction(Option<unsaved_changes_dialog::GuardedAction>);
+
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq, Reflect, States)]
 enum UiState {
     #[default]
     Interactive,
+    ShowingExportBiomeMapFileDialog,
+    ShowingExportHeightMapFileDialog,
+    ShowingImportBitmapFileDialog,
     ShowingLoadFileDialog,
     ShowingPreferencesDialog,
+    ShowingRecoveryDialog,
     ShowingSaveFileDialog,
+    ShowingUnsavedChangesDialog,
 }
 
 impl UiState {
@@ -150,18 +194,70 @@ fn inspector_ui_system(world: &mut World) -> Result<(), BevyError> {
 fn draw_ui_dialogs_system(
     mut commands: Commands,
     mut contexts: EguiContexts,
+    mut export_biome_map_file_dialogs: Query<&mut file_dialog::ExportBiomeMapFileDialog>,
+    mut export_height_map_file_dialogs: Query<&mut file_dialog::ExportHeightMapFileDialog>,
+    mut import_bitmap_file_dialogs: Query<&mut file_dialog::ImportBitmapFileDialog>,
     mut load_file_dialogs: Query<&mut file_dialog::LoadFileDialog>,
+    mut pending_guarded_action: ResMut<PendingGuardedAction>,
     mut preferences_dialogs: Query<&mut preferences_dialog::PreferencesDialog>,
+    mut recovery_dialogs: Query<&mut recovery_dialog::RecoveryDialog>,
     mut save_file_dialogs: Query<&mut file_dialog::SaveFileDialog>,
+    session: Res<session::Session>,
     theme: Res<theme::Theme>,
     theme_colors: Res<Assets<theme::ThemeColors>>,
     ui_state: Res<State<UiState>>,
     mut ui_state_next: ResMut<NextState<UiState>>,
+    mut unsaved_changes_dialogs: Query<&mut unsaved_changes_dialog::UnsavedChangesDialog>,
 ) {
     if let Ok(ctx) = contexts.ctx_mut() {
         if !ui_state.is_interactive() {
             match ui_state.as_ref().get() {
                 UiState::Interactive => unreachable!(),
+                UiState::ShowingExportBiomeMapFileDialog => {
+                    if let Ok(mut dialog) = export_biome_map_file_dialogs.single_mut() {
+                        match dialog.show(ctx) {
+                            file_dialog::DialogState::Open => (),
+                            file_dialog::DialogState::Selected(path) => {
+                                ui_state_next.set(UiState::Interactive);
+                                commands.queue(crate_preview::ExportBiomeMap(path));
+                            }
+                            file_dialog::DialogState::Cancelled => {
+                                ui_state_next.set(UiState::Interactive);
+                            }
+                        }
+                    }
+                }
+                UiState::ShowingExportHeightMapFileDialog => {
+                    if let Ok(mut dialog) = export_height_map_file_dialogs.single_mut() {
+                        match dialog.show(ctx) {
+                            file_dialog::DialogState::Open => (),
+                            file_dialog::DialogState::Selected(path) => {
+                                ui_state_next.set(UiState::Interactive);
+                                commands.queue(crate_bake::StartBake(path));
+                            }
+                            file_dialog::DialogState::Cancelled => {
+                                ui_state_next.set(UiState::Interactive);
+                            }
+                        }
+                    }
+                }
+                UiState::ShowingImportBitmapFileDialog => {
+                    if let Ok(mut dialog) = import_bitmap_file_dialogs.single_mut() {
+                        match dialog.show(ctx) {
+                            file_dialog::DialogState::Open => (),
+                            file_dialog::DialogState::Selected(path) => {
+                                ui_state_next.set(UiState::Interactive);
+                                commands.queue(layer::ImportBitmap {
+                                    path,
+                                    target: dialog.target(),
+                                });
+                            }
+                            file_dialog::DialogState::Cancelled => {
+                                ui_state_next.set(UiState::Interactive);
+                            }
+                        }
+                    }
+                }
                 UiState::ShowingLoadFileDialog => {
                     if let Ok(mut dialog) = load_file_dialogs.single_mut() {
                         match dialog.show(ctx) {
@@ -197,6 +293,31 @@ fn draw_ui_dialogs_system(
                         }
                     }
                 }
+                UiState::ShowingRecoveryDialog => {
+                    if let Ok(mut dialog) = recovery_dialogs.single_mut() {
+                        if let Some(colors) = theme_colors.get(&theme.colors) {
+                            match dialog.show(ctx, colors) {
+                                recovery_dialog::DialogState::Open => (),
+                                recovery_dialog::DialogState::Restore(idx) => {
+                                    commands.queue(session::RestoreRecovery(idx));
+                                    ui_state_next.set(UiState::Interactive);
+                                }
+                                recovery_dialog::DialogState::Discard(idx) => {
+                                    commands.queue(session::DiscardRecovery(idx));
+                                    if dialog.is_empty() {
+                                        ui_state_next.set(UiState::Interactive);
+                                    }
+                                }
+                                recovery_dialog::DialogState::Later => {
+                                    commands.queue(session::PostponeRecovery);
+                                    ui_state_next.set(UiState::Interactive);
+                                }
+                            }
+                        } else {
+                            error!("Cannot read theme colors.");
+                        }
+                    }
+                }
                 UiState::ShowingSaveFileDialog => {
                     if let Ok(mut dialog) = save_file_dialogs.single_mut() {
                         match dialog.show(ctx) {
@@ -204,23 +325,64 @@ fn draw_ui_dialogs_system(
                             file_dialog::DialogState::Selected(path) => {
                                 ui_state_next.set(UiState::Interactive);
                                 commands.queue(session::SaveSession(Some(path)));
+                                if let Some(action) = pending_guarded_action.0.take() {
+                                    commands.queue(RunGuardedAction {
+                                        action,
+                                        only_if_saved: true,
+                                    });
+                                }
                             }
                             file_dialog::DialogState::Cancelled => {
-                                // Currently there is no cleanup necessary.  If there is
-                                // need for cleanup in the future it should ideally be
-                                // handled by a OnExit(state) system.
+                                // Pending guarded action is cleared by a
+                                // OnExit(state) system.
                                 ui_state_next.set(UiState::Interactive);
                             }
                         }
                     }
                 }
+                UiState::ShowingUnsavedChangesDialog => {
+                    if let Ok(mut dialog) = unsaved_changes_dialogs.single_mut() {
+                        if let Some(colors) = theme_colors.get(&theme.colors) {
+                            match dialog.show(ctx, colors) {
+                                unsaved_changes_dialog::DialogState::Open => (),
+                                unsaved_changes_dialog::DialogState::Save => {
+                                    if session.has_save_file() {
+                                        ui_state_next.set(UiState::Interactive);
+                                        commands.queue(session::SaveSession(None));
+                                        commands.queue(RunGuardedAction {
+                                            action: dialog.action(),
+                                            only_if_saved: true,
+                                        });
+                                    } else {
+                                        pending_guarded_action.0 = Some(dialog.action());
+                                        ui_state_next.set(UiState::ShowingSaveFileDialog);
+                                    }
+                                }
+                                unsaved_changes_dialog::DialogState::Discard => {
+                                    ui_state_next.set(UiState::Interactive);
+                                    commands.queue(RunGuardedAction {
+                                        action: dialog.action(),
+                                        only_if_saved: false,
+                                    });
+                                }
+                                unsaved_changes_dialog::DialogState::Cancelled => {
+                                    ui_state_next.set(UiState::Interactive);
+                                }
+                            }
+                        } else {
+                            error!("Cannot read theme colors.");
+                        }
+                    }
+                }
             }
         }
     }
 }
 
 fn draw_ui_panels_system(
-    mut app_exit_events: MessageWriter<AppExit>,
+    bake_query: bake::BakeQuery,
+    biome_table: Res<crate_biome::BiomeTable>,
+    mut biome_table_ui: ResMut<biome::BiomeTableUi>,
     mut commands: Commands,
     mut contexts: EguiContexts,
     egui_theme: Res<egui_ext::EguiTheme>,
@@ -228,6 +390,7 @@ fn draw_ui_panels_system(
     mut masks_query: layer::Masks,
     preview_query: preview::PreviewQuery,
     session: Res<session::Session>,
+    terrain_info: Res<crate_preview::TerrainInfo>,
     theme: Res<theme::Theme>,
     theme_colors: Res<Assets<theme::ThemeColors>>,
     undo_stack: Res<undo::UndoStack>,
@@ -238,7 +401,7 @@ fn draw_ui_panels_system(
     egui::TopBottomPanel::top("menubar").show(ctx, |ui| {
         draw_ui_menu(
             ui,
-            &mut app_exit_events,
+            biome_table.as_ref(),
             &mut commands,
             &layers_query,
             session.as_ref(),
@@ -255,10 +418,22 @@ fn draw_ui_panels_system(
         }
     });
 
+    egui::TopBottomPanel::bottom("statusbar").show(ctx, |ui| {
+        terrain_info::draw_ui_for_status_bar(ui, terrain_info.as_ref());
+    });
+
     egui::SidePanel::left("sidepanel_left")
         .resizable(true)
         .show(ctx, |ui| {
             preview::draw_ui_for_preview(ui, preview_query);
+            ui.separator();
+            if bake::draw_ui_for_bake(&mut commands, ui, bake_query) {
+                ui_state_next.set(UiState::ShowingExportHeightMapFileDialog);
+            }
+            ui.separator();
+            biome::draw_ui_for_biomes(ui, biome_table_ui.as_mut());
+            ui.separator();
+            terrain_info::draw_ui_for_terrain_info(ui, terrain_info.as_ref());
             ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
         });
 
@@ -268,6 +443,7 @@ fn draw_ui_panels_system(
             ui.with_layout(egui::Layout::top_down_justified(egui::Align::Min), |ui| {
                 if let Some(colors) = theme_colors.get(&theme.colors) {
                     layer::draw_ui_for_layers(
+                        biome_table.as_ref(),
                         &mut commands,
                         colors,
                         ui,
@@ -299,6 +475,51 @@ fn draw_ui_panels_system(
     Ok(())
 }
 
+fn clear_pending_guarded_action_system(mut pending_guarded_action: ResMut<PendingGuardedAction>) {
+    pending_guarded_action.0 = None;
+}
+
+/// Quit when the window is closed, asking to save the unsaved changes first.
+fn guard_window_close_system(
+    mut commands: Commands,
+    mut close_requests: MessageReader<WindowCloseRequested>,
+    session: Res<session::Session>,
+    ui_state: Res<State<UiState>>,
+) {
+    close_requests.clear();
+    // Closing the window again while the dialog is open does nothing.
+    if *ui_state.get() != UiState::ShowingUnsavedChangesDialog {
+        guard_unsaved_changes(
+            &mut commands,
+            session.as_ref(),
+            unsaved_changes_dialog::GuardedAction::Quit,
+        );
+    }
+}
+
+fn offer_recovery_system(mut ui_state_next: ResMut<NextState<UiState>>) {
+    ui_state_next.set(UiState::ShowingRecoveryDialog);
+}
+
+fn show_export_biome_map_file_dialog_system(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Export Biome Map File Dialog"),
+        file_dialog::ExportBiomeMapFileDialog::default(),
+        DespawnOnExit(UiState::ShowingExportBiomeMapFileDialog),
+    ));
+}
+
+fn show_export_height_map_file_dialog_system(
+    mut commands: Commands,
+    bake_settings: Res<crate_bake::BakeSettings>,
+) {
+    commands.spawn((
+        Name::new("Export Height Map File Dialog"),
+        file_dialog::ExportHeightMapFileDialog::new(bake_settings.format),
+        DespawnOnExit(UiState::ShowingExportHeightMapFileDialog),
+    ));
+}
+
 fn show_load_file_dialog_system(world: &mut World) {
     let dialog = file_dialog::LoadFileDialog::from_world(world);
     world.spawn((
@@ -319,6 +540,17 @@ fn show_preferences_dialog_system(mut commands: Commands) {
     })
 }
 
+fn show_recovery_dialog_system(mut commands: Commands) {
+    commands.queue(|world: &mut World| {
+        let dialog = recovery_dialog::RecoveryDialog::from_world(world);
+        world.spawn((
+            Name::new("Recovery Dialog"),
+            dialog,
+            DespawnOnExit(UiState::ShowingRecoveryDialog),
+        ));
+    })
+}
+
 fn show_save_file_dialog_system(mut commands: Commands) {
     commands.spawn((
         Name::new("Save File Dialog"),
@@ -338,12 +570,12 @@ fn update_window_title_system(
             .map(|p| p.to_string_lossy().into())
             .unwrap_or("(Unsaved)".to_string());
         let save_status_part: &str = if session.has_unsaved_changes() {
-            "*"
+            "* "
         } else {
             ""
         };
         window.title = format!(
-            "{} {} — {}",
+            "{}{} — {}",
             save_status_part,
             file_path_part,
             constants::APPLICATION_TITLE
@@ -351,11 +583,69 @@ fn update_window_title_system(
     }
 }
 
+// COMMANDS
+
+/// Continues with an action that discards the current session.
+struct RunGuardedAction {
+    action: unsaved_changes_dialog::GuardedAction,
+    /// Run the action only if the session is saved, i.e. saving did not
+    /// fail.
+    only_if_saved: bool,
+}
+
+impl Command for RunGuardedAction {
+    fn apply(self, world: &mut World) {
+        if self.only_if_saved && world.resource::<session::Session>().has_unsaved_changes() {
+            warn!("Session is not saved, {:?} is cancelled.", self.action);
+            return;
+        }
+        match self.action {
+            unsaved_changes_dialog::GuardedAction::New => {
+                session::InitializeNewSession.apply(world);
+            }
+            unsaved_changes_dialog::GuardedAction::Open => {
+                world
+                    .resource_mut::<NextState<UiState>>()
+                    .set(UiState::ShowingLoadFileDialog);
+            }
+            unsaved_changes_dialog::GuardedAction::Quit => {
+                world.write_message(AppExit::Success);
+            }
+        }
+    }
+}
+
 // LIB
 
+/// Run `action` right away if there are no unsaved changes, ask to save them
+/// first otherwise.
+fn guard_unsaved_changes(
+    commands: &mut Commands,
+    session: &session::Session,
+    action: unsaved_changes_dialog::GuardedAction,
+) {
+    if session.has_unsaved_changes() {
+        commands.spawn((
+            Name::new("Unsaved Changes Dialog"),
+            unsaved_changes_dialog::UnsavedChangesDialog::new(action, session),
+            DespawnOnExit(UiState::ShowingUnsavedChangesDialog),
+        ));
+        commands.set_state(UiState::ShowingUnsavedChangesDialog);
+    } else {
+        commands.queue(RunGuardedAction {
+            action,
+            only_if_saved: false,
+        });
+    }
+}
+
+fn has_pending_recovery(pending_recovery: Res<session::PendingRecovery>) -> bool {
+    !pending_recovery.0.is_empty()
+}
+
 fn draw_ui_menu(
     ui: &mut egui::Ui,
-    app_exit_events: &mut MessageWriter<AppExit>,
+    biome_table: &crate_biome::BiomeTable,
     commands: &mut Commands,
     layers_query: &layer::Layers,
     session: &session::Session,
@@ -365,11 +655,19 @@ fn draw_ui_menu(
     egui::MenuBar::new().ui(ui, |ui| {
         ui.menu_button("File", |ui| {
             if ui.button("New").clicked() {
-                commands.queue(session::InitializeNewSession);
+                guard_unsaved_changes(
+                    commands,
+                    session,
+                    unsaved_changes_dialog::GuardedAction::New,
+                );
                 ui.close();
             }
             if ui.button("Open...").clicked() {
-                ui_state_next.set(UiState::ShowingLoadFileDialog);
+                guard_unsaved_changes(
+                    commands,
+                    session,
+                    unsaved_changes_dialog::GuardedAction::Open,
+                );
                 ui.close();
             }
             if ui.button("Save").clicked() {
@@ -385,8 +683,23 @@ fn draw_ui_menu(
                 ui.close();
             }
             ui.separator();
+            if ui
+                .add_enabled_ui(!biome_table.is_empty(), |ui| {
+                    ui.button("Export Biome Map...")
+                })
+                .inner
+                .clicked()
+            {
+                ui_state_next.set(UiState::ShowingExportBiomeMapFileDialog);
+                ui.close();
+            }
+            ui.separator();
             if ui.button("Quit").clicked() {
-                app_exit_events.write(AppExit::Success);
+                guard_unsaved_changes(
+                    commands,
+                    session,
+                    unsaved_changes_dialog::GuardedAction::Quit,
+                );
                 ui.close();
             }
         });
@@ -0,0 +1,229 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::bake;
+use crate::preview;
+use crate::undo;
+
+const LATENCY: Duration = Duration::from_millis(100);
+
+// PLUGIN
+
+pub struct BakeUiPlugin;
+
+impl Plugin for BakeUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BakeSettingsUi>()
+            .init_resource::<BakeSettingsUi>();
+        app.add_systems(
+            Update,
+            (
+                update_bake_settings_ui_system,
+                reset_bake_settings_ui_system.run_if(resource_changed::<bake::BakeSettings>),
+            ),
+        );
+    }
+}
+
+// RESOURCES
+
+#[derive(Debug, Reflect, Resource)]
+#[reflect(Resource)]
+pub(super) struct BakeSettingsUi {
+    bake_settings: bake::BakeSettings,
+    timer: Timer,
+}
+
+impl Default for BakeSettingsUi {
+    fn default() -> Self {
+        Self {
+            bake_settings: bake::BakeSettings::default(),
+            timer: Timer::new(LATENCY, TimerMode::Once),
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct BakeQuery<'w, 's> {
+    bake: Res<'w, bake::Bake>,
+    bake_settings_ui: ResMut<'w, BakeSettingsUi>,
+    preview_regions: Query<'w, 's, &'static preview::PreviewRegion>,
+}
+
+// SYSTEMS
+
+/// Update BakeSettings based on UI changes.
+fn update_bake_settings_ui_system(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    bake_settings: Res<bake::BakeSettings>,
+    mut bake_settings_ui: ResMut<BakeSettingsUi>,
+) {
+    if !bake_settings_ui.timer.is_finished() {
+        bake_settings_ui.timer.tick(time.delta());
+        if bake_settings_ui.timer.just_finished()
+            && bake_settings_ui.bake_settings != *bake_settings
+        {
+            commands.queue(undo::PushAction::from(bake::UpdateBakeSettingsAction::new(
+                bake_settings.clone(),
+                bake_settings_ui.bake_settings.clone(),
+            )));
+        }
+    }
+}
+
+/// Update BakeSettingsUi based on changes to BakeSettings.
+///
+/// This gets triggered when undo/redo or loading a project changes
+/// [BakeSettings](bake::BakeSettings).
+fn reset_bake_settings_ui_system(
+    bake_settings: Res<bake::BakeSettings>,
+    mut bake_settings_ui: ResMut<BakeSettingsUi>,
+) {
+    bake_settings_ui.bake_settings = bake_settings.clone();
+    bake_settings_ui.timer.pause();
+}
+
+// LIB
+
+/// Draw bake settings, returns whether the export is requested.
+pub fn draw_ui_for_bake(
+    commands: &mut Commands,
+    ui: &mut egui::Ui,
+    mut bake_query: BakeQuery,
+) -> bool {
+    ui.heading("Bake");
+    if let Some(progress) = bake_query.bake.progress() {
+        ui.horizontal(|ui| {
+            ui.add(egui::ProgressBar::new(progress).show_percentage());
+            if ui.button("Cancel").clicked() {
+                commands.queue(bake::CancelBake);
+            }
+        });
+        return false;
+    }
+
+    let BakeSettingsUi {
+        ref mut bake_settings,
+        ref mut timer,
+    } = *bake_query.bake_settings_ui;
+    let original_bake_settings: bake::BakeSettings = bake_settings.clone();
+    ui.horizontal(|ui| {
+        ui.label("Center");
+        ui.add(egui::widgets::DragValue::new(&mut bake_settings.center.x));
+        ui.add(egui::widgets::DragValue::new(&mut bake_settings.center.y));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Size");
+        ui.add(
+            egui::widgets::DragValue::new(&mut bake_settings.size).range(f32::EPSILON..=f32::MAX),
+        );
+        if let Ok(preview_region) = bake_query.preview_regions.single() {
+            if ui.button("Match preview").clicked() {
+                bake_settings.center = preview_region.center();
+                bake_settings.size = preview_region.size();
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Resolution");
+        for value in bake_settings.resolution.as_mut() {
+            ui.add(
+                egui::widgets::DragValue::new(value)
+                    .range(bake::MIN_RESOLUTION..=bake::MAX_RESOLUTION),
+            );
+        }
+    });
+    let extent: Vec2 = bake_settings.extent();
+    ui.weak(format!(
+        "{:.2} × {:.2}, {:.3} per pixel",
+        extent.x,
+        extent.y,
+        bake_settings.spacing()
+    ));
+    ui.horizontal(|ui| {
+        ui.label("Format");
+        egui::ComboBox::from_id_salt("bake-format")
+            .selected_text(bake_settings.format.to_string())
+            .show_ui(ui, |ui| {
+                for format in [
+                    bake::BakeFormat::Png16,
+                    bake::BakeFormat::Exr,
+                    bake::BakeFormat::Tiff,
+                    bake::BakeFormat::Glb,
+                    bake::BakeFormat::Obj,
+                    bake::BakeFormat::Stl,
+                ] {
+                    ui.selectable_value(&mut bake_settings.format, format, format.to_string());
+                }
+            });
+    });
+    // Float formats store the heights as they are.
+    if bake_settings.format == bake::BakeFormat::Png16 {
+        ui.horizontal(|ui| {
+            let mut fit: bool = bake_settings.normalization == bake::HeightNormalization::Fit;
+            if ui.checkbox(&mut fit, "Fit heights").changed() {
+                bake_settings.normalization = if fit {
+                    bake::HeightNormalization::Fit
+                } else {
+                    bake::HeightNormalization::Range {
+                        max: 100.0,
+                        min: 0.0,
+                    }
+                };
+            }
+            if let bake::HeightNormalization::Range { max, min } = &mut bake_settings.normalization
+            {
+                // The range must not be empty.
+                let highest_min: f32 = max.next_down();
+                ui.add(egui::widgets::DragValue::new(min).range(f32::MIN..=highest_min));
+                let lowest_max: f32 = min.next_up();
+                ui.add(egui::widgets::DragValue::new(max).range(lowest_max..=f32::MAX));
+            }
+        });
+    }
+    if bake_settings.format.is_mesh() {
+        let mesh_settings: &mut bake::MeshSettings = &mut bake_settings.mesh;
+        ui.horizontal(|ui| {
+            ui.label("Scale");
+            ui.add(
+                egui::widgets::DragValue::new(&mut mesh_settings.scale)
+                    .range(f32::EPSILON..=f32::MAX)
+                    .speed(0.01),
+            );
+        });
+        ui.horizontal(|ui| {
+            let mut has_base: bool = mesh_settings.base_depth.is_some();
+            if ui.checkbox(&mut has_base, "Base").changed() {
+                mesh_settings.base_depth = has_base.then_some(10.0);
+            }
+            if let Some(base_depth) = &mut mesh_settings.base_depth {
+                ui.add(egui::widgets::DragValue::new(base_depth).range(0.0..=f32::MAX));
+            }
+        });
+    }
+    if *bake_settings != original_bake_settings {
+        timer.unpause();
+        timer.reset();
+    }
+    ui.button("Export...").clicked()
+}
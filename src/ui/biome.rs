@@ -0,0 +1,220 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::biome;
+use crate::layer;
+use crate::undo;
+
+use super::egui_ext::draw_ui_editable_f32;
+
+const BIOME_NAME_CHAR_LIMIT: usize = 20;
+const LATENCY: Duration = Duration::from_millis(100);
+const SLOPE_RANGE: RangeInclusive<f32> = 0.0..=90.0;
+
+// PLUGIN
+
+pub struct BiomeUiPlugin;
+
+impl Plugin for BiomeUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BiomeTableUi>()
+            .init_resource::<BiomeTableUi>();
+        app.add_systems(
+            Update,
+            (
+                update_biome_table_ui_system,
+                reset_biome_table_ui_system.run_if(resource_changed::<biome::BiomeTable>),
+            ),
+        );
+    }
+}
+
+// RESOURCES
+
+#[derive(Debug, Reflect, Resource)]
+#[reflect(Resource)]
+pub(super) struct BiomeTableUi {
+    biome_table: biome::BiomeTable,
+    timer: Timer,
+}
+
+impl Default for BiomeTableUi {
+    fn default() -> Self {
+        Self {
+            biome_table: biome::BiomeTable::default(),
+            timer: Timer::new(LATENCY, TimerMode::Once),
+        }
+    }
+}
+
+// SYSTEMS
+
+/// Update BiomeTable based on UI changes.
+fn update_biome_table_ui_system(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    biome_table: Res<biome::BiomeTable>,
+    mut biome_table_ui: ResMut<BiomeTableUi>,
+) {
+    if !biome_table_ui.timer.is_finished() {
+        biome_table_ui.timer.tick(time.delta());
+        if biome_table_ui.timer.just_finished() && biome_table_ui.biome_table != *biome_table {
+            commands.queue(undo::PushAction::from(biome::UpdateBiomeTableAction::new(
+                biome_table.clone(),
+                biome_table_ui.biome_table.clone(),
+            )));
+        }
+    }
+}
+
+/// Update BiomeTableUi based on changes to BiomeTable.
+///
+/// This gets triggered when undo/redo changes [BiomeTable](biome::BiomeTable).
+fn reset_biome_table_ui_system(
+    biome_table: Res<biome::BiomeTable>,
+    mut biome_table_ui: ResMut<BiomeTableUi>,
+) {
+    biome_table_ui.biome_table = biome_table.clone();
+    biome_table_ui.timer.pause();
+}
+
+// LIB
+
+pub(super) fn draw_ui_for_biomes(ui: &mut egui::Ui, biome_table_ui: &mut BiomeTableUi) {
+    ui.heading("Biomes");
+    let mut biomes: Vec<biome::Biome> = biome_table_ui.biome_table.biomes().to_vec();
+    let mut changed: bool = false;
+    if ui.button("Add biome").clicked() {
+        biomes.push(biome::Biome::new(
+            format!("Biome {}", biomes.len() + 1),
+            Color::srgb(0.5, 0.5, 0.5),
+        ));
+        changed = true;
+    }
+
+    let mut raised: Option<usize> = None;
+    let mut removed: Option<usize> = None;
+    for (idx, biome) in biomes.iter_mut().enumerate() {
+        let frame = egui::containers::Frame::group(ui.style());
+        frame.show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let [r, g, b, _] = biome.color.to_srgba().to_u8_array();
+                let mut color: [u8; 3] = [r, g, b];
+                if ui.color_edit_button_srgb(&mut color).changed() {
+                    biome.color = Color::srgb_u8(color[0], color[1], color[2]);
+                    changed = true;
+                }
+                if ui
+                    .add(
+                        egui::widgets::TextEdit::singleline(&mut biome.name)
+                            .char_limit(BIOME_NAME_CHAR_LIMIT),
+                    )
+                    .changed()
+                {
+                    changed = true;
+                }
+                if ui
+                    .add_enabled(idx > 0, egui::Button::new("Raise"))
+                    .clicked()
+                {
+                    raised = Some(idx);
+                }
+                if ui.button("Delete").clicked() {
+                    removed = Some(idx);
+                }
+            });
+            changed |= draw_ui_for_biome_range(
+                ui,
+                "Height:",
+                Some(layer::HEIGHT_RANGE),
+                &mut biome.height,
+                biome::BiomeRange::new(0.0, 100.0),
+            );
+            changed |= draw_ui_for_biome_range(
+                ui,
+                "Slope:",
+                Some(SLOPE_RANGE),
+                &mut biome.slope,
+                biome::BiomeRange::new(0.0, 45.0),
+            );
+            changed |= draw_ui_for_biome_range(
+                ui,
+                "Moisture:",
+                None,
+                &mut biome.moisture,
+                biome::BiomeRange::new(0.0, 1.0),
+            );
+            changed |= draw_ui_for_biome_range(
+                ui,
+                "Temperature:",
+                None,
+                &mut biome.temperature,
+                biome::BiomeRange::new(0.0, 30.0),
+            );
+        });
+    }
+    if let Some(idx) = raised {
+        biomes.swap(idx - 1, idx);
+        changed = true;
+    }
+    if let Some(idx) = removed {
+        biomes.remove(idx);
+        changed = true;
+    }
+
+    if changed {
+        biome_table_ui.biome_table = biome::BiomeTable::new(biomes);
+        biome_table_ui.timer.unpause();
+        biome_table_ui.timer.reset();
+    }
+}
+
+/// Draw a rule of a biome, returns whether it is changed.
+///
+/// `default` is used when the rule is enabled.
+fn draw_ui_for_biome_range(
+    ui: &mut egui::Ui,
+    label: &str,
+    limits: Option<RangeInclusive<f32>>,
+    range: &mut Option<biome::BiomeRange>,
+    default: biome::BiomeRange,
+) -> bool {
+    let mut changed: bool = false;
+    ui.horizontal(|ui| {
+        let mut is_enabled: bool = range.is_some();
+        if ui.checkbox(&mut is_enabled, label).changed() {
+            *range = is_enabled.then_some(default);
+            changed = true;
+        }
+        if let Some(range) = range {
+            if let Some(new_min) = draw_ui_editable_f32(limits.clone(), None, ui, range.min) {
+                range.min = new_min.min(range.max);
+                changed = true;
+            }
+            if let Some(new_max) = draw_ui_editable_f32(limits, None, ui, range.max) {
+                range.max = new_max.max(range.min);
+                changed = true;
+            }
+        }
+    });
+    changed
+}
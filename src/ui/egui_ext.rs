@@ -160,6 +160,25 @@ impl ToColor32 for Color {
     }
 }
 
+/// Dim everything behind a modal window.
+pub fn draw_ui_modal_overlay(ctx: &egui::Context, color: Color32) {
+    // Basically copied from egui-file-dialog.
+    // See file_dialog::FileDialog::ui_update_modal_background.
+    let modal_overlay_response = egui::Area::new("Modal Overlay".into())
+        .interactable(true)
+        .fixed_pos(egui::Pos2::ZERO)
+        .show(ctx, |ui| {
+            let content_rect = ctx.input(egui::InputState::content_rect);
+
+            ui.allocate_response(content_rect.size(), egui::Sense::click());
+
+            ui.painter()
+                .rect_filled(content_rect, egui::CornerRadius::ZERO, color);
+        })
+        .response;
+    ctx.move_to_top(modal_overlay_response.layer_id);
+}
+
 pub fn draw_ui_editable_f32(
     range: Option<RangeInclusive<f32>>,
     speed: Option<f32>,
@@ -180,3 +199,23 @@ pub fn draw_ui_editable_f32(
         None
     }
 }
+
+pub fn draw_ui_editable_u32(
+    range: Option<RangeInclusive<u32>>,
+    ui: &mut egui::Ui,
+    value: u32,
+) -> Option<u32> {
+    let mut value_edited: u32 = value;
+    let mut widget = egui::widgets::DragValue::new(&mut value_edited)
+        .update_while_editing(false)
+        .speed(1.0);
+    if let Some(range) = range {
+        widget = widget.range(range);
+    }
+    let response = ui.add(widget);
+    if response.changed() && value_edited != value {
+        Some(value_edited)
+    } else {
+        None
+    }
+}
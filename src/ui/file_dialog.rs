@@ -21,11 +21,21 @@ use bevy::prelude::*;
 use bevy_egui::egui::{Color32, Context};
 use egui_file_dialog;
 
+use crate::bake::BakeFormat;
+use crate::session::save::TEXT_EXTENSION;
 use crate::theme::{Theme, ThemeColors};
 use crate::ui::egui_ext::ToColor32;
+use crate::ui::layer::ImportBitmapTarget;
 
+static DEFAULT_BIOME_MAP_FILE_NAME: &str = "biomes.png";
 static DEFAULT_FILE_NAME: &str = "untitled.yer";
+static DEFAULT_HEIGHT_MAP_FILE_STEM: &str = "heightmap";
+static FILE_FILTER_IMAGE_FILES_NAME: &str = "Image Files";
+static FILE_FILTER_MESH_FILES_NAME: &str = "Mesh Files";
 static FILE_FILTER_PROJECT_FILES_NAME: &str = "Project Files";
+static FILE_FILTER_TEXT_PROJECT_FILES_NAME: &str = "Text Project Files";
+static IMAGE_SUFFIX: &str = "png";
+static IMAGE_SUFFIXES: [&str; 1] = [IMAGE_SUFFIX];
 static SUFFIX: &str = "yer";
 
 // PLUGIN
@@ -34,13 +44,127 @@ pub struct UiFileDialogPlugin;
 
 impl Plugin for UiFileDialogPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<LoadFileDialog>()
+        app.register_type::<ExportBiomeMapFileDialog>()
+            .register_type::<ExportHeightMapFileDialog>()
+            .register_type::<ImportBitmapFileDialog>()
+            .register_type::<LoadFileDialog>()
             .register_type::<SaveFileDialog>();
     }
 }
 
 // COMPONENTS
 
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub(super) struct ExportBiomeMapFileDialog {
+    #[reflect(ignore)]
+    file_dialog: egui_file_dialog::FileDialog,
+}
+
+impl ExportBiomeMapFileDialog {
+    pub(super) fn show(&mut self, ctx: &Context) -> DialogState {
+        match self.file_dialog.update(ctx).state() {
+            egui_file_dialog::DialogState::Open => DialogState::Open,
+            egui_file_dialog::DialogState::Cancelled => DialogState::Cancelled,
+            egui_file_dialog::DialogState::Picked(path) => {
+                DialogState::Selected(sanitize_path(path.into(), IMAGE_SUFFIX))
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Default for ExportBiomeMapFileDialog {
+    fn default() -> Self {
+        let mut file_dialog = egui_file_dialog::FileDialog::new()
+            .add_save_extension(FILE_FILTER_IMAGE_FILES_NAME, IMAGE_SUFFIX)
+            .default_save_extension(FILE_FILTER_IMAGE_FILES_NAME)
+            .default_file_name(DEFAULT_BIOME_MAP_FILE_NAME)
+            .as_modal(true);
+        file_dialog.save_file();
+
+        Self { file_dialog }
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub(super) struct ExportHeightMapFileDialog {
+    #[reflect(ignore)]
+    file_dialog: egui_file_dialog::FileDialog,
+    #[reflect(ignore)]
+    suffix: &'static str,
+}
+
+impl ExportHeightMapFileDialog {
+    pub(super) fn new(format: BakeFormat) -> Self {
+        let suffix: &'static str = format.extension();
+        let file_filter_name: &str = if format.is_mesh() {
+            FILE_FILTER_MESH_FILES_NAME
+        } else {
+            FILE_FILTER_IMAGE_FILES_NAME
+        };
+        let mut file_dialog = egui_file_dialog::FileDialog::new()
+            .add_save_extension(file_filter_name, suffix)
+            .default_save_extension(file_filter_name)
+            .default_file_name(&format!("{}.{}", DEFAULT_HEIGHT_MAP_FILE_STEM, suffix))
+            .as_modal(true);
+        file_dialog.save_file();
+
+        Self {
+            file_dialog,
+            suffix,
+        }
+    }
+
+    pub(super) fn show(&mut self, ctx: &Context) -> DialogState {
+        match self.file_dialog.update(ctx).state() {
+            egui_file_dialog::DialogState::Open => DialogState::Open,
+            egui_file_dialog::DialogState::Cancelled => DialogState::Cancelled,
+            egui_file_dialog::DialogState::Picked(path) => {
+                DialogState::Selected(sanitize_path(path.into(), self.suffix))
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub(super) struct ImportBitmapFileDialog {
+    #[reflect(ignore)]
+    file_dialog: egui_file_dialog::FileDialog,
+    target: ImportBitmapTarget,
+}
+
+impl ImportBitmapFileDialog {
+    pub(super) fn new(target: ImportBitmapTarget) -> Self {
+        let mut file_dialog = egui_file_dialog::FileDialog::new()
+            .add_file_filter_extensions(FILE_FILTER_IMAGE_FILES_NAME, IMAGE_SUFFIXES.to_vec())
+            .default_file_filter(FILE_FILTER_IMAGE_FILES_NAME)
+            .as_modal(true);
+        file_dialog.pick_file();
+
+        Self {
+            file_dialog,
+            target,
+        }
+    }
+
+    pub(super) fn target(&self) -> ImportBitmapTarget {
+        self.target
+    }
+
+    pub(super) fn show(&mut self, ctx: &Context) -> DialogState {
+        match self.file_dialog.update(ctx).state() {
+            egui_file_dialog::DialogState::Open => DialogState::Open,
+            egui_file_dialog::DialogState::Cancelled => DialogState::Cancelled,
+            egui_file_dialog::DialogState::Picked(path) => DialogState::Selected(path.into()),
+            _ => unreachable!(),
+        }
+    }
+}
+
 #[derive(Component, Reflect)]
 #[reflect(Component)]
 pub(super) struct LoadFileDialog {
@@ -71,7 +195,10 @@ impl FromWorld for LoadFileDialog {
         };
 
         let mut file_dialog = egui_file_dialog::FileDialog::new()
-            .add_file_filter_extensions(FILE_FILTER_PROJECT_FILES_NAME, vec![SUFFIX])
+            .add_file_filter_extensions(
+                FILE_FILTER_PROJECT_FILES_NAME,
+                vec![SUFFIX, TEXT_EXTENSION],
+            )
             .default_file_filter(FILE_FILTER_PROJECT_FILES_NAME)
             .modal_overlay_color(modal_overlay_color)
             .as_modal(true);
@@ -93,8 +220,14 @@ impl SaveFileDialog {
         match self.file_dialog.update(ctx).state() {
             egui_file_dialog::DialogState::Open => DialogState::Open,
             egui_file_dialog::DialogState::Cancelled => DialogState::Cancelled,
+            // Text project files are saved as they are.
+            egui_file_dialog::DialogState::Picked(path)
+                if path.extension() == Some(OsStr::new(TEXT_EXTENSION)) =>
+            {
+                DialogState::Selected(path.into())
+            }
             egui_file_dialog::DialogState::Picked(path) => {
-                DialogState::Selected(sanitize_path(path.into()))
+                DialogState::Selected(sanitize_path(path.into(), SUFFIX))
             }
             _ => unreachable!(),
         }
@@ -105,6 +238,7 @@ impl Default for SaveFileDialog {
     fn default() -> Self {
         let mut file_dialog = egui_file_dialog::FileDialog::new()
             .add_save_extension(FILE_FILTER_PROJECT_FILES_NAME, SUFFIX)
+            .add_save_extension(FILE_FILTER_TEXT_PROJECT_FILES_NAME, TEXT_EXTENSION)
             .default_save_extension(FILE_FILTER_PROJECT_FILES_NAME)
             .default_file_name(DEFAULT_FILE_NAME)
             .as_modal(true);
@@ -122,9 +256,12 @@ pub enum DialogState {
     Cancelled,
 }
 
-fn sanitize_path(path: PathBuf) -> PathBuf {
-    let suffix = format!(".{}", SUFFIX);
-    if path.extension() != Some(OsStr::new(SUFFIX)) {
+/// Append `suffix` to the file name of `path` if it has a different
+/// extension.
+fn sanitize_path(path: PathBuf, suffix: &str) -> PathBuf {
+    let extension: &OsStr = OsStr::new(suffix);
+    let suffix = format!(".{}", suffix);
+    if path.extension() != Some(extension) {
         match path.file_name() {
             Some(file_name) => {
                 let mut file_name = file_name.to_owned();
@@ -146,7 +283,7 @@ mod tests {
     fn sanitize_path_appends_extension_to_filename_without_extension() {
         assert_eq!(
             PathBuf::from("somefile.yer"),
-            sanitize_path(PathBuf::from("somefile"))
+            sanitize_path(PathBuf::from("somefile"), SUFFIX)
         );
     }
 
@@ -154,7 +291,7 @@ mod tests {
     fn sanitize_path_does_not_change_filename_with_correct_extension() {
         assert_eq!(
             PathBuf::from("somefile.yer"),
-            sanitize_path(PathBuf::from("somefile.yer"))
+            sanitize_path(PathBuf::from("somefile.yer"), SUFFIX)
         );
     }
 
@@ -162,7 +299,7 @@ mod tests {
     fn sanitize_path_adds_extension_if_the_filename_has_a_different_extension() {
         assert_eq!(
             PathBuf::from("some.file.yer"),
-            sanitize_path(PathBuf::from("some.file"))
+            sanitize_path(PathBuf::from("some.file"), SUFFIX)
         );
     }
 }
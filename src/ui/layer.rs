@@ -15,23 +15,34 @@
 // with Yer.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use bevy::ecs::{query::QueryData, system::SystemParam};
 use bevy::prelude::*;
 use bevy_egui::egui;
 
-use crate::id::{LayerId, MaskId};
+use crate::biome;
+use crate::id::{BiomeId, LayerId, MaskId};
 use crate::layer;
-use crate::math::{approx_eq, ONE_IN_TEN_THOUSAND};
+use crate::math::{approx_eq, Channel, ONE_IN_TEN_THOUSAND};
 use crate::theme;
 use crate::undo;
 
-use super::egui_ext::{draw_ui_editable_f32, ToColor32};
+use super::egui_ext::{draw_ui_editable_f32, draw_ui_editable_u32, ToColor32};
+use super::file_dialog::ImportBitmapFileDialog;
+use super::UiState;
 
 const LATENCY: Duration = Duration::from_millis(100);
 const LAYER_SELECTION_BOX_WIDTH: f32 = 24.0f32;
+const MASK_CURVE_EDITOR_SIZE: f32 = 96.0;
+const MASK_CURVE_POINT_RADIUS: f32 = 4.0;
 const MINUS_ONE_TO_ONE: RangeInclusive<f32> = -1.0..=1.0;
+const HYDRAULIC_EROSION_ITERATIONS_RANGE: RangeInclusive<u32> = 1..=16;
+const NOISE_OCTAVES_RANGE: RangeInclusive<u32> = 1..=12;
+const SLOPE_ANGLE_RANGE: RangeInclusive<f32> = 0.0..=90.0;
+const THERMAL_EROSION_ANGLE_RANGE: RangeInclusive<f32> = 0.0..=89.0;
+const THERMAL_EROSION_ITERATIONS_RANGE: RangeInclusive<u32> = 1..=500;
 const ZERO_TO_POSITIVE_INFINITY: RangeInclusive<f32> = 0.0..=f32::INFINITY;
 const ZERO_TO_ONE: RangeInclusive<f32> = 0.0..=1.0;
 const ZERO_TO_ONE_INCREMENT: f32 = 0.0025;
@@ -53,8 +64,10 @@ impl Plugin for LayerUiPlugin {
                 add_layer_ui_system,
                 add_mask_ui_system,
                 update_height_map_ui_system,
+                update_layer_ui_system,
                 update_mask_ui_system,
                 reset_height_map_ui_system,
+                reset_layer_ui_system,
                 reset_mask_ui_system,
             ),
         );
@@ -134,35 +147,168 @@ impl<'w, 's> Masks<'w, 's> {
 
 #[derive(Component, Debug, Reflect)]
 pub(super) enum HeightMapUi {
-    Constant { height: f32, timer: Timer },
+    Bitmap {
+        black_height: f32,
+        position: Vec2,
+        rotation: f32,
+        size: Vec2,
+        timer: Timer,
+        white_height: f32,
+    },
+    Constant {
+        height: f32,
+        timer: Timer,
+    },
+    HydraulicErosion {
+        deposition_rate: f32,
+        erosion_rate: f32,
+        iterations: u32,
+        rain_amount: f32,
+        sediment_capacity: f32,
+        seed: u32,
+        timer: Timer,
+    },
+    Noise {
+        amplitude: f32,
+        frequency: f32,
+        gain: f32,
+        lacunarity: f32,
+        octaves: u8,
+        offset: f32,
+        seed: u32,
+        timer: Timer,
+    },
+    Summit {
+        base_radius: f32,
+        peak_height: f32,
+        position: Vec2,
+        ridge_frequency: f32,
+        ridge_strength: f32,
+        seed: u32,
+        timer: Timer,
+    },
+    ThermalErosion {
+        angle: f32,
+        iterations: u32,
+        strength: f32,
+        timer: Timer,
+    },
 }
 
 impl From<&layer::HeightMap> for HeightMapUi {
     fn from(value: &layer::HeightMap) -> Self {
         match value {
+            layer::HeightMap::Bitmap {
+                black_height,
+                position,
+                rotation,
+                size,
+                white_height,
+                ..
+            } => Self::Bitmap {
+                black_height: *black_height,
+                position: *position,
+                rotation: *rotation,
+                size: *size,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+                white_height: *white_height,
+            },
             layer::HeightMap::Constant(height) => Self::Constant {
                 height: *height,
                 timer: Timer::new(LATENCY, TimerMode::Once),
             },
+            layer::HeightMap::HydraulicErosion {
+                deposition_rate,
+                erosion_rate,
+                iterations,
+                rain_amount,
+                sediment_capacity,
+                seed,
+            } => Self::HydraulicErosion {
+                deposition_rate: *deposition_rate,
+                erosion_rate: *erosion_rate,
+                iterations: *iterations,
+                rain_amount: *rain_amount,
+                sediment_capacity: *sediment_capacity,
+                seed: *seed,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+            },
+            layer::HeightMap::Noise {
+                amplitude,
+                frequency,
+                gain,
+                lacunarity,
+                octaves,
+                offset,
+                seed,
+            } => Self::Noise {
+                amplitude: *amplitude,
+                frequency: *frequency,
+                gain: *gain,
+                lacunarity: *lacunarity,
+                octaves: *octaves,
+                offset: *offset,
+                seed: *seed,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+            },
+            layer::HeightMap::Summit {
+                base_radius,
+                peak_height,
+                position,
+                ridge_frequency,
+                ridge_strength,
+                seed,
+                ..
+            } => Self::Summit {
+                base_radius: *base_radius,
+                peak_height: *peak_height,
+                position: *position,
+                ridge_frequency: *ridge_frequency,
+                ridge_strength: *ridge_strength,
+                seed: *seed,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+            },
+            layer::HeightMap::ThermalErosion {
+                angle,
+                iterations,
+                strength,
+            } => Self::ThermalErosion {
+                angle: *angle,
+                iterations: *iterations,
+                strength: *strength,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+            },
         }
     }
 }
 
 #[derive(Component, Debug, Reflect)]
 pub(super) struct LayerUi {
+    /// Name of the custom channel to be added.
+    custom_channel: String,
     name: String,
+    opacity: f32,
+    /// Opacity of the layer when `opacity` was last reset.  Changes to the
+    /// other fields of the layer keep the pending edits of `opacity`.
+    synced_opacity: f32,
+    timer: Timer,
 }
 
 impl From<&layer::Layer> for LayerUi {
     fn from(layer: &layer::Layer) -> Self {
         Self {
+            custom_channel: String::new(),
             name: layer.name.clone(),
+            opacity: layer.opacity,
+            synced_opacity: layer.opacity,
+            timer: Timer::new(LATENCY, TimerMode::Once),
         }
     }
 }
 
 #[derive(Component, Debug, Reflect)]
 pub(super) struct MaskUi {
+    curve: layer::MaskCurve,
     strength: f32,
     timer: Timer,
 }
@@ -170,6 +316,7 @@ pub(super) struct MaskUi {
 impl From<&layer::Mask> for MaskUi {
     fn from(mask: &layer::Mask) -> Self {
         Self {
+            curve: mask.curve.clone(),
             strength: mask.strength,
             timer: Timer::new(LATENCY, TimerMode::Once),
         }
@@ -178,6 +325,17 @@ impl From<&layer::Mask> for MaskUi {
 
 #[derive(Component, Debug, Reflect)]
 pub(super) enum MaskSourceUi {
+    /// Biome masks are updated as soon as a biome is selected.
+    Biome,
+    Bitmap {
+        black_point: f32,
+        center: Vec2,
+        out_of_bounds_value: f32,
+        rotation: f32,
+        size: f32,
+        timer: Timer,
+        white_point: f32,
+    },
     Circle {
         center: Vec2,
         falloff_radius: f32,
@@ -187,6 +345,43 @@ pub(super) enum MaskSourceUi {
         smoothness: f32,
         timer: Timer,
     },
+    Gradient {
+        curve: layer::MaskCurve,
+        end: Vec2,
+        smoothness: f32,
+        start: Vec2,
+        timer: Timer,
+    },
+    Height {
+        falloff: f32,
+        max: f32,
+        min: f32,
+        timer: Timer,
+    },
+    Noise {
+        contrast: f32,
+        octaves: u8,
+        scale: f32,
+        seed: u32,
+        threshold: f32,
+        timer: Timer,
+    },
+    Polygon {
+        smoothness: f32,
+        timer: Timer,
+        vertices: Vec<layer::MaskVertex>,
+    },
+    Slope {
+        falloff: f32,
+        max_angle: f32,
+        min_angle: f32,
+        timer: Timer,
+    },
+    Spline {
+        smoothness: f32,
+        timer: Timer,
+        vertices: Vec<layer::MaskVertex>,
+    },
     Square {
         center: Vec2,
         falloff_radius: f32,
@@ -201,6 +396,24 @@ pub(super) enum MaskSourceUi {
 impl From<&layer::MaskSource> for MaskSourceUi {
     fn from(value: &layer::MaskSource) -> Self {
         match value {
+            layer::MaskSource::Biome { .. } => Self::Biome,
+            layer::MaskSource::Bitmap {
+                black_point,
+                center,
+                out_of_bounds_value,
+                rotation,
+                size,
+                white_point,
+                ..
+            } => Self::Bitmap {
+                black_point: *black_point,
+                center: *center,
+                out_of_bounds_value: *out_of_bounds_value,
+                rotation: *rotation,
+                size: *size,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+                white_point: *white_point,
+            },
             layer::MaskSource::Circle {
                 center,
                 falloff_radius,
@@ -218,6 +431,67 @@ impl From<&layer::MaskSource> for MaskSourceUi {
                 smoothness: *smoothness,
                 timer: Timer::new(LATENCY, TimerMode::Once),
             },
+            layer::MaskSource::Gradient {
+                curve,
+                end,
+                smoothness,
+                start,
+                ..
+            } => Self::Gradient {
+                curve: curve.clone(),
+                end: *end,
+                smoothness: *smoothness,
+                start: *start,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+            },
+            layer::MaskSource::Height { falloff, max, min } => Self::Height {
+                falloff: *falloff,
+                max: *max,
+                min: *min,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+            },
+            layer::MaskSource::Noise {
+                contrast,
+                octaves,
+                scale,
+                seed,
+                threshold,
+                ..
+            } => Self::Noise {
+                contrast: *contrast,
+                octaves: *octaves,
+                scale: *scale,
+                seed: *seed,
+                threshold: *threshold,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+            },
+            layer::MaskSource::Polygon {
+                smoothness,
+                vertices,
+            } => Self::Polygon {
+                smoothness: *smoothness,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+                vertices: vertices.clone(),
+            },
+            layer::MaskSource::Slope {
+                falloff,
+                max_angle,
+                min_angle,
+                ..
+            } => Self::Slope {
+                falloff: *falloff,
+                max_angle: *max_angle,
+                min_angle: *min_angle,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+            },
+            layer::MaskSource::Spline {
+                smoothness,
+                vertices,
+            } => Self::Spline {
+                smoothness: *smoothness,
+                timer: Timer::new(LATENCY, TimerMode::Once),
+                vertices: vertices.clone(),
+            },
             layer::MaskSource::Square {
                 center,
                 falloff_radius,
@@ -246,6 +520,74 @@ pub(super) struct Selected;
 
 // COMMANDS
 
+/// Load a bitmap from `path` and replace the image of `target`.
+pub(super) struct ImportBitmap {
+    pub path: PathBuf,
+    pub target: ImportBitmapTarget,
+}
+
+impl Command for ImportBitmap {
+    fn apply(self, world: &mut World) {
+        info!("Importing bitmap '{}'", self.path.to_string_lossy());
+        let old_image: layer::BitmapImage = match self.target {
+            ImportBitmapTarget::HeightMap(layer_id) => match world
+                .query::<(&layer::Layer, &layer::HeightMap)>()
+                .iter(world)
+                .find(|(layer, _)| layer.id() == layer_id)
+                .map(|(_, height_map)| height_map)
+            {
+                Some(layer::HeightMap::Bitmap { image, .. }) => image.clone(),
+                Some(_) => {
+                    error!("Layer with id {} is not a bitmap layer.", layer_id);
+                    return;
+                }
+                None => {
+                    warn!("Layer with id {} no longer exists.", layer_id);
+                    return;
+                }
+            },
+            ImportBitmapTarget::MaskSource(mask_id) => match world
+                .query::<(&layer::Mask, &layer::MaskSource)>()
+                .iter(world)
+                .find(|(mask, _)| mask.id() == mask_id)
+                .map(|(_, mask_source)| mask_source)
+            {
+                Some(layer::MaskSource::Bitmap { image, .. }) => image.clone(),
+                Some(_) => {
+                    error!("Mask with id {} is not a bitmap mask.", mask_id);
+                    return;
+                }
+                None => {
+                    warn!("Mask with id {} no longer exists.", mask_id);
+                    return;
+                }
+            },
+        };
+        match layer::BitmapImage::load(self.path.as_path()) {
+            Ok(new_image) => match self.target {
+                ImportBitmapTarget::HeightMap(layer_id) => {
+                    undo::PushAction::from(layer::HeightMapBitmapUpdateAction::update_image(
+                        layer_id, old_image, new_image,
+                    ))
+                    .apply(world)
+                }
+                ImportBitmapTarget::MaskSource(mask_id) => undo::PushAction::from(
+                    layer::UpdateMaskSourceAction::update_image(mask_id, old_image, new_image),
+                )
+                .apply(world),
+            },
+            Err(e) => error!(error = &e as &dyn core::error::Error),
+        }
+    }
+}
+
+/// What an imported bitmap is going to replace.
+#[derive(Clone, Copy, Debug, Reflect)]
+pub(super) enum ImportBitmapTarget {
+    HeightMap(LayerId),
+    MaskSource(MaskId),
+}
+
 struct SelectLayer(Entity);
 
 impl Command for SelectLayer {
@@ -300,133 +642,73 @@ fn add_mask_ui_system(
 /// duration.  When there are frequent updates to HeightMapUi (such as
 /// dragging the input) only the last one gets triggered.  See [LATENCY].
 ///
-/// See also [HeightMapConstantUpdateHeightAction](layer::HeightMapConstantUpdateHeightAction).
+/// See also [HeightMapBitmapUpdateAction](layer::HeightMapBitmapUpdateAction),
+/// [HeightMapConstantUpdateHeightAction](layer::HeightMapConstantUpdateHeightAction),
+/// [HeightMapHydraulicErosionUpdateAction](layer::HeightMapHydraulicErosionUpdateAction),
+/// [HeightMapNoiseUpdateAction](layer::HeightMapNoiseUpdateAction),
+/// [HeightMapSummitUpdateAction](layer::HeightMapSummitUpdateAction) and
+/// [HeightMapThermalErosionUpdateAction](layer::HeightMapThermalErosionUpdateAction).
 fn update_height_map_ui_system(
     mut commands: Commands,
     time: Res<Time<Real>>,
     mut layers: Query<(&layer::Layer, &layer::HeightMap, &mut HeightMapUi)>,
 ) {
     for (layer, height_map, mut height_map_ui) in layers.iter_mut() {
-        match *height_map_ui {
-            HeightMapUi::Constant {
-                height,
-                ref mut timer,
-            } => {
-                if !timer.is_finished() {
-                    timer.tick(time.delta());
-                    let layer::HeightMap::Constant(original_height) = height_map;
-                    if timer.just_finished()
-                        && !approx_eq(*original_height, height, ONE_IN_TEN_THOUSAND)
-                    {
-                        commands.queue(undo::PushAction::from(
-                            layer::HeightMapConstantUpdateHeightAction::new(
-                                layer.id(),
-                                *original_height,
-                                height,
-                            ),
-                        ));
-                    }
-                }
-            }
-        }
-    }
-}
-
-/// Update MaskSource based on UI changes.
-fn update_mask_ui_system(
-    mut commands: Commands,
-    time: Res<Time<Real>>,
-    mut mask_query: Query<(
-        &layer::Mask,
-        &layer::MaskSource,
-        &mut MaskUi,
-        &mut MaskSourceUi,
-    )>,
-) {
-    for (mask, mask_source, mut mask_ui, mut mask_source_ui) in mask_query.iter_mut() {
-        if !mask_ui.timer.is_finished() {
-            mask_ui.timer.tick(time.delta());
-            if mask_ui.timer.just_finished()
-                && !approx_eq(mask.strength, mask_ui.strength, ONE_IN_TEN_THOUSAND)
-            {
-                commands.queue(undo::PushAction::from(
-                    layer::UpdateMaskAction::update_strength(
-                        mask.id(),
-                        mask.strength,
-                        mask_ui.strength,
-                    ),
-                ));
-            }
-        }
-
-        match (mask_source, mask_source_ui.as_mut()) {
+        match (height_map, height_map_ui.as_mut()) {
             (
-                layer::MaskSource::Circle {
-                    center: original_center,
-                    falloff_radius: original_falloff_radius,
-                    irregularity: original_irregularity,
-                    radius: original_radius,
+                layer::HeightMap::Bitmap {
+                    black_height: original_black_height,
+                    position: original_position,
                     rotation: original_rotation,
-                    smoothness: original_smoothness,
+                    size: original_size,
+                    white_height: original_white_height,
                     ..
                 },
-                &mut MaskSourceUi::Circle {
-                    ref center,
-                    ref falloff_radius,
-                    ref irregularity,
-                    ref radius,
+                &mut HeightMapUi::Bitmap {
+                    ref black_height,
+                    ref position,
                     ref rotation,
-                    ref smoothness,
+                    ref size,
                     ref mut timer,
+                    ref white_height,
                 },
             ) => {
                 if !timer.is_finished() {
                     timer.tick(time.delta());
                     if timer.just_finished()
-                        && !approx_eq(original_center.distance(*center), 0.0, ONE_IN_TEN_THOUSAND)
-                    {
-                        commands.queue(undo::PushAction::from(
-                            layer::UpdateMaskSourceAction::update_center(
-                                mask.id(),
-                                *original_center,
-                                *center,
-                            ),
-                        ));
-                    }
-                    if timer.just_finished()
-                        && !approx_eq(
-                            *original_falloff_radius,
-                            *falloff_radius,
-                            ONE_IN_TEN_THOUSAND,
-                        )
+                        && !approx_eq(*original_black_height, *black_height, ONE_IN_TEN_THOUSAND)
                     {
                         commands.queue(undo::PushAction::from(
-                            layer::UpdateMaskSourceAction::update_falloff_radius(
-                                mask.id(),
-                                *original_falloff_radius,
-                                *falloff_radius,
+                            layer::HeightMapBitmapUpdateAction::update_black_height(
+                                layer.id(),
+                                *original_black_height,
+                                *black_height,
                             ),
                         ));
                     }
                     if timer.just_finished()
-                        && !approx_eq(*original_irregularity, *irregularity, ONE_IN_TEN_THOUSAND)
+                        && !approx_eq(*original_white_height, *white_height, ONE_IN_TEN_THOUSAND)
                     {
                         commands.queue(undo::PushAction::from(
-                            layer::UpdateMaskSourceAction::update_irregularity(
-                                mask.id(),
-                                *original_irregularity,
-                                *irregularity,
+                            layer::HeightMapBitmapUpdateAction::update_white_height(
+                                layer.id(),
+                                *original_white_height,
+                                *white_height,
                             ),
                         ));
                     }
                     if timer.just_finished()
-                        && !approx_eq(*original_radius, *radius, ONE_IN_TEN_THOUSAND)
+                        && !approx_eq(
+                            original_position.distance(*position),
+                            0.0,
+                            ONE_IN_TEN_THOUSAND,
+                        )
                     {
                         commands.queue(undo::PushAction::from(
-                            layer::UpdateMaskSourceAction::update_radius(
-                                mask.id(),
-                                *original_radius,
-                                *radius,
+                            layer::HeightMapBitmapUpdateAction::update_position(
+                                layer.id(),
+                                *original_position,
+                                *position,
                             ),
                         ));
                     }
@@ -434,73 +716,1002 @@ fn update_mask_ui_system(
                         && !approx_eq(*original_rotation, *rotation, ONE_IN_TEN_THOUSAND)
                     {
                         commands.queue(undo::PushAction::from(
-                            layer::UpdateMaskSourceAction::update_rotation(
-                                mask.id(),
+                            layer::HeightMapBitmapUpdateAction::update_rotation(
+                                layer.id(),
                                 *original_rotation,
                                 *rotation,
                             ),
                         ));
                     }
                     if timer.just_finished()
-                        && !approx_eq(*original_smoothness, *smoothness, ONE_IN_TEN_THOUSAND)
+                        && !approx_eq(original_size.distance(*size), 0.0, ONE_IN_TEN_THOUSAND)
                     {
                         commands.queue(undo::PushAction::from(
-                            layer::UpdateMaskSourceAction::update_smoothness(
-                                mask.id(),
-                                *original_smoothness,
-                                *smoothness,
+                            layer::HeightMapBitmapUpdateAction::update_size(
+                                layer.id(),
+                                *original_size,
+                                *size,
                             ),
                         ));
                     }
                 }
             }
-            (layer::MaskSource::Circle { .. }, _) => unreachable!(),
+            (layer::HeightMap::Bitmap { .. }, _) => unreachable!(),
             (
-                layer::MaskSource::Square {
-                    center: original_center,
-                    falloff_radius: original_falloff_radius,
-                    irregularity: original_irregularity,
-                    rotation: original_rotation,
-                    size: original_size,
-                    smoothness: original_smoothness,
-                    ..
-                },
-                &mut MaskSourceUi::Square {
-                    ref center,
-                    ref falloff_radius,
-                    ref irregularity,
-                    ref rotation,
-                    ref size,
-                    ref smoothness,
+                layer::HeightMap::Constant(original_height),
+                &mut HeightMapUi::Constant {
+                    height,
                     ref mut timer,
                 },
             ) => {
                 if !timer.is_finished() {
                     timer.tick(time.delta());
-
                     if timer.just_finished()
-                        && !approx_eq(original_center.distance(*center), 0.0, ONE_IN_TEN_THOUSAND)
+                        && !approx_eq(*original_height, height, ONE_IN_TEN_THOUSAND)
                     {
                         commands.queue(undo::PushAction::from(
-                            layer::UpdateMaskSourceAction::update_center(
-                                mask.id(),
-                                *original_center,
-                                *center,
+                            layer::HeightMapConstantUpdateHeightAction::new(
+                                layer.id(),
+                                *original_height,
+                                height,
                             ),
                         ));
                     }
+                }
+            }
+            (layer::HeightMap::Constant(_), _) => unreachable!(),
+            (
+                layer::HeightMap::HydraulicErosion {
+                    deposition_rate: original_deposition_rate,
+                    erosion_rate: original_erosion_rate,
+                    iterations: original_iterations,
+                    rain_amount: original_rain_amount,
+                    sediment_capacity: original_sediment_capacity,
+                    seed: original_seed,
+                },
+                &mut HeightMapUi::HydraulicErosion {
+                    ref deposition_rate,
+                    ref erosion_rate,
+                    ref iterations,
+                    ref rain_amount,
+                    ref sediment_capacity,
+                    ref seed,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
                     if timer.just_finished()
                         && !approx_eq(
-                            *original_falloff_radius,
-                            *falloff_radius,
+                            *original_deposition_rate,
+                            *deposition_rate,
                             ONE_IN_TEN_THOUSAND,
                         )
                     {
                         commands.queue(undo::PushAction::from(
-                            layer::UpdateMaskSourceAction::update_falloff_radius(
-                                mask.id(),
-                                *original_falloff_radius,
-                                *falloff_radius,
+                            layer::HeightMapHydraulicErosionUpdateAction::update_deposition_rate(
+                                layer.id(),
+                                *original_deposition_rate,
+                                *deposition_rate,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_erosion_rate, *erosion_rate, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapHydraulicErosionUpdateAction::update_erosion_rate(
+                                layer.id(),
+                                *original_erosion_rate,
+                                *erosion_rate,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && original_iterations != iterations {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapHydraulicErosionUpdateAction::update_iterations(
+                                layer.id(),
+                                *original_iterations,
+                                *iterations,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_rain_amount, *rain_amount, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapHydraulicErosionUpdateAction::update_rain_amount(
+                                layer.id(),
+                                *original_rain_amount,
+                                *rain_amount,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(
+                            *original_sediment_capacity,
+                            *sediment_capacity,
+                            ONE_IN_TEN_THOUSAND,
+                        )
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapHydraulicErosionUpdateAction::update_sediment_capacity(
+                                layer.id(),
+                                *original_sediment_capacity,
+                                *sediment_capacity,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && original_seed != seed {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapHydraulicErosionUpdateAction::update_seed(
+                                layer.id(),
+                                *original_seed,
+                                *seed,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::HeightMap::HydraulicErosion { .. }, _) => unreachable!(),
+            (
+                layer::HeightMap::Noise {
+                    amplitude: original_amplitude,
+                    frequency: original_frequency,
+                    gain: original_gain,
+                    lacunarity: original_lacunarity,
+                    octaves: original_octaves,
+                    offset: original_offset,
+                    seed: original_seed,
+                },
+                &mut HeightMapUi::Noise {
+                    ref amplitude,
+                    ref frequency,
+                    ref gain,
+                    ref lacunarity,
+                    ref octaves,
+                    ref offset,
+                    ref seed,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(*original_amplitude, *amplitude, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapNoiseUpdateAction::update_amplitude(
+                                layer.id(),
+                                *original_amplitude,
+                                *amplitude,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_frequency, *frequency, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapNoiseUpdateAction::update_frequency(
+                                layer.id(),
+                                *original_frequency,
+                                *frequency,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_gain, *gain, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapNoiseUpdateAction::update_gain(
+                                layer.id(),
+                                *original_gain,
+                                *gain,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_lacunarity, *lacunarity, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapNoiseUpdateAction::update_lacunarity(
+                                layer.id(),
+                                *original_lacunarity,
+                                *lacunarity,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && original_octaves != octaves {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapNoiseUpdateAction::update_octaves(
+                                layer.id(),
+                                *original_octaves,
+                                *octaves,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_offset, *offset, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapNoiseUpdateAction::update_offset(
+                                layer.id(),
+                                *original_offset,
+                                *offset,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && original_seed != seed {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapNoiseUpdateAction::update_seed(
+                                layer.id(),
+                                *original_seed,
+                                *seed,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::HeightMap::Noise { .. }, _) => unreachable!(),
+            (
+                layer::HeightMap::Summit {
+                    base_radius: original_base_radius,
+                    peak_height: original_peak_height,
+                    position: original_position,
+                    ridge_frequency: original_ridge_frequency,
+                    ridge_strength: original_ridge_strength,
+                    seed: original_seed,
+                    ..
+                },
+                &mut HeightMapUi::Summit {
+                    ref base_radius,
+                    ref peak_height,
+                    ref position,
+                    ref ridge_frequency,
+                    ref ridge_strength,
+                    ref seed,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(*original_base_radius, *base_radius, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapSummitUpdateAction::update_base_radius(
+                                layer.id(),
+                                *original_base_radius,
+                                *base_radius,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_peak_height, *peak_height, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapSummitUpdateAction::update_peak_height(
+                                layer.id(),
+                                *original_peak_height,
+                                *peak_height,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(
+                            original_position.distance(*position),
+                            0.0,
+                            ONE_IN_TEN_THOUSAND,
+                        )
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapSummitUpdateAction::update_position(
+                                layer.id(),
+                                *original_position,
+                                *position,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(
+                            *original_ridge_frequency,
+                            *ridge_frequency,
+                            ONE_IN_TEN_THOUSAND,
+                        )
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapSummitUpdateAction::update_ridge_frequency(
+                                layer.id(),
+                                *original_ridge_frequency,
+                                *ridge_frequency,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(
+                            *original_ridge_strength,
+                            *ridge_strength,
+                            ONE_IN_TEN_THOUSAND,
+                        )
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapSummitUpdateAction::update_ridge_strength(
+                                layer.id(),
+                                *original_ridge_strength,
+                                *ridge_strength,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && original_seed != seed {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapSummitUpdateAction::update_seed(
+                                layer.id(),
+                                *original_seed,
+                                *seed,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::HeightMap::Summit { .. }, _) => unreachable!(),
+            (
+                layer::HeightMap::ThermalErosion {
+                    angle: original_angle,
+                    iterations: original_iterations,
+                    strength: original_strength,
+                },
+                &mut HeightMapUi::ThermalErosion {
+                    ref angle,
+                    ref iterations,
+                    ref strength,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(*original_angle, *angle, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapThermalErosionUpdateAction::update_angle(
+                                layer.id(),
+                                *original_angle,
+                                *angle,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && original_iterations != iterations {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapThermalErosionUpdateAction::update_iterations(
+                                layer.id(),
+                                *original_iterations,
+                                *iterations,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_strength, *strength, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::HeightMapThermalErosionUpdateAction::update_strength(
+                                layer.id(),
+                                *original_strength,
+                                *strength,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::HeightMap::ThermalErosion { .. }, _) => unreachable!(),
+        }
+    }
+}
+
+/// Update Layer based on UI changes.
+///
+/// Like [update_height_map_ui_system], the changes are debounced.
+fn update_layer_ui_system(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut layers: Query<(&layer::Layer, &mut LayerUi)>,
+) {
+    for (layer, mut layer_ui) in layers.iter_mut() {
+        if !layer_ui.timer.is_finished() {
+            layer_ui.timer.tick(time.delta());
+            if layer_ui.timer.just_finished()
+                && !approx_eq(layer.opacity, layer_ui.opacity, ONE_IN_TEN_THOUSAND)
+            {
+                commands.queue(undo::PushAction::from(
+                    layer::UpdateLayerAction::update_opacity(layer, layer_ui.opacity),
+                ));
+            }
+        }
+    }
+}
+
+/// Update MaskSource based on UI changes.
+fn update_mask_ui_system(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut mask_query: Query<(
+        &layer::Mask,
+        &layer::MaskSource,
+        &mut MaskUi,
+        &mut MaskSourceUi,
+    )>,
+) {
+    for (mask, mask_source, mut mask_ui, mut mask_source_ui) in mask_query.iter_mut() {
+        if !mask_ui.timer.is_finished() {
+            mask_ui.timer.tick(time.delta());
+            if mask_ui.timer.just_finished()
+                && !approx_eq(mask.strength, mask_ui.strength, ONE_IN_TEN_THOUSAND)
+            {
+                commands.queue(undo::PushAction::from(
+                    layer::UpdateMaskAction::update_strength(
+                        mask.id(),
+                        mask.strength,
+                        mask_ui.strength,
+                    ),
+                ));
+            }
+            if mask_ui.timer.just_finished() && mask.curve != mask_ui.curve {
+                commands.queue(undo::PushAction::from(
+                    layer::UpdateMaskAction::update_curve(
+                        mask.id(),
+                        mask.curve.clone(),
+                        mask_ui.curve.clone(),
+                    ),
+                ));
+            }
+        }
+
+        match (mask_source, mask_source_ui.as_mut()) {
+            (layer::MaskSource::Biome { .. }, &mut MaskSourceUi::Biome) => (),
+            (layer::MaskSource::Biome { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Bitmap {
+                    black_point: original_black_point,
+                    center: original_center,
+                    out_of_bounds_value: original_out_of_bounds_value,
+                    rotation: original_rotation,
+                    size: original_size,
+                    white_point: original_white_point,
+                    ..
+                },
+                &mut MaskSourceUi::Bitmap {
+                    ref black_point,
+                    ref center,
+                    ref out_of_bounds_value,
+                    ref rotation,
+                    ref size,
+                    ref mut timer,
+                    ref white_point,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(original_center.distance(*center), 0.0, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_center(
+                                mask.id(),
+                                *original_center,
+                                *center,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_black_point, *black_point, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_black_point(
+                                mask.id(),
+                                *original_black_point,
+                                *black_point,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(
+                            *original_out_of_bounds_value,
+                            *out_of_bounds_value,
+                            ONE_IN_TEN_THOUSAND,
+                        )
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_out_of_bounds_value(
+                                mask.id(),
+                                *original_out_of_bounds_value,
+                                *out_of_bounds_value,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_rotation, *rotation, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_rotation(
+                                mask.id(),
+                                *original_rotation,
+                                *rotation,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_size, *size, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_size(
+                                mask.id(),
+                                *original_size,
+                                *size,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_white_point, *white_point, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_white_point(
+                                mask.id(),
+                                *original_white_point,
+                                *white_point,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::MaskSource::Bitmap { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Circle {
+                    center: original_center,
+                    falloff_radius: original_falloff_radius,
+                    irregularity: original_irregularity,
+                    radius: original_radius,
+                    rotation: original_rotation,
+                    smoothness: original_smoothness,
+                    ..
+                },
+                &mut MaskSourceUi::Circle {
+                    ref center,
+                    ref falloff_radius,
+                    ref irregularity,
+                    ref radius,
+                    ref rotation,
+                    ref smoothness,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(original_center.distance(*center), 0.0, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_center(
+                                mask.id(),
+                                *original_center,
+                                *center,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(
+                            *original_falloff_radius,
+                            *falloff_radius,
+                            ONE_IN_TEN_THOUSAND,
+                        )
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_falloff_radius(
+                                mask.id(),
+                                *original_falloff_radius,
+                                *falloff_radius,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_irregularity, *irregularity, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_irregularity(
+                                mask.id(),
+                                *original_irregularity,
+                                *irregularity,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_radius, *radius, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_radius(
+                                mask.id(),
+                                *original_radius,
+                                *radius,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_rotation, *rotation, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_rotation(
+                                mask.id(),
+                                *original_rotation,
+                                *rotation,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_smoothness, *smoothness, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_smoothness(
+                                mask.id(),
+                                *original_smoothness,
+                                *smoothness,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::MaskSource::Circle { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Gradient {
+                    curve: original_curve,
+                    end: original_end,
+                    smoothness: original_smoothness,
+                    start: original_start,
+                    ..
+                },
+                &mut MaskSourceUi::Gradient {
+                    ref curve,
+                    ref end,
+                    ref smoothness,
+                    ref start,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished() && original_curve != curve {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_curve(
+                                mask.id(),
+                                original_curve.clone(),
+                                curve.clone(),
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(original_end.distance(*end), 0.0, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_end(
+                                mask.id(),
+                                *original_end,
+                                *end,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_smoothness, *smoothness, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_smoothness(
+                                mask.id(),
+                                *original_smoothness,
+                                *smoothness,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(original_start.distance(*start), 0.0, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_start(
+                                mask.id(),
+                                *original_start,
+                                *start,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::MaskSource::Gradient { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Height {
+                    falloff: original_falloff,
+                    max: original_max,
+                    min: original_min,
+                },
+                &mut MaskSourceUi::Height {
+                    ref falloff,
+                    ref max,
+                    ref min,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(*original_falloff, *falloff, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_falloff(
+                                mask.id(),
+                                *original_falloff,
+                                *falloff,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && !approx_eq(*original_max, *max, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_max(
+                                mask.id(),
+                                *original_max,
+                                *max,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && !approx_eq(*original_min, *min, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_min(
+                                mask.id(),
+                                *original_min,
+                                *min,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::MaskSource::Height { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Noise {
+                    contrast: original_contrast,
+                    octaves: original_octaves,
+                    scale: original_scale,
+                    seed: original_seed,
+                    threshold: original_threshold,
+                    ..
+                },
+                &mut MaskSourceUi::Noise {
+                    ref contrast,
+                    ref octaves,
+                    ref scale,
+                    ref seed,
+                    ref threshold,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(*original_contrast, *contrast, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_contrast(
+                                mask.id(),
+                                *original_contrast,
+                                *contrast,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && original_octaves != octaves {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_octaves(
+                                mask.id(),
+                                *original_octaves,
+                                *octaves,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_scale, *scale, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_scale(
+                                mask.id(),
+                                *original_scale,
+                                *scale,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() && original_seed != seed {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_seed(
+                                mask.id(),
+                                *original_seed,
+                                *seed,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_threshold, *threshold, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_threshold(
+                                mask.id(),
+                                *original_threshold,
+                                *threshold,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::MaskSource::Noise { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Polygon {
+                    smoothness: original_smoothness,
+                    vertices: original_vertices,
+                },
+                &mut MaskSourceUi::Polygon {
+                    ref smoothness,
+                    ref mut timer,
+                    ref vertices,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(*original_smoothness, *smoothness, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_smoothness(
+                                mask.id(),
+                                *original_smoothness,
+                                *smoothness,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() {
+                        push_mask_vertex_update_actions(
+                            &mut commands,
+                            mask.id(),
+                            original_vertices,
+                            vertices,
+                        );
+                    }
+                }
+            }
+            (layer::MaskSource::Polygon { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Slope {
+                    falloff: original_falloff,
+                    max_angle: original_max_angle,
+                    min_angle: original_min_angle,
+                    ..
+                },
+                &mut MaskSourceUi::Slope {
+                    ref falloff,
+                    ref max_angle,
+                    ref min_angle,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(*original_falloff, *falloff, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_falloff(
+                                mask.id(),
+                                *original_falloff,
+                                *falloff,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_max_angle, *max_angle, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_max_angle(
+                                mask.id(),
+                                *original_max_angle,
+                                *max_angle,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(*original_min_angle, *min_angle, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_min_angle(
+                                mask.id(),
+                                *original_min_angle,
+                                *min_angle,
+                            ),
+                        ));
+                    }
+                }
+            }
+            (layer::MaskSource::Slope { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Spline {
+                    smoothness: original_smoothness,
+                    vertices: original_vertices,
+                },
+                &mut MaskSourceUi::Spline {
+                    ref smoothness,
+                    ref mut timer,
+                    ref vertices,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+                    if timer.just_finished()
+                        && !approx_eq(*original_smoothness, *smoothness, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_smoothness(
+                                mask.id(),
+                                *original_smoothness,
+                                *smoothness,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished() {
+                        push_mask_vertex_update_actions(
+                            &mut commands,
+                            mask.id(),
+                            original_vertices,
+                            vertices,
+                        );
+                    }
+                }
+            }
+            (layer::MaskSource::Spline { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Square {
+                    center: original_center,
+                    falloff_radius: original_falloff_radius,
+                    irregularity: original_irregularity,
+                    rotation: original_rotation,
+                    size: original_size,
+                    smoothness: original_smoothness,
+                    ..
+                },
+                &mut MaskSourceUi::Square {
+                    ref center,
+                    ref falloff_radius,
+                    ref irregularity,
+                    ref rotation,
+                    ref size,
+                    ref smoothness,
+                    ref mut timer,
+                },
+            ) => {
+                if !timer.is_finished() {
+                    timer.tick(time.delta());
+
+                    if timer.just_finished()
+                        && !approx_eq(original_center.distance(*center), 0.0, ONE_IN_TEN_THOUSAND)
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_center(
+                                mask.id(),
+                                *original_center,
+                                *center,
+                            ),
+                        ));
+                    }
+                    if timer.just_finished()
+                        && !approx_eq(
+                            *original_falloff_radius,
+                            *falloff_radius,
+                            ONE_IN_TEN_THOUSAND,
+                        )
+                    {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_falloff_radius(
+                                mask.id(),
+                                *original_falloff_radius,
+                                *falloff_radius,
                             ),
                         ));
                     }
@@ -562,15 +1773,161 @@ fn reset_height_map_ui_system(
     mut layers: Query<(&layer::HeightMap, &mut HeightMapUi), Changed<layer::HeightMap>>,
 ) {
     for (height_map, mut height_map_ui) in layers.iter_mut() {
-        match height_map {
-            layer::HeightMap::Constant(original_height) => {
-                let HeightMapUi::Constant {
-                    ref mut height,
-                    ref mut timer,
-                } = *height_map_ui;
+        match (height_map, height_map_ui.as_mut()) {
+            (
+                layer::HeightMap::Bitmap {
+                    black_height: original_black_height,
+                    position: original_position,
+                    rotation: original_rotation,
+                    size: original_size,
+                    white_height: original_white_height,
+                    ..
+                },
+                HeightMapUi::Bitmap {
+                    black_height,
+                    position,
+                    rotation,
+                    size,
+                    timer,
+                    white_height,
+                },
+            ) => {
+                *black_height = *original_black_height;
+                *position = *original_position;
+                *rotation = *original_rotation;
+                *size = *original_size;
+                *white_height = *original_white_height;
+                timer.pause();
+            }
+            (layer::HeightMap::Bitmap { .. }, _) => unreachable!(),
+            (
+                layer::HeightMap::Constant(original_height),
+                HeightMapUi::Constant { height, timer },
+            ) => {
                 *height = *original_height;
                 timer.pause();
             }
+            (layer::HeightMap::Constant(_), _) => unreachable!(),
+            (
+                layer::HeightMap::HydraulicErosion {
+                    deposition_rate: original_deposition_rate,
+                    erosion_rate: original_erosion_rate,
+                    iterations: original_iterations,
+                    rain_amount: original_rain_amount,
+                    sediment_capacity: original_sediment_capacity,
+                    seed: original_seed,
+                },
+                HeightMapUi::HydraulicErosion {
+                    deposition_rate,
+                    erosion_rate,
+                    iterations,
+                    rain_amount,
+                    sediment_capacity,
+                    seed,
+                    timer,
+                },
+            ) => {
+                *deposition_rate = *original_deposition_rate;
+                *erosion_rate = *original_erosion_rate;
+                *iterations = *original_iterations;
+                *rain_amount = *original_rain_amount;
+                *sediment_capacity = *original_sediment_capacity;
+                *seed = *original_seed;
+                timer.pause();
+            }
+            (layer::HeightMap::HydraulicErosion { .. }, _) => unreachable!(),
+            (
+                layer::HeightMap::Noise {
+                    amplitude: original_amplitude,
+                    frequency: original_frequency,
+                    gain: original_gain,
+                    lacunarity: original_lacunarity,
+                    octaves: original_octaves,
+                    offset: original_offset,
+                    seed: original_seed,
+                },
+                HeightMapUi::Noise {
+                    amplitude,
+                    frequency,
+                    gain,
+                    lacunarity,
+                    octaves,
+                    offset,
+                    seed,
+                    timer,
+                },
+            ) => {
+                *amplitude = *original_amplitude;
+                *frequency = *original_frequency;
+                *gain = *original_gain;
+                *lacunarity = *original_lacunarity;
+                *octaves = *original_octaves;
+                *offset = *original_offset;
+                *seed = *original_seed;
+                timer.pause();
+            }
+            (layer::HeightMap::Noise { .. }, _) => unreachable!(),
+            (
+                layer::HeightMap::Summit {
+                    base_radius: original_base_radius,
+                    peak_height: original_peak_height,
+                    position: original_position,
+                    ridge_frequency: original_ridge_frequency,
+                    ridge_strength: original_ridge_strength,
+                    seed: original_seed,
+                    ..
+                },
+                HeightMapUi::Summit {
+                    base_radius,
+                    peak_height,
+                    position,
+                    ridge_frequency,
+                    ridge_strength,
+                    seed,
+                    timer,
+                },
+            ) => {
+                *base_radius = *original_base_radius;
+                *peak_height = *original_peak_height;
+                *position = *original_position;
+                *ridge_frequency = *original_ridge_frequency;
+                *ridge_strength = *original_ridge_strength;
+                *seed = *original_seed;
+                timer.pause();
+            }
+            (layer::HeightMap::Summit { .. }, _) => unreachable!(),
+            (
+                layer::HeightMap::ThermalErosion {
+                    angle: original_angle,
+                    iterations: original_iterations,
+                    strength: original_strength,
+                },
+                HeightMapUi::ThermalErosion {
+                    angle,
+                    iterations,
+                    strength,
+                    timer,
+                },
+            ) => {
+                *angle = *original_angle;
+                *iterations = *original_iterations;
+                *strength = *original_strength;
+                timer.pause();
+            }
+            (layer::HeightMap::ThermalErosion { .. }, _) => unreachable!(),
+        }
+    }
+}
+
+/// Update LayerUi based on changes to Layer.
+///
+/// This gets triggered when undo/redo changes [Layer](layer::Layer).
+fn reset_layer_ui_system(mut layers: Query<(&layer::Layer, &mut LayerUi), Changed<layer::Layer>>) {
+    for (layer, mut layer_ui) in layers.iter_mut() {
+        if layer.opacity != layer_ui.synced_opacity {
+            layer_ui.opacity = layer.opacity;
+            layer_ui.synced_opacity = layer.opacity;
+            layer_ui.timer.pause();
         }
     }
 }
@@ -583,6 +1940,37 @@ fn reset_mask_ui_system(
 ) {
     for (mask_source, mut mask_source_ui) in mask_query.iter_mut() {
         match (mask_source, mask_source_ui.as_mut()) {
+            (layer::MaskSource::Biome { .. }, MaskSourceUi::Biome) => (),
+            (layer::MaskSource::Biome { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Bitmap {
+                    black_point: original_black_point,
+                    center: original_center,
+                    out_of_bounds_value: original_out_of_bounds_value,
+                    rotation: original_rotation,
+                    size: original_size,
+                    white_point: original_white_point,
+                    ..
+                },
+                MaskSourceUi::Bitmap {
+                    black_point,
+                    center,
+                    out_of_bounds_value,
+                    rotation,
+                    size,
+                    timer,
+                    white_point,
+                },
+            ) => {
+                *black_point = *original_black_point;
+                *center = *original_center;
+                *out_of_bounds_value = *original_out_of_bounds_value;
+                *rotation = *original_rotation;
+                *size = *original_size;
+                *white_point = *original_white_point;
+                timer.pause();
+            }
+            (layer::MaskSource::Bitmap { .. }, _) => unreachable!(),
             (
                 layer::MaskSource::Circle {
                     center: original_center,
@@ -612,6 +2000,126 @@ fn reset_mask_ui_system(
                 timer.pause();
             }
             (layer::MaskSource::Circle { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Gradient {
+                    curve: original_curve,
+                    end: original_end,
+                    smoothness: original_smoothness,
+                    start: original_start,
+                    ..
+                },
+                MaskSourceUi::Gradient {
+                    curve,
+                    end,
+                    smoothness,
+                    start,
+                    timer,
+                },
+            ) => {
+                *curve = original_curve.clone();
+                *end = *original_end;
+                *smoothness = *original_smoothness;
+                *start = *original_start;
+                timer.pause();
+            }
+            (layer::MaskSource::Gradient { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Height {
+                    falloff: original_falloff,
+                    max: original_max,
+                    min: original_min,
+                },
+                MaskSourceUi::Height {
+                    falloff,
+                    max,
+                    min,
+                    timer,
+                },
+            ) => {
+                *falloff = *original_falloff;
+                *max = *original_max;
+                *min = *original_min;
+                timer.pause();
+            }
+            (layer::MaskSource::Height { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Noise {
+                    contrast: original_contrast,
+                    octaves: original_octaves,
+                    scale: original_scale,
+                    seed: original_seed,
+                    threshold: original_threshold,
+                    ..
+                },
+                MaskSourceUi::Noise {
+                    contrast,
+                    octaves,
+                    scale,
+                    seed,
+                    threshold,
+                    timer,
+                },
+            ) => {
+                *contrast = *original_contrast;
+                *octaves = *original_octaves;
+                *scale = *original_scale;
+                *seed = *original_seed;
+                *threshold = *original_threshold;
+                timer.pause();
+            }
+            (layer::MaskSource::Noise { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Polygon {
+                    smoothness: original_smoothness,
+                    vertices: original_vertices,
+                },
+                MaskSourceUi::Polygon {
+                    smoothness,
+                    timer,
+                    vertices,
+                },
+            ) => {
+                *smoothness = *original_smoothness;
+                *vertices = original_vertices.clone();
+                timer.pause();
+            }
+            (layer::MaskSource::Polygon { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Slope {
+                    falloff: original_falloff,
+                    max_angle: original_max_angle,
+                    min_angle: original_min_angle,
+                    ..
+                },
+                MaskSourceUi::Slope {
+                    falloff,
+                    max_angle,
+                    min_angle,
+                    timer,
+                },
+            ) => {
+                *falloff = *original_falloff;
+                *max_angle = *original_max_angle;
+                *min_angle = *original_min_angle;
+                timer.pause();
+            }
+            (layer::MaskSource::Slope { .. }, _) => unreachable!(),
+            (
+                layer::MaskSource::Spline {
+                    smoothness: original_smoothness,
+                    vertices: original_vertices,
+                },
+                MaskSourceUi::Spline {
+                    smoothness,
+                    timer,
+                    vertices,
+                },
+            ) => {
+                *smoothness = *original_smoothness;
+                *vertices = original_vertices.clone();
+                timer.pause();
+            }
+            (layer::MaskSource::Spline { .. }, _) => unreachable!(),
             (
                 layer::MaskSource::Square {
                     center: original_center,
@@ -648,6 +2156,7 @@ fn reset_mask_ui_system(
 // LIB
 
 fn draw_ui_for_layer_common_bottom(
+    biome_table: &biome::BiomeTable,
     commands: &mut Commands,
     masks_query: &mut Masks,
     layer_query_item: &LayerQueryItem,
@@ -681,6 +2190,32 @@ fn draw_ui_for_layer_common_bottom(
             .first()
             .map(|(_, m)| m.mask.id().clone());
 
+        if ui.button("Add biome mask").clicked() {
+            let mask_bundle: layer::MaskBundle = layer::MaskBundle {
+                mask: layer::Mask::default(),
+                mask_source: layer::MaskSource::biome(),
+            };
+            let layer_id: LayerId = layer_query_item.layer.id();
+            commands.queue(undo::PushAction::from(layer::CreateMaskAction::new(
+                mask_bundle,
+                layer_id,
+                topmost_mask_id,
+            )));
+        }
+
+        if ui.button("Add bitmap mask").clicked() {
+            let mask_bundle: layer::MaskBundle = layer::MaskBundle {
+                mask: layer::Mask::default(),
+                mask_source: layer::MaskSource::bitmap(),
+            };
+            let layer_id: LayerId = layer_query_item.layer.id();
+            commands.queue(undo::PushAction::from(layer::CreateMaskAction::new(
+                mask_bundle,
+                layer_id,
+                topmost_mask_id,
+            )));
+        }
+
         if ui.button("Add circle mask").clicked() {
             let mask_bundle: layer::MaskBundle = layer::MaskBundle {
                 mask: layer::Mask::default(),
@@ -694,6 +2229,84 @@ fn draw_ui_for_layer_common_bottom(
             )));
         }
 
+        if ui.button("Add gradient mask").clicked() {
+            let mask_bundle: layer::MaskBundle = layer::MaskBundle {
+                mask: layer::Mask::default(),
+                mask_source: layer::MaskSource::gradient(),
+            };
+            let layer_id: LayerId = layer_query_item.layer.id();
+            commands.queue(undo::PushAction::from(layer::CreateMaskAction::new(
+                mask_bundle,
+                layer_id,
+                topmost_mask_id,
+            )));
+        }
+
+        if ui.button("Add height mask").clicked() {
+            let mask_bundle: layer::MaskBundle = layer::MaskBundle {
+                mask: layer::Mask::default(),
+                mask_source: layer::MaskSource::height(),
+            };
+            let layer_id: LayerId = layer_query_item.layer.id();
+            commands.queue(undo::PushAction::from(layer::CreateMaskAction::new(
+                mask_bundle,
+                layer_id,
+                topmost_mask_id,
+            )));
+        }
+
+        if ui.button("Add noise mask").clicked() {
+            let mask_bundle: layer::MaskBundle = layer::MaskBundle {
+                mask: layer::Mask::default(),
+                mask_source: layer::MaskSource::noise(),
+            };
+            let layer_id: LayerId = layer_query_item.layer.id();
+            commands.queue(undo::PushAction::from(layer::CreateMaskAction::new(
+                mask_bundle,
+                layer_id,
+                topmost_mask_id,
+            )));
+        }
+
+        if ui.button("Add polygon mask").clicked() {
+            let mask_bundle: layer::MaskBundle = layer::MaskBundle {
+                mask: layer::Mask::default(),
+                mask_source: layer::MaskSource::polygon(),
+            };
+            let layer_id: LayerId = layer_query_item.layer.id();
+            commands.queue(undo::PushAction::from(layer::CreateMaskAction::new(
+                mask_bundle,
+                layer_id,
+                topmost_mask_id,
+            )));
+        }
+
+        if ui.button("Add slope mask").clicked() {
+            let mask_bundle: layer::MaskBundle = layer::MaskBundle {
+                mask: layer::Mask::default(),
+                mask_source: layer::MaskSource::slope(),
+            };
+            let layer_id: LayerId = layer_query_item.layer.id();
+            commands.queue(undo::PushAction::from(layer::CreateMaskAction::new(
+                mask_bundle,
+                layer_id,
+                topmost_mask_id,
+            )));
+        }
+
+        if ui.button("Add spline mask").clicked() {
+            let mask_bundle: layer::MaskBundle = layer::MaskBundle {
+                mask: layer::Mask::default(),
+                mask_source: layer::MaskSource::spline(),
+            };
+            let layer_id: LayerId = layer_query_item.layer.id();
+            commands.queue(undo::PushAction::from(layer::CreateMaskAction::new(
+                mask_bundle,
+                layer_id,
+                topmost_mask_id,
+            )));
+        }
+
         if ui.button("Add square mask").clicked() {
             let mask_bundle: layer::MaskBundle = layer::MaskBundle {
                 mask: layer::Mask::default(),
@@ -709,6 +2322,7 @@ fn draw_ui_for_layer_common_bottom(
 
         for (previous_mask_id, m) in masks_in_reverse_order.iter_mut() {
             draw_ui_for_mask(
+                biome_table,
                 commands,
                 layer_query_item.layer.id(),
                 m,
@@ -719,6 +2333,79 @@ fn draw_ui_for_layer_common_bottom(
     });
 }
 
+fn draw_ui_for_layer_channels(
+    commands: &mut Commands,
+    ui: &mut egui::Ui,
+    layer_query_item: &mut LayerQueryItem,
+) {
+    const CHANNEL_NAME_CHAR_LIMIT: usize = 20;
+    let layer_id: LayerId = layer_query_item.layer.id();
+    let old_channels: &Vec<layer::LayerChannel> = &layer_query_item.layer.channels;
+    let mut channels: Vec<layer::LayerChannel> = old_channels.clone();
+    let frame = egui::containers::Frame::group(ui.style());
+    frame.show(ui, |ui| {
+        ui.heading("Channels");
+        ui.horizontal(|ui| {
+            for channel in [Channel::Moisture, Channel::Temperature] {
+                ui.add_enabled_ui(!channels.iter().any(|c| c.channel == channel), |ui| {
+                    if ui.button(format!("Add {}", channel.to_string())).clicked() {
+                        channels.push(layer::LayerChannel::new(channel));
+                    }
+                });
+            }
+        });
+        ui.horizontal(|ui| {
+            let custom_channel: &mut String = &mut layer_query_item.layer_ui.custom_channel;
+            ui.add(
+                egui::widgets::TextEdit::singleline(custom_channel)
+                    .char_limit(CHANNEL_NAME_CHAR_LIMIT)
+                    .hint_text("Channel name"),
+            );
+            let channel = Channel::Custom(custom_channel.trim().to_string());
+            ui.add_enabled_ui(
+                !custom_channel.trim().is_empty() && !channels.iter().any(|c| c.channel == channel),
+                |ui| {
+                    if ui.button("Add channel").clicked() {
+                        channels.push(layer::LayerChannel::new(channel));
+                        custom_channel.clear();
+                    }
+                },
+            );
+        });
+        let mut removed: Option<usize> = None;
+        for (idx, layer_channel) in channels.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(layer_channel.channel.to_string());
+                egui::ComboBox::from_id_salt(format!(
+                    "layer-channel-blend-mode-{}-{}",
+                    layer_id, idx
+                ))
+                .selected_text(layer_channel.blend_mode.to_string())
+                .show_ui(ui, |ui| {
+                    for blend_mode in layer::LayerBlendMode::ITEMS.into_iter() {
+                        ui.selectable_value(
+                            &mut layer_channel.blend_mode,
+                            blend_mode,
+                            blend_mode.to_string(),
+                        );
+                    }
+                });
+                if ui.button("Remove").clicked() {
+                    removed = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = removed {
+            channels.remove(idx);
+        }
+    });
+    if channels != *old_channels {
+        commands.queue(undo::PushAction::from(
+            layer::UpdateLayerChannelsAction::new(layer_id, old_channels.clone(), channels),
+        ));
+    }
+}
+
 fn draw_ui_for_layer_common_top(
     commands: &mut Commands,
     ui: &mut egui::Ui,
@@ -774,6 +2461,64 @@ fn draw_ui_for_layer_common_top(
                     ));
                 }
             }
+            {
+                let mut layer_write_height: bool = layer_query_item.layer.write_height;
+                if ui
+                    .toggle_value(&mut layer_write_height, "Height")
+                    .on_hover_text("Write height, disable to write only the channels.")
+                    .changed()
+                    && layer_write_height != layer_query_item.layer.write_height
+                {
+                    commands.queue(undo::PushAction::from(
+                        layer::UpdateLayerAction::toggle_write_height(layer_query_item.layer),
+                    ));
+                }
+            }
+            ui.separator();
+            {
+                let LayerUi {
+                    ref mut opacity,
+                    ref mut timer,
+                    ..
+                } = *layer_query_item.layer_ui;
+                ui.label("Opacity:");
+                if let Some(new_opacity) = draw_ui_editable_f32(
+                    Some(ZERO_TO_ONE),
+                    Some(ZERO_TO_ONE_INCREMENT),
+                    ui,
+                    *opacity,
+                ) {
+                    *opacity = new_opacity;
+                    timer.unpause();
+                    timer.reset();
+                }
+            }
+            {
+                let mut blend_mode_edited = layer_query_item.layer.blend_mode;
+                egui::ComboBox::from_id_salt(format!(
+                    "layer-blend-mode-{}",
+                    layer_query_item.layer.id()
+                ))
+                .selected_text(blend_mode_edited.to_string())
+                .show_ui(ui, |ui| {
+                    for blend_mode in layer::LayerBlendMode::ITEMS.into_iter() {
+                        ui.selectable_value(
+                            &mut blend_mode_edited,
+                            blend_mode,
+                            blend_mode.to_string(),
+                        );
+                    }
+                });
+                if blend_mode_edited != layer_query_item.layer.blend_mode {
+                    commands.queue(undo::PushAction::from(
+                        layer::ChangeLayerBlendModeAction::new(
+                            layer_query_item.layer.id(),
+                            layer_query_item.layer.blend_mode,
+                            blend_mode_edited,
+                        ),
+                    ));
+                }
+            }
             ui.separator();
             if ui.button("Delete").clicked() {
                 let masks: Vec<layer::MaskBundle> = masks_query
@@ -791,6 +2536,157 @@ fn draw_ui_for_layer_common_top(
     };
 }
 
+fn draw_ui_for_bitmap_layer(
+    commands: &mut Commands,
+    ui: &mut egui::Ui,
+    layer_query_item: &mut LayerQueryItem,
+) {
+    let layer_id: LayerId = layer_query_item.layer.id();
+    let layer::HeightMap::Bitmap {
+        image,
+        interpolation: original_interpolation,
+        out_of_bounds: original_out_of_bounds,
+        ..
+    } = layer_query_item.height_map
+    else {
+        unreachable!()
+    };
+    let HeightMapUi::Bitmap {
+        black_height,
+        position,
+        rotation,
+        size,
+        timer,
+        white_height,
+    } = layer_query_item.height_map_ui.as_mut()
+    else {
+        unreachable!()
+    };
+    ui.horizontal(|ui| {
+        ui.label("Image:");
+        if image.is_empty() {
+            ui.label("(None)");
+        } else {
+            let UVec2 { x: w, y: h } = image.size();
+            ui.label(format!(
+                "{} ({}×{})",
+                image.file_name().unwrap_or_default(),
+                w,
+                h
+            ));
+        }
+        if ui.button("Load...").clicked() {
+            commands.spawn((
+                Name::new("Import Bitmap Dialog"),
+                ImportBitmapFileDialog::new(ImportBitmapTarget::HeightMap(layer_id)),
+                DespawnOnExit(UiState::ShowingImportBitmapFileDialog),
+            ));
+            commands.set_state(UiState::ShowingImportBitmapFileDialog);
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Black Height:");
+        if let Some(new_black_height) =
+            draw_ui_editable_f32(Some(layer::HEIGHT_RANGE), None, ui, *black_height)
+        {
+            *black_height = new_black_height;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("White Height:");
+        if let Some(new_white_height) =
+            draw_ui_editable_f32(Some(layer::HEIGHT_RANGE), None, ui, *white_height)
+        {
+            *white_height = new_white_height;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Position:");
+        if let Some(new_x) = draw_ui_editable_f32(None, None, ui, position.x) {
+            position.x = new_x;
+            timer.unpause();
+            timer.reset();
+        }
+        if let Some(new_y) = draw_ui_editable_f32(None, None, ui, position.y) {
+            position.y = new_y;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Size:");
+        if let Some(new_x) = draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, size.x)
+        {
+            size.x = new_x;
+            timer.unpause();
+            timer.reset();
+        }
+        if let Some(new_y) = draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, size.y)
+        {
+            size.y = new_y;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Rotation:");
+        if let Some(new_rotation) = draw_ui_editable_f32(
+            Some(ZERO_TO_ONE),
+            Some(ZERO_TO_ONE_INCREMENT),
+            ui,
+            *rotation,
+        ) {
+            *rotation = new_rotation;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Interpolation:");
+        let mut interpolation_edited = *original_interpolation;
+        for interpolation in layer::BitmapInterpolation::ITEMS.into_iter() {
+            ui.selectable_value(
+                &mut interpolation_edited,
+                interpolation,
+                interpolation.to_string(),
+            );
+        }
+        if interpolation_edited != *original_interpolation {
+            commands.queue(undo::PushAction::from(
+                layer::HeightMapBitmapUpdateAction::change_interpolation(
+                    layer_id,
+                    *original_interpolation,
+                    interpolation_edited,
+                ),
+            ));
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Out of Bounds:");
+        let mut out_of_bounds_edited = *original_out_of_bounds;
+        for out_of_bounds in layer::BitmapOutOfBounds::ITEMS.into_iter() {
+            ui.selectable_value(
+                &mut out_of_bounds_edited,
+                out_of_bounds,
+                out_of_bounds.to_string(),
+            );
+        }
+        if out_of_bounds_edited != *original_out_of_bounds {
+            commands.queue(undo::PushAction::from(
+                layer::HeightMapBitmapUpdateAction::change_out_of_bounds(
+                    layer_id,
+                    *original_out_of_bounds,
+                    out_of_bounds_edited,
+                ),
+            ));
+        }
+    });
+}
+
 fn draw_ui_for_constant_layer(ui: &mut egui::Ui, height_map_ui: &mut HeightMapUi) {
     ui.horizontal(|ui| {
         ui.label("Height:");
@@ -809,12 +2705,507 @@ fn draw_ui_for_constant_layer(ui: &mut egui::Ui, height_map_ui: &mut HeightMapUi
                     },
                 );
             }
+            _ => unreachable!(),
+        }
+    });
+}
+
+fn draw_ui_for_hydraulic_erosion_layer(ui: &mut egui::Ui, height_map_ui: &mut HeightMapUi) {
+    let HeightMapUi::HydraulicErosion {
+        deposition_rate,
+        erosion_rate,
+        iterations,
+        rain_amount,
+        sediment_capacity,
+        seed,
+        timer,
+    } = height_map_ui
+    else {
+        unreachable!()
+    };
+    ui.horizontal(|ui| {
+        ui.label("Iterations:");
+        if let Some(new_iterations) =
+            draw_ui_editable_u32(Some(HYDRAULIC_EROSION_ITERATIONS_RANGE), ui, *iterations)
+        {
+            *iterations = new_iterations;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Rain Amount:");
+        if let Some(new_rain_amount) =
+            draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, *rain_amount)
+        {
+            *rain_amount = new_rain_amount;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Sediment Capacity:");
+        if let Some(new_sediment_capacity) = draw_ui_editable_f32(
+            Some(ZERO_TO_POSITIVE_INFINITY),
+            None,
+            ui,
+            *sediment_capacity,
+        ) {
+            *sediment_capacity = new_sediment_capacity;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Erosion Rate:");
+        if let Some(new_erosion_rate) = draw_ui_editable_f32(
+            Some(ZERO_TO_ONE),
+            Some(ZERO_TO_ONE_INCREMENT),
+            ui,
+            *erosion_rate,
+        ) {
+            *erosion_rate = new_erosion_rate;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Deposition Rate:");
+        if let Some(new_deposition_rate) = draw_ui_editable_f32(
+            Some(ZERO_TO_ONE),
+            Some(ZERO_TO_ONE_INCREMENT),
+            ui,
+            *deposition_rate,
+        ) {
+            *deposition_rate = new_deposition_rate;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Seed:");
+        if let Some(new_seed) = draw_ui_editable_u32(None, ui, *seed) {
+            *seed = new_seed;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+}
+
+/// Draw an editor for `curve`, returns whether the curve is edited.
+///
+/// Points can be dragged, double click adds a new point and right click
+/// removes a point.
+fn draw_ui_for_mask_curve(ui: &mut egui::Ui, curve: &mut layer::MaskCurve) -> bool {
+    let (response, painter) = ui.allocate_painter(
+        egui::Vec2::splat(MASK_CURVE_EDITOR_SIZE),
+        egui::Sense::click(),
+    );
+    let rect: egui::Rect = response.rect;
+    let to_screen = |point: Vec2| {
+        egui::pos2(
+            rect.left() + point.x * rect.width(),
+            rect.bottom() - point.y * rect.height(),
+        )
+    };
+    let from_screen = |pos: egui::Pos2| {
+        Vec2::new(
+            (pos.x - rect.left()) / rect.width(),
+            (rect.bottom() - pos.y) / rect.height(),
+        )
+        .clamp(Vec2::ZERO, Vec2::ONE)
+    };
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    painter.add(egui::Shape::line(
+        curve
+            .points()
+            .iter()
+            .map(|point| to_screen(*point))
+            .collect(),
+        visuals.widgets.inactive.fg_stroke,
+    ));
+
+    let mut points: Vec<Vec2> = curve.points().to_vec();
+    let mut is_edited: bool = false;
+    let mut removed: Option<usize> = None;
+    for idx in 0..points.len() {
+        let center: egui::Pos2 = to_screen(points[idx]);
+        let point_response = ui.interact(
+            egui::Rect::from_center_size(center, egui::Vec2::splat(MASK_CURVE_POINT_RADIUS * 2.0)),
+            response.id.with(idx),
+            egui::Sense::click_and_drag(),
+        );
+        if point_response.dragged() {
+            if let Some(pos) = point_response.interact_pointer_pos() {
+                // Keep the points in order while dragging.
+                let min_x: f32 = if idx > 0 { points[idx - 1].x } else { 0.0 };
+                let max_x: f32 = points.get(idx + 1).map_or(1.0, |point| point.x);
+                let mut point: Vec2 = from_screen(pos);
+                point.x = point.x.clamp(min_x, max_x);
+                points[idx] = point;
+                is_edited = true;
+            }
+        }
+        if point_response.secondary_clicked() && points.len() > 2 {
+            removed = Some(idx);
+        }
+        let stroke = visuals.widgets.style(&point_response).fg_stroke;
+        painter.circle_filled(center, MASK_CURVE_POINT_RADIUS, stroke.color);
+    }
+    if let Some(idx) = removed {
+        points.remove(idx);
+        is_edited = true;
+    }
+    if response.double_clicked() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            points.push(from_screen(pos));
+            is_edited = true;
+        }
+    }
+    if is_edited {
+        *curve = layer::MaskCurve::new(points);
+    }
+    is_edited
+}
+
+fn draw_ui_for_mask_vertices(
+    commands: &mut Commands,
+    mask_id: MaskId,
+    ui: &mut egui::Ui,
+    vertices: &mut [layer::MaskVertex],
+    timer: &mut Timer,
+) {
+    let count: usize = vertices.len();
+    for index in 0..count {
+        let next: layer::MaskVertex = vertices[(index + 1) % count];
+        let vertex: &mut layer::MaskVertex = &mut vertices[index];
+        ui.horizontal(|ui| {
+            ui.label(format!("Vertex {}:", index + 1));
+            if let Some(new_x) = draw_ui_editable_f32(None, None, ui, vertex.position.x) {
+                vertex.position.x = new_x;
+                timer.unpause();
+                timer.reset();
+            }
+            if let Some(new_y) = draw_ui_editable_f32(None, None, ui, vertex.position.y) {
+                vertex.position.y = new_y;
+                timer.unpause();
+                timer.reset();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Falloff:");
+            if let Some(new_falloff) =
+                draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, vertex.falloff)
+            {
+                vertex.falloff = new_falloff;
+                timer.unpause();
+                timer.reset();
+            }
+            if ui.button("Insert").clicked() {
+                // New vertex goes halfway to the next one.
+                let new_vertex = layer::MaskVertex::new(
+                    vertex.position.midpoint(next.position),
+                    (vertex.falloff + next.falloff) * 0.5,
+                );
+                commands.queue(undo::PushAction::from(
+                    layer::UpdateMaskSourceAction::insert_vertex(mask_id, index + 1, new_vertex),
+                ));
+            }
+            // Outlines need at least 3 vertices.
+            if ui
+                .add_enabled(count > 3, egui::Button::new("Delete"))
+                .clicked()
+            {
+                commands.queue(undo::PushAction::from(
+                    layer::UpdateMaskSourceAction::delete_vertex(mask_id, index, *vertex),
+                ));
+            }
+        });
+    }
+}
+
+fn draw_ui_for_noise_layer(ui: &mut egui::Ui, height_map_ui: &mut HeightMapUi) {
+    let HeightMapUi::Noise {
+        amplitude,
+        frequency,
+        gain,
+        lacunarity,
+        octaves,
+        offset,
+        seed,
+        timer,
+    } = height_map_ui
+    else {
+        unreachable!()
+    };
+    ui.horizontal(|ui| {
+        ui.label("Offset:");
+        if let Some(new_offset) = draw_ui_editable_f32(Some(layer::HEIGHT_RANGE), None, ui, *offset)
+        {
+            *offset = new_offset;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Amplitude:");
+        if let Some(new_amplitude) =
+            draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, *amplitude)
+        {
+            *amplitude = new_amplitude;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Frequency:");
+        if let Some(new_frequency) = draw_ui_editable_f32(
+            Some(ZERO_TO_POSITIVE_INFINITY),
+            Some(ZERO_TO_ONE_INCREMENT),
+            ui,
+            *frequency,
+        ) {
+            *frequency = new_frequency;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Octaves:");
+        if let Some(new_octaves) =
+            draw_ui_editable_u32(Some(NOISE_OCTAVES_RANGE), ui, (*octaves).into())
+        {
+            *octaves = new_octaves.try_into().unwrap();
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Lacunarity:");
+        if let Some(new_lacunarity) =
+            draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, *lacunarity)
+        {
+            *lacunarity = new_lacunarity;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Gain:");
+        if let Some(new_gain) =
+            draw_ui_editable_f32(Some(ZERO_TO_ONE), Some(ZERO_TO_ONE_INCREMENT), ui, *gain)
+        {
+            *gain = new_gain;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Seed:");
+        if let Some(new_seed) = draw_ui_editable_u32(None, ui, *seed) {
+            *seed = new_seed;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+}
+
+/// Push an action for each vertex moved or with its falloff changed.
+fn push_mask_vertex_update_actions(
+    commands: &mut Commands,
+    mask_id: MaskId,
+    original_vertices: &[layer::MaskVertex],
+    vertices: &[layer::MaskVertex],
+) {
+    for (index, (original_vertex, vertex)) in
+        original_vertices.iter().zip(vertices.iter()).enumerate()
+    {
+        if !approx_eq(
+            original_vertex.position.distance(vertex.position),
+            0.0,
+            ONE_IN_TEN_THOUSAND,
+        ) {
+            commands.queue(undo::PushAction::from(
+                layer::UpdateMaskSourceAction::move_vertex(
+                    mask_id,
+                    index,
+                    original_vertex.position,
+                    vertex.position,
+                ),
+            ));
+        }
+        if !approx_eq(original_vertex.falloff, vertex.falloff, ONE_IN_TEN_THOUSAND) {
+            commands.queue(undo::PushAction::from(
+                layer::UpdateMaskSourceAction::update_vertex_falloff(
+                    mask_id,
+                    index,
+                    original_vertex.falloff,
+                    vertex.falloff,
+                ),
+            ));
+        }
+    }
+}
+
+fn draw_ui_for_summit_layer(
+    commands: &mut Commands,
+    ui: &mut egui::Ui,
+    layer_query_item: &mut LayerQueryItem,
+) {
+    let layer_id: LayerId = layer_query_item.layer.id();
+    let layer::HeightMap::Summit {
+        profile: original_profile,
+        ..
+    } = layer_query_item.height_map
+    else {
+        unreachable!()
+    };
+    let HeightMapUi::Summit {
+        base_radius,
+        peak_height,
+        position,
+        ridge_frequency,
+        ridge_strength,
+        seed,
+        timer,
+    } = layer_query_item.height_map_ui.as_mut()
+    else {
+        unreachable!()
+    };
+    ui.horizontal(|ui| {
+        ui.label("Position:");
+        if let Some(new_x) = draw_ui_editable_f32(None, None, ui, position.x) {
+            position.x = new_x;
+            timer.unpause();
+            timer.reset();
+        }
+        if let Some(new_y) = draw_ui_editable_f32(None, None, ui, position.y) {
+            position.y = new_y;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Peak Height:");
+        if let Some(new_peak_height) =
+            draw_ui_editable_f32(Some(layer::HEIGHT_RANGE), None, ui, *peak_height)
+        {
+            *peak_height = new_peak_height;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Base Radius:");
+        if let Some(new_base_radius) =
+            draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, *base_radius)
+        {
+            *base_radius = new_base_radius;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Profile:");
+        let mut profile_edited = *original_profile;
+        for profile in layer::SummitProfile::ITEMS.into_iter() {
+            ui.selectable_value(&mut profile_edited, profile, profile.to_string());
+        }
+        if profile_edited != *original_profile {
+            commands.queue(undo::PushAction::from(
+                layer::HeightMapSummitUpdateAction::change_profile(
+                    layer_id,
+                    *original_profile,
+                    profile_edited,
+                ),
+            ));
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Ridges:");
+        if let Some(new_ridge_strength) = draw_ui_editable_f32(
+            Some(ZERO_TO_ONE),
+            Some(ZERO_TO_ONE_INCREMENT),
+            ui,
+            *ridge_strength,
+        ) {
+            *ridge_strength = new_ridge_strength;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Ridge Frequency:");
+        if let Some(new_ridge_frequency) = draw_ui_editable_f32(
+            Some(ZERO_TO_POSITIVE_INFINITY),
+            Some(ZERO_TO_ONE_INCREMENT),
+            ui,
+            *ridge_frequency,
+        ) {
+            *ridge_frequency = new_ridge_frequency;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Seed:");
+        if let Some(new_seed) = draw_ui_editable_u32(None, ui, *seed) {
+            *seed = new_seed;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+}
+
+fn draw_ui_for_thermal_erosion_layer(ui: &mut egui::Ui, height_map_ui: &mut HeightMapUi) {
+    let HeightMapUi::ThermalErosion {
+        angle,
+        iterations,
+        strength,
+        timer,
+    } = height_map_ui
+    else {
+        unreachable!()
+    };
+    ui.horizontal(|ui| {
+        ui.label("Angle:");
+        if let Some(new_angle) =
+            draw_ui_editable_f32(Some(THERMAL_EROSION_ANGLE_RANGE), None, ui, *angle)
+        {
+            *angle = new_angle;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Iterations:");
+        if let Some(new_iterations) =
+            draw_ui_editable_u32(Some(THERMAL_EROSION_ITERATIONS_RANGE), ui, *iterations)
+        {
+            *iterations = new_iterations;
+            timer.unpause();
+            timer.reset();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Strength:");
+        if let Some(new_strength) = draw_ui_editable_f32(
+            Some(ZERO_TO_ONE),
+            Some(ZERO_TO_ONE_INCREMENT),
+            ui,
+            *strength,
+        ) {
+            *strength = new_strength;
+            timer.unpause();
+            timer.reset();
         }
     });
 }
 
 /// Draw the UI for the stack of layers in the project.
 pub fn draw_ui_for_layers(
+    biome_table: &biome::BiomeTable,
     commands: &mut Commands,
     theme_colors: &theme::ThemeColors,
     ui: &mut egui::Ui,
@@ -823,17 +3214,65 @@ pub fn draw_ui_for_layers(
 ) {
     egui::containers::ScrollArea::vertical().show(ui, |ui| {
         ui.heading("Layers");
-        if ui.button("New Layer").clicked() {
+        ui.menu_button("New Layer", |ui| {
             let top_layer_id: Option<LayerId> = layers_query
                 .layers
                 .iter()
                 .sort::<&layer::LayerOrder>()
                 .last()
                 .map(|l| l.layer.id());
-            commands.queue(undo::PushAction::from(layer::CreateLayerAction::new(
-                top_layer_id,
-            )));
-        }
+            if ui.button("Bitmap").clicked() {
+                commands.queue(undo::PushAction::from(
+                    layer::CreateLayerAction::with_height_map(
+                        top_layer_id,
+                        layer::HeightMap::bitmap(),
+                    ),
+                ));
+                ui.close();
+            }
+            if ui.button("Constant").clicked() {
+                commands.queue(undo::PushAction::from(layer::CreateLayerAction::new(
+                    top_layer_id,
+                )));
+                ui.close();
+            }
+            if ui.button("Hydraulic Erosion").clicked() {
+                commands.queue(undo::PushAction::from(
+                    layer::CreateLayerAction::with_height_map(
+                        top_layer_id,
+                        layer::HeightMap::hydraulic_erosion(),
+                    ),
+                ));
+                ui.close();
+            }
+            if ui.button("Noise").clicked() {
+                commands.queue(undo::PushAction::from(
+                    layer::CreateLayerAction::with_height_map(
+                        top_layer_id,
+                        layer::HeightMap::noise(),
+                    ),
+                ));
+                ui.close();
+            }
+            if ui.button("Summit").clicked() {
+                commands.queue(undo::PushAction::from(
+                    layer::CreateLayerAction::with_height_map(
+                        top_layer_id,
+                        layer::HeightMap::summit(),
+                    ),
+                ));
+                ui.close();
+            }
+            if ui.button("Thermal Erosion").clicked() {
+                commands.queue(undo::PushAction::from(
+                    layer::CreateLayerAction::with_height_map(
+                        top_layer_id,
+                        layer::HeightMap::thermal_erosion(),
+                    ),
+                ));
+                ui.close();
+            }
+        });
         {
             let layer_ids: Vec<LayerId> = layers_query
                 .iter()
@@ -851,6 +3290,7 @@ pub fn draw_ui_for_layers(
             {
                 let parent_layer_id = layer_ids.get(idx + 1).cloned();
                 draw_ui_for_layer(
+                    biome_table,
                     commands,
                     theme_colors,
                     ui,
@@ -864,6 +3304,7 @@ pub fn draw_ui_for_layers(
 }
 
 fn draw_ui_for_layer(
+    biome_table: &biome::BiomeTable,
     commands: &mut Commands,
     theme_colors: &theme::ThemeColors,
     ui: &mut egui::Ui,
@@ -900,26 +3341,50 @@ fn draw_ui_for_layer(
             }
             let actual_height: f32 = ui
                 .vertical_centered_justified(|ui| {
+                    draw_ui_for_layer_common_top(
+                        commands,
+                        ui,
+                        layer_query_item,
+                        masks_query,
+                        parent_layer_id,
+                    );
+                    ui.separator();
                     match *layer_query_item.height_map_ui {
+                        HeightMapUi::Bitmap { .. } => {
+                            draw_ui_for_bitmap_layer(commands, ui, layer_query_item);
+                        }
                         HeightMapUi::Constant { .. } => {
-                            draw_ui_for_layer_common_top(
-                                commands,
+                            draw_ui_for_constant_layer(ui, layer_query_item.height_map_ui.as_mut());
+                        }
+                        HeightMapUi::HydraulicErosion { .. } => {
+                            draw_ui_for_hydraulic_erosion_layer(
                                 ui,
-                                layer_query_item,
-                                masks_query,
-                                parent_layer_id,
+                                layer_query_item.height_map_ui.as_mut(),
                             );
-                            ui.separator();
-                            draw_ui_for_constant_layer(ui, layer_query_item.height_map_ui.as_mut());
-                            ui.separator();
-                            draw_ui_for_layer_common_bottom(
-                                commands,
-                                masks_query,
-                                layer_query_item,
+                        }
+                        HeightMapUi::Noise { .. } => {
+                            draw_ui_for_noise_layer(ui, layer_query_item.height_map_ui.as_mut());
+                        }
+                        HeightMapUi::Summit { .. } => {
+                            draw_ui_for_summit_layer(commands, ui, layer_query_item);
+                        }
+                        HeightMapUi::ThermalErosion { .. } => {
+                            draw_ui_for_thermal_erosion_layer(
                                 ui,
+                                layer_query_item.height_map_ui.as_mut(),
                             );
                         }
                     };
+                    ui.separator();
+                    draw_ui_for_layer_channels(commands, ui, layer_query_item);
+                    ui.separator();
+                    draw_ui_for_layer_common_bottom(
+                        biome_table,
+                        commands,
+                        masks_query,
+                        layer_query_item,
+                        ui,
+                    );
                 })
                 .response
                 .rect
@@ -932,6 +3397,7 @@ fn draw_ui_for_layer(
 }
 
 fn draw_ui_for_mask(
+    biome_table: &biome::BiomeTable,
     commands: &mut Commands,
     layer_id: LayerId,
     mask: &mut <MaskQuery as QueryData>::Item<'_, '_>,
@@ -950,6 +3416,12 @@ fn draw_ui_for_mask(
                     layer::UpdateMaskAction::toggle_enabled(mask.mask.id(), is_enabled),
                 ));
             }
+            let mut invert: bool = mask.mask.invert;
+            if ui.toggle_value(&mut invert, "Invert").changed() && invert != mask.mask.invert {
+                commands.queue(undo::PushAction::from(
+                    layer::UpdateMaskAction::toggle_invert(mask.mask.id(), invert),
+                ));
+            }
             if ui.button("Delete").clicked() {
                 let mask_bundle = layer::MaskBundle {
                     mask: mask.mask.clone(),
@@ -963,11 +3435,18 @@ fn draw_ui_for_mask(
             }
         });
 
+        let MaskUi {
+            ref mut curve,
+            ref mut strength,
+            ref mut timer,
+        } = *mask.mask_ui;
+        // Pick up the changes from undo/redo when there is no pending edit.
+        if timer.is_finished() {
+            *curve = mask.mask.curve.clone();
+            *strength = mask.mask.strength;
+        }
+
         ui.horizontal(|ui| {
-            let MaskUi {
-                ref mut strength,
-                ref mut timer,
-            } = *mask.mask_ui;
             ui.label("Strength:");
             if let Some(new_strength) = draw_ui_editable_f32(
                 Some(ZERO_TO_ONE),
@@ -982,9 +3461,159 @@ fn draw_ui_for_mask(
             }
         });
 
+        ui.horizontal(|ui| {
+            ui.label("Curve:");
+            if draw_ui_for_mask_curve(ui, curve) {
+                timer.unpause();
+                timer.reset();
+            }
+        });
+
         ui.separator();
 
         match *mask.mask_source_ui {
+            MaskSourceUi::Biome => {
+                let layer::MaskSource::Biome { biomes } = mask.mask_source else {
+                    unreachable!()
+                };
+                if biome_table.is_empty() {
+                    ui.label("There are no biomes in the project.");
+                }
+                ui.horizontal_wrapped(|ui| {
+                    let mut biomes_edited: Vec<BiomeId> = biomes.clone();
+                    for biome in biome_table.biomes().iter() {
+                        let mut is_selected: bool = biomes_edited.contains(&biome.id());
+                        if ui.toggle_value(&mut is_selected, &biome.name).changed() {
+                            if is_selected {
+                                biomes_edited.push(biome.id());
+                            } else {
+                                biomes_edited.retain(|id| *id != biome.id());
+                            }
+                        }
+                    }
+                    if biomes_edited != *biomes {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::update_biomes(
+                                mask.mask.id(),
+                                biomes.clone(),
+                                biomes_edited,
+                            ),
+                        ));
+                    }
+                });
+            }
+            MaskSourceUi::Bitmap {
+                ref mut black_point,
+                ref mut center,
+                ref mut out_of_bounds_value,
+                ref mut rotation,
+                ref mut size,
+                ref mut timer,
+                ref mut white_point,
+            } => {
+                let layer::MaskSource::Bitmap { image, .. } = mask.mask_source else {
+                    unreachable!()
+                };
+                ui.horizontal(|ui| {
+                    ui.label("Image:");
+                    if image.is_empty() {
+                        ui.label("(None)");
+                    } else {
+                        let UVec2 { x: w, y: h } = image.size();
+                        ui.label(format!(
+                            "{} ({}×{})",
+                            image.file_name().unwrap_or_default(),
+                            w,
+                            h
+                        ));
+                    }
+                    if ui.button("Load...").clicked() {
+                        commands.spawn((
+                            Name::new("Import Bitmap Dialog"),
+                            ImportBitmapFileDialog::new(ImportBitmapTarget::MaskSource(
+                                mask.mask.id(),
+                            )),
+                            DespawnOnExit(UiState::ShowingImportBitmapFileDialog),
+                        ));
+                        commands.set_state(UiState::ShowingImportBitmapFileDialog);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Center:");
+                    if let Some(new_x) = draw_ui_editable_f32(None, None, ui, center.x) {
+                        center.x = new_x;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                    if let Some(new_y) = draw_ui_editable_f32(None, None, ui, center.y) {
+                        center.y = new_y;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Size:");
+                    if let Some(new_size) =
+                        draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, *size)
+                    {
+                        *size = new_size;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Rotation:");
+                    if let Some(new_rotation) = draw_ui_editable_f32(
+                        Some(ZERO_TO_ONE),
+                        Some(ZERO_TO_ONE_INCREMENT),
+                        ui,
+                        *rotation,
+                    ) {
+                        *rotation = new_rotation;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Black Point:");
+                    if let Some(new_black_point) = draw_ui_editable_f32(
+                        Some(ZERO_TO_ONE),
+                        Some(ZERO_TO_ONE_INCREMENT),
+                        ui,
+                        *black_point,
+                    ) {
+                        *black_point = new_black_point;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("White Point:");
+                    if let Some(new_white_point) = draw_ui_editable_f32(
+                        Some(ZERO_TO_ONE),
+                        Some(ZERO_TO_ONE_INCREMENT),
+                        ui,
+                        *white_point,
+                    ) {
+                        *white_point = new_white_point;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Out of Bounds:");
+                    if let Some(new_out_of_bounds_value) = draw_ui_editable_f32(
+                        Some(ZERO_TO_ONE),
+                        Some(ZERO_TO_ONE_INCREMENT),
+                        ui,
+                        *out_of_bounds_value,
+                    ) {
+                        *out_of_bounds_value = new_out_of_bounds_value;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+            }
             MaskSourceUi::Circle {
                 ref mut center,
                 ref mut falloff_radius,
@@ -1070,6 +3699,238 @@ fn draw_ui_for_mask(
                     }
                 });
             }
+            MaskSourceUi::Gradient {
+                ref mut curve,
+                ref mut end,
+                ref mut smoothness,
+                ref mut start,
+                ref mut timer,
+            } => {
+                let layer::MaskSource::Gradient { shape, .. } = mask.mask_source else {
+                    unreachable!()
+                };
+                ui.horizontal(|ui| {
+                    ui.label("Shape:");
+                    let mut shape_edited = *shape;
+                    for item in layer::GradientShape::ITEMS.into_iter() {
+                        ui.selectable_value(&mut shape_edited, item, item.to_string());
+                    }
+                    if shape_edited != *shape {
+                        commands.queue(undo::PushAction::from(
+                            layer::UpdateMaskSourceAction::change_shape(
+                                mask.mask.id(),
+                                *shape,
+                                shape_edited,
+                            ),
+                        ));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Start:");
+                    if let Some(new_x) = draw_ui_editable_f32(None, None, ui, start.x) {
+                        start.x = new_x;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                    if let Some(new_y) = draw_ui_editable_f32(None, None, ui, start.y) {
+                        start.y = new_y;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("End:");
+                    if let Some(new_x) = draw_ui_editable_f32(None, None, ui, end.x) {
+                        end.x = new_x;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                    if let Some(new_y) = draw_ui_editable_f32(None, None, ui, end.y) {
+                        end.y = new_y;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Smoothness:");
+                    if let Some(new_smoothness) = draw_ui_editable_f32(
+                        Some(ZERO_TO_ONE),
+                        Some(ZERO_TO_ONE_INCREMENT),
+                        ui,
+                        *smoothness,
+                    ) {
+                        *smoothness = new_smoothness;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Ramp:");
+                    if draw_ui_for_mask_curve(ui, curve) {
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+            }
+            MaskSourceUi::Height {
+                ref mut falloff,
+                ref mut max,
+                ref mut min,
+                ref mut timer,
+            } => {
+                ui.horizontal(|ui| {
+                    ui.label("Min:");
+                    if let Some(new_min) =
+                        draw_ui_editable_f32(Some(layer::HEIGHT_RANGE), None, ui, *min)
+                    {
+                        *min = new_min;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Max:");
+                    if let Some(new_max) =
+                        draw_ui_editable_f32(Some(layer::HEIGHT_RANGE), None, ui, *max)
+                    {
+                        *max = new_max;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Falloff:");
+                    if let Some(new_falloff) =
+                        draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, *falloff)
+                    {
+                        *falloff = new_falloff;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+            }
+            MaskSourceUi::Noise {
+                ref mut contrast,
+                ref mut octaves,
+                ref mut scale,
+                ref mut seed,
+                ref mut threshold,
+                ref mut timer,
+            } => {
+                ui.horizontal(|ui| {
+                    ui.label("Scale:");
+                    if let Some(new_scale) =
+                        draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, *scale)
+                    {
+                        *scale = new_scale;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Threshold:");
+                    if let Some(new_threshold) = draw_ui_editable_f32(
+                        Some(ZERO_TO_ONE),
+                        Some(ZERO_TO_ONE_INCREMENT),
+                        ui,
+                        *threshold,
+                    ) {
+                        *threshold = new_threshold;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Contrast:");
+                    if let Some(new_contrast) =
+                        draw_ui_editable_f32(Some(ZERO_TO_POSITIVE_INFINITY), None, ui, *contrast)
+                    {
+                        *contrast = new_contrast;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Octaves:");
+                    if let Some(new_octaves) =
+                        draw_ui_editable_u32(Some(NOISE_OCTAVES_RANGE), ui, (*octaves).into())
+                    {
+                        *octaves = new_octaves.try_into().unwrap();
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Seed:");
+                    if let Some(new_seed) = draw_ui_editable_u32(None, ui, *seed) {
+                        *seed = new_seed;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+            }
+            MaskSourceUi::Polygon {
+                ref mut smoothness,
+                ref mut timer,
+                ref mut vertices,
+            }
+            | MaskSourceUi::Spline {
+                ref mut smoothness,
+                ref mut timer,
+                ref mut vertices,
+            } => {
+                ui.horizontal(|ui| {
+                    ui.label("Smoothness:");
+                    if let Some(new_smoothness) = draw_ui_editable_f32(
+                        Some(ZERO_TO_ONE),
+                        Some(ZERO_TO_ONE_INCREMENT),
+                        ui,
+                        *smoothness,
+                    ) {
+                        *smoothness = new_smoothness;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                draw_ui_for_mask_vertices(commands, mask.mask.id(), ui, vertices, timer);
+            }
+            MaskSourceUi::Slope {
+                ref mut falloff,
+                ref mut max_angle,
+                ref mut min_angle,
+                ref mut timer,
+            } => {
+                ui.horizontal(|ui| {
+                    ui.label("Min Angle:");
+                    if let Some(new_min_angle) =
+                        draw_ui_editable_f32(Some(SLOPE_ANGLE_RANGE), None, ui, *min_angle)
+                    {
+                        *min_angle = new_min_angle;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Max Angle:");
+                    if let Some(new_max_angle) =
+                        draw_ui_editable_f32(Some(SLOPE_ANGLE_RANGE), None, ui, *max_angle)
+                    {
+                        *max_angle = new_max_angle;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Falloff:");
+                    if let Some(new_falloff) =
+                        draw_ui_editable_f32(Some(SLOPE_ANGLE_RANGE), None, ui, *falloff)
+                    {
+                        *falloff = new_falloff;
+                        timer.unpause();
+                        timer.reset();
+                    }
+                });
+            }
             MaskSourceUi::Square {
                 ref mut center,
                 ref mut falloff_radius,
@@ -1159,7 +4020,7 @@ fn draw_ui_for_mask(
 
         if previous_mask_id.is_some() {
             ui.separator();
-            ui.horizontal(|ui| {
+            ui.horizontal_wrapped(|ui| {
                 ui.label("Composition Mode:");
 
                 let mut mode_edited = mask.mask.composition_mode;
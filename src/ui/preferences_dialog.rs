@@ -21,7 +21,7 @@ use crate::constants;
 use crate::preferences::{Preferences, UpdatePreferences};
 use crate::theme::ThemeColors;
 
-use super::egui_ext::ToColor32;
+use super::egui_ext::{draw_ui_modal_overlay, ToColor32};
 
 // COMPONENTS
 
@@ -33,25 +33,7 @@ pub struct PreferencesDialog {
 
 impl PreferencesDialog {
     pub fn show(&mut self, ctx: &mut egui::Context, theme_colors: &ThemeColors) -> DialogState {
-        // Basically copied from egui-file-dialog.
-        // See file_dialog::FileDialog::ui_update_modal_background.
-        let modal_overlay_response = egui::Area::new("Modal Overlay".into())
-            .interactable(true)
-            .fixed_pos(egui::Pos2::ZERO)
-            .show(ctx, |ui| {
-                let content_rect = ctx.input(egui::InputState::content_rect);
-
-                ui.allocate_response(content_rect.size(), egui::Sense::click());
-
-                // TODO: Get the color from theme
-                ui.painter().rect_filled(
-                    content_rect,
-                    egui::CornerRadius::ZERO,
-                    theme_colors.bg_color.with_alpha(0.85).to_color32(),
-                );
-            })
-            .response;
-        ctx.move_to_top(modal_overlay_response.layer_id);
+        draw_ui_modal_overlay(ctx, theme_colors.bg_color.with_alpha(0.85).to_color32());
 
         egui::Window::new("Preferences")
             .show(ctx, |ui| {
@@ -64,6 +46,17 @@ impl PreferencesDialog {
                     );
                 });
 
+                ui.horizontal(|ui| {
+                    ui.label("Autosave interval");
+                    ui.add(
+                        egui::widgets::DragValue::new(&mut self.preferences.autosave_interval)
+                            .fixed_decimals(0)
+                            .range(constants::AUTOSAVE_INTERVAL_RANGE)
+                            .suffix(" s"),
+                    )
+                    .on_hover_text("Set to 0 to disable autosave.");
+                });
+
                 let mut response = DialogState::Open;
                 if ui.button("Save and Close").clicked() {
                     response = DialogState::Confirmed;
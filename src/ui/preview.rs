@@ -24,6 +24,7 @@ use crate::preview;
 
 #[derive(SystemParam)]
 pub struct PreviewQuery<'w, 's> {
+    preview_overlay: ResMut<'w, preview::PreviewOverlay>,
     preview_regions: Query<'w, 's, (Entity, &'static preview::PreviewRegion)>,
     update_preview_region_events: MessageWriter<'w, preview::UpdatePreviewRegion>,
 }
@@ -84,6 +85,18 @@ pub fn draw_ui_for_preview(ui: &mut egui::Ui, mut preview_query: PreviewQuery) {
                 );
             }
         });
+
+        ui.horizontal(|ui| {
+            let mut show_biomes: bool =
+                *preview_query.preview_overlay == preview::PreviewOverlay::Biomes;
+            if ui.checkbox(&mut show_biomes, "Show biomes").changed() {
+                *preview_query.preview_overlay = if show_biomes {
+                    preview::PreviewOverlay::Biomes
+                } else {
+                    preview::PreviewOverlay::None
+                };
+            }
+        });
     } else {
         panic!("There are multiple preview regions, or none.");
     }
@@ -0,0 +1,110 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::session::PendingRecovery;
+use crate::theme::ThemeColors;
+
+use super::egui_ext::{draw_ui_modal_overlay, ToColor32};
+
+// COMPONENTS
+
+/// Offers to restore the autosaves of the sessions that did not exit cleanly.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct RecoveryDialog {
+    /// Sources of the pending recoveries, in the same order.
+    sources: Vec<Option<PathBuf>>,
+}
+
+impl RecoveryDialog {
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    pub fn show(&mut self, ctx: &mut egui::Context, theme_colors: &ThemeColors) -> DialogState {
+        draw_ui_modal_overlay(ctx, theme_colors.bg_color.with_alpha(0.85).to_color32());
+
+        egui::Window::new("Recover Unsaved Changes")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("The editor did not exit cleanly, but the changes were autosaved.");
+
+                let mut response = DialogState::Open;
+                egui::Grid::new("recovery-dialog-recoveries").show(ui, |ui| {
+                    for (idx, source) in self.sources.iter().enumerate() {
+                        ui.label(format!(
+                            "Project: {}",
+                            source
+                                .as_ref()
+                                .map(|p| p.to_string_lossy())
+                                .unwrap_or("(Unsaved)".into())
+                        ));
+                        if ui.button("Restore").clicked() {
+                            response = DialogState::Restore(idx);
+                        }
+                        if ui.button("Discard").clicked() {
+                            response = DialogState::Discard(idx);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if ui.button("Later").clicked() {
+                    response = DialogState::Later;
+                }
+                if let DialogState::Discard(idx) = response {
+                    self.sources.remove(idx);
+                }
+                response
+            })
+            .map(|r| {
+                ctx.move_to_top(r.response.layer_id);
+                r.inner
+            })
+            .flatten()
+            .unwrap_or(DialogState::Open)
+    }
+}
+
+impl FromWorld for RecoveryDialog {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            sources: world
+                .resource::<PendingRecovery>()
+                .0
+                .iter()
+                .map(|recovery| recovery.source.clone())
+                .collect(),
+        }
+    }
+}
+
+// LIB
+
+pub enum DialogState {
+    Open,
+    /// Restore the recovery at the index, postponing the others.
+    Restore(usize),
+    /// Discard the recovery at the index.
+    Discard(usize),
+    /// Keep the recoveries to be offered on the next start.
+    Later,
+}
@@ -0,0 +1,99 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use bevy_egui::egui;
+
+use crate::math::Sample;
+use crate::preview;
+
+// LIB
+
+/// Draw the terrain under the cursor, on a single line.
+pub fn draw_ui_for_status_bar(ui: &mut egui::Ui, terrain_info: &preview::TerrainInfo) {
+    ui.horizontal(|ui| match &terrain_info.0 {
+        Some(terrain_point) => {
+            ui.label(format!(
+                "X: {:.2}  Y: {:.2}",
+                terrain_point.position.x, terrain_point.position.y
+            ));
+            ui.separator();
+            ui.label(format!("Height: {:.2}", terrain_point.sample.height()));
+            ui.separator();
+            ui.label(format!("Slope: {:.1}°", terrain_point.slope));
+            ui.separator();
+            ui.label(match terrain_point.aspect {
+                Some(aspect) => format!("Aspect: {:.0}°", aspect),
+                None => "Aspect: Flat".to_owned(),
+            });
+            ui.separator();
+            ui.label(format!(
+                "Biome: {}",
+                terrain_point.biome.as_deref().unwrap_or("Unclassified")
+            ));
+            for (channel, value) in terrain_point.sample.channels() {
+                ui.separator();
+                ui.label(format!("{}: {:.2}", channel.to_string(), value));
+            }
+        }
+        None => {
+            ui.weak("Move the cursor over the terrain to inspect it.");
+        }
+    });
+}
+
+/// Draw the samples of the individual layers under the cursor.
+pub fn draw_ui_for_terrain_info(ui: &mut egui::Ui, terrain_info: &preview::TerrainInfo) {
+    ui.heading("Terrain Info");
+    let Some(terrain_point) = &terrain_info.0 else {
+        ui.weak("Move the cursor over the terrain to inspect it.");
+        return;
+    };
+    egui::Grid::new("terrain-info-layers")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Layer");
+            ui.strong("Height");
+            ui.strong("Alpha");
+            ui.end_row();
+            for preview::LayerInfo {
+                approximate,
+                name,
+                sample,
+            } in terrain_point.layers.iter()
+            {
+                ui.label(name);
+                match sample {
+                    Some(sample) if *approximate => {
+                        ui.weak(format!("≈{:.2}", sample.height()))
+                            .on_hover_text("Filters below this layer are not included.");
+                        ui.weak(format!("{:.2}", sample.alpha().factor()));
+                    }
+                    Some(sample) => draw_ui_for_sample(ui, sample),
+                    None => {
+                        ui.weak("Filter");
+                        ui.weak("-");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+}
+
+fn draw_ui_for_sample(ui: &mut egui::Ui, sample: &Sample) {
+    ui.label(format!("{:.2}", sample.height()));
+    ui.label(format!("{:.2}", sample.alpha().factor()));
+}
@@ -0,0 +1,111 @@
+// Copyright © 2024-2026 Atamert Ölçgen.
+// This file is part of Yer.
+//
+// Yer is free software: you can redistribute it and/or modify it under the
+// terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// Yer is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with Yer.  If not, see <https://www.gnu.org/licenses/>.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::session::Session;
+use crate::theme::ThemeColors;
+
+use super::egui_ext::{draw_ui_modal_overlay, ToColor32};
+
+// COMPONENTS
+
+/// Asks to save the unsaved changes before an action that would lose them.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct UnsavedChangesDialog {
+    action: GuardedAction,
+    project_name: String,
+}
+
+impl UnsavedChangesDialog {
+    pub fn new(action: GuardedAction, session: &Session) -> Self {
+        Self {
+            action,
+            project_name: session
+                .get_file_path()
+                .and_then(|p| p.file_name().map(|f| f.to_string_lossy().into_owned()))
+                .unwrap_or("Untitled".to_owned()),
+        }
+    }
+
+    pub fn action(&self) -> GuardedAction {
+        self.action
+    }
+
+    pub fn show(&mut self, ctx: &mut egui::Context, theme_colors: &ThemeColors) -> DialogState {
+        draw_ui_modal_overlay(ctx, theme_colors.bg_color.with_alpha(0.85).to_color32());
+
+        egui::Window::new("Unsaved Changes")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Save the changes to '{}' before {}?",
+                    self.project_name,
+                    self.action.description()
+                ));
+
+                let mut response = DialogState::Open;
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        response = DialogState::Save;
+                    }
+                    if ui.button("Discard").clicked() {
+                        response = DialogState::Discard;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        response = DialogState::Cancelled;
+                    }
+                });
+                response
+            })
+            .map(|r| {
+                ctx.move_to_top(r.response.layer_id);
+                r.inner
+            })
+            .flatten()
+            .unwrap_or(DialogState::Open)
+    }
+}
+
+// LIB
+
+pub enum DialogState {
+    Open,
+    Save,
+    Discard,
+    Cancelled,
+}
+
+/// An action that discards the current session.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Reflect)]
+pub enum GuardedAction {
+    New,
+    Open,
+    Quit,
+}
+
+impl GuardedAction {
+    fn description(&self) -> &'static str {
+        match self {
+            Self::New => "creating a new project",
+            Self::Open => "opening another project",
+            Self::Quit => "quitting",
+        }
+    }
+}
@@ -18,13 +18,16 @@ use core::f32::consts::FRAC_PI_2;
 
 use bevy::math::Affine3A;
 use bevy::pbr::wireframe::{Wireframe, WireframeColor, WireframePlugin};
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, MeshRayCastSettings};
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
 
 use crate::constants::{
     PREVIEW_DEFAULT_FACE_ALPHA, PREVIEW_DEFAULT_FACE_COLOR, PREVIEW_DEFAULT_WIREFRAME_COLOR,
     VIEWPORT_CAMERA_INITIAL_TARGET, VIEWPORT_CAMERA_INITIAL_TRANSLATION,
     VIEWPORT_LIGHT_LOOK_AT_TARGET, VIEWPORT_LIGHT_POSITION,
 };
+use crate::preview;
 use crate::theme;
 
 const LINES_PLUS: [(Vec2, Vec2); 2] = [
@@ -51,6 +54,8 @@ impl Plugin for ViewportPlugin {
         if !app.is_plugin_added::<WireframePlugin>() {
             app.add_plugins(WireframePlugin::default());
         }
+        app.register_type::<TerrainCursor>()
+            .init_resource::<TerrainCursor>();
         app.add_systems(
             Startup,
             (
@@ -66,12 +71,22 @@ impl Plugin for ViewportPlugin {
                 draw_focal_point_system,
                 keyboard_actions_system,
                 update_camera_system,
+                update_terrain_cursor_system,
                 update_viewport_colors_system,
             ),
         );
     }
 }
 
+// RESOURCES
+
+/// Position on the preview mesh under the cursor, in terrain coordinates.
+///
+/// This is `None` when the cursor is not over the preview mesh.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect, Resource)]
+#[reflect(Resource)]
+pub struct TerrainCursor(pub Option<Vec2>);
+
 // COMPONENTS
 
 #[derive(Component, Reflect)]
@@ -587,9 +602,46 @@ fn update_camera_system(
     );
 }
 
-/// Update preview mesh colors and clear color when the theme changes.
+fn update_terrain_cursor_system(
+    camera_query: Single<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut mesh_ray_cast: MeshRayCast,
+    preview_mesh_query: Single<(Entity, &GlobalTransform), With<PreviewMesh>>,
+    mut terrain_cursor: ResMut<TerrainCursor>,
+    viewport_focus: Single<&ViewportFocus>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    let (camera, camera_transform) = *camera_query;
+    let (preview_mesh_entity, preview_mesh_transform) = *preview_mesh_query;
+    let position: Option<Vec2> = if ***viewport_focus {
+        window.cursor_position().and_then(|cursor_position| {
+            let ray = camera
+                .viewport_to_world(camera_transform, cursor_position)
+                .ok()?;
+            let filter = |entity: Entity| entity == preview_mesh_entity;
+            let settings = MeshRayCastSettings::default()
+                .with_filter(&filter)
+                .always_early_exit();
+            let (_, hit) = mesh_ray_cast.cast_ray(ray, &settings).first()?;
+            // Preview mesh is Z-up, its XY is the terrain position.
+            Some(
+                preview_mesh_transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(hit.point)
+                    .truncate(),
+            )
+        })
+    } else {
+        None
+    };
+    terrain_cursor.set_if_neq(TerrainCursor(position));
+}
+
+/// Update preview mesh colors and clear color when the theme or the preview
+/// overlay changes.
 fn update_viewport_colors_system(
     mut clear_color: ResMut<ClearColor>,
+    preview_overlay: Res<preview::PreviewOverlay>,
     mut preview_mesh: Single<
         (&mut MeshMaterial3d<StandardMaterial>, &mut WireframeColor),
         With<PreviewMesh>,
@@ -598,7 +650,7 @@ fn update_viewport_colors_system(
     theme: Res<theme::Theme>,
     theme_colors: Res<Assets<theme::ThemeColors>>,
 ) {
-    if !theme.is_changed() {
+    if !theme.is_changed() && !preview_overlay.is_changed() {
         return;
     }
 
@@ -608,8 +660,12 @@ fn update_viewport_colors_system(
         standard_materials.get_mut(&preview_mesh.0 .0),
     ) {
         (Some(colors), Some(standard_material)) => {
-            standard_material.base_color =
-                colors.primary_color.with_alpha(PREVIEW_DEFAULT_FACE_ALPHA);
+            // Overlay colors are multiplied with the base color.
+            standard_material.base_color = match *preview_overlay {
+                preview::PreviewOverlay::None => colors.primary_color,
+                preview::PreviewOverlay::Biomes => Color::WHITE,
+            }
+            .with_alpha(PREVIEW_DEFAULT_FACE_ALPHA);
             preview_mesh.1.color = colors.primary_color;
             clear_color.0 = colors.bg_color;
         }